@@ -0,0 +1,948 @@
+use anyhow::{Context, Result};
+use clap::{ArgMatches, Args};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::config::WeaverConfig;
+use crate::models::{
+    AnchorRange, BundleFormat, ChangeStatus, ConflictPolicy, ContextItem, IncludeType, ItemChange,
+    NarrativeData, NarrativeFilter, NarrativeSort, OutputOptions, Problem, ResolveFormat,
+    RevisionSummary, SourceLabel, ValidationIssue,
+};
+use crate::services::bundle::{self, ImportAction, ImportOptions};
+use crate::services::{anchor, render, validation, FileScanner, NarrativeStore};
+
+/// `create` / `edit` で追加するアイテム
+///
+/// 種類の違うフラグを混ぜて指定しても、コマンドラインに書いた順に並べる。
+#[derive(Args, Debug, Default)]
+pub struct ItemArgs {
+    /// Include a whole file
+    #[arg(long = "add", value_name = "PATH")]
+    pub add: Vec<String>,
+
+    /// Include one section of a markdown file
+    #[arg(long = "add-section", value_name = "PATH:SECTION")]
+    pub add_section: Vec<String>,
+
+    /// Include a range of lines (1-based, inclusive)
+    #[arg(long = "add-lines", value_name = "PATH:START-END")]
+    pub add_lines: Vec<String>,
+
+    /// Include the lines between `<!-- weave:NAME-start -->` and `<!-- weave:NAME-end -->`
+    #[arg(long = "add-anchor", value_name = "PATH:NAME")]
+    pub add_anchor: Vec<String>,
+
+    /// Include a literal text block
+    #[arg(long = "add-text", value_name = "TEXT")]
+    pub add_text: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// Only list templates
+    #[arg(long)]
+    pub templates: bool,
+
+    /// Only list narratives with this tag (repeat to require several)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Only list narratives in this folder or its subfolders
+    #[arg(long)]
+    pub folder: Option<String>,
+
+    /// Search names, descriptions, tags and folders
+    #[arg(short, long, value_name = "TEXT")]
+    pub search: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = NarrativeSort::Updated)]
+    pub sort: NarrativeSort,
+
+    /// Print as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    #[arg(short = 'P', long, default_value = ".")]
+    pub path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct CreateArgs {
+    /// Name of the new narrative
+    #[arg(short, long)]
+    pub name: String,
+
+    #[arg(short, long)]
+    pub description: Option<String>,
+
+    /// Mark the narrative as a template
+    #[arg(long)]
+    pub template: bool,
+
+    /// Tag the narrative (repeatable)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Folder to group the narrative under, e.g. `part1/battles`
+    #[arg(long)]
+    pub folder: Option<String>,
+
+    #[command(flatten)]
+    pub items: ItemArgs,
+
+    /// Separator between items (accepts \n and \t escapes)
+    #[arg(long)]
+    pub separator: Option<String>,
+
+    /// Prefix each item with its source path
+    #[arg(long, value_enum)]
+    pub source_label: Option<SourceLabel>,
+
+    #[arg(short = 'P', long, default_value = ".")]
+    pub path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct EditArgs {
+    /// Narrative ID, name or unique prefix
+    pub narrative: String,
+
+    /// New name
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// New description (an empty string clears it)
+    #[arg(short, long)]
+    pub description: Option<String>,
+
+    /// Mark the narrative as a template
+    #[arg(long, conflicts_with = "no_template")]
+    pub template: bool,
+
+    /// Unmark the narrative as a template
+    #[arg(long)]
+    pub no_template: bool,
+
+    /// Add a tag (repeatable)
+    #[arg(long, value_name = "TAG")]
+    pub add_tag: Vec<String>,
+
+    /// Remove a tag (repeatable)
+    #[arg(long, value_name = "TAG")]
+    pub remove_tag: Vec<String>,
+
+    /// Move to this folder (an empty string moves it out of any folder)
+    #[arg(long)]
+    pub folder: Option<String>,
+
+    /// Remove all items before adding new ones
+    #[arg(long)]
+    pub clear: bool,
+
+    /// Remove the item at this position (1-based, as printed by `weaver show`)
+    #[arg(long, value_name = "POSITION")]
+    pub remove: Vec<usize>,
+
+    /// Items appended after the existing ones
+    #[command(flatten)]
+    pub items: ItemArgs,
+
+    /// Separator between items (accepts \n and \t escapes)
+    #[arg(long)]
+    pub separator: Option<String>,
+
+    /// Prefix each item with its source path
+    #[arg(long, value_enum)]
+    pub source_label: Option<SourceLabel>,
+
+    /// Message recorded with the revision
+    #[arg(short, long)]
+    pub message: Option<String>,
+
+    #[arg(short = 'P', long, default_value = ".")]
+    pub path: PathBuf,
+}
+
+pub fn resolve(
+    query: &str,
+    path: PathBuf,
+    separator: Option<String>,
+    source_label: Option<SourceLabel>,
+    format: ResolveFormat,
+    record: bool,
+) -> Result<()> {
+    let scanner = FileScanner::new(path.clone());
+    scanner.scan()?;
+
+    let store = open_store(&path)?;
+    let narrative = store.find(query)?;
+
+    let mut options = narrative.output.clone();
+    if let Some(separator) = separator {
+        options.separator = unescape(&separator);
+    }
+    if let Some(source_label) = source_label {
+        options.source_label = source_label;
+    }
+
+    let items = store.resolve(&scanner, &narrative, record)?;
+    let content = render::render(&narrative, &items, format, &options)?;
+    print!("{content}");
+    Ok(())
+}
+
+/// 前回 `resolve` したときからの変化を表示し、変化があったかを返す
+pub fn diff(query: &str, stat: bool, json: bool, path: PathBuf) -> Result<bool> {
+    let scanner = FileScanner::new(path.clone());
+    scanner.scan()?;
+
+    let store = open_store(&path)?;
+    let narrative = store.find(query)?;
+    let diff = store.resolve_diff(&scanner, &narrative)?.with_context(|| {
+        format!(
+            "'{}' has not been resolved yet; run `weaver resolve {}` first",
+            narrative.name, narrative.name
+        )
+    })?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(diff.changed);
+    }
+
+    let recorded_at = diff.recorded_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+    let revs = if diff.recorded_rev == diff.current_rev {
+        format!("rev {}", diff.current_rev)
+    } else {
+        format!("rev {} → {}", diff.recorded_rev, diff.current_rev)
+    };
+    if !diff.changed {
+        println!("{}: no changes since the resolve at {} ({})", diff.name, recorded_at, revs);
+        return Ok(false);
+    }
+
+    let changes: Vec<&ItemChange> = diff
+        .items
+        .iter()
+        .filter(|item| item.status != ChangeStatus::Unchanged)
+        .collect();
+    println!(
+        "{}: {} of {} item(s) changed since the resolve at {} ({})",
+        diff.name,
+        changes.len(),
+        diff.items.len(),
+        recorded_at,
+        revs
+    );
+    for item in changes {
+        let (mark, chars) = match (item.status, item.before_chars, item.after_chars) {
+            (ChangeStatus::Modified, Some(before), Some(after)) => {
+                ("M", format!(" ({before} → {after} chars)"))
+            }
+            (ChangeStatus::Added, _, Some(after)) => ("A", format!(" ({after} chars)")),
+            (ChangeStatus::Removed, Some(before), _) => ("D", format!(" ({before} chars)")),
+            _ => ("?", String::new()),
+        };
+        println!("  {} {}{}", mark, item.label, chars);
+    }
+
+    if !stat {
+        println!();
+        print!("{}", diff.diff);
+    }
+    Ok(true)
+}
+
+pub fn list(args: ListArgs) -> Result<()> {
+    let store = open_store(&args.path)?;
+
+    let narratives = store.search(&NarrativeFilter {
+        query: args.search,
+        tags: args.tags,
+        folder: args.folder,
+        template: args.templates.then_some(true),
+        sort: args.sort,
+    });
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&narratives)?);
+        return Ok(());
+    }
+
+    // フォルダーを使っていれば、フォルダーごとに見出しを付けて並べる（フォルダーなしは先頭）
+    if narratives.iter().any(|n| n.folder.is_some()) {
+        let mut groups: BTreeMap<Option<&str>, Vec<&NarrativeData>> = BTreeMap::new();
+        for narrative in &narratives {
+            groups.entry(narrative.folder.as_deref()).or_default().push(narrative);
+        }
+        for (index, (folder, group)) in groups.iter().enumerate() {
+            if index > 0 {
+                println!();
+            }
+            match folder {
+                Some(folder) => println!("{folder}/"),
+                None => println!("(no folder)"),
+            }
+            for narrative in group {
+                println!("  {}", list_line(narrative));
+            }
+        }
+    } else {
+        for narrative in &narratives {
+            println!("{}", list_line(narrative));
+        }
+    }
+
+    Ok(())
+}
+
+fn list_line(narrative: &NarrativeData) -> String {
+    let template = if narrative.is_template { " [template]" } else { "" };
+    let tags: String = narrative.tags.iter().map(|tag| format!(" #{tag}")).collect();
+    format!(
+        "{}  {}  {:>3} items  {}{}{}",
+        &narrative.id.to_string()[..8],
+        narrative.updated_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+        narrative.contexts.len(),
+        narrative.name,
+        template,
+        tags
+    )
+}
+
+pub fn show(query: &str, path: PathBuf, json: bool) -> Result<()> {
+    let store = open_store(&path)?;
+    let narrative = store.find(query)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&narrative)?);
+        return Ok(());
+    }
+
+    println!("{}", narrative.name);
+    println!("  id:       {}", narrative.id);
+    if let Some(description) = &narrative.description {
+        println!("  description: {description}");
+    }
+    if narrative.is_template {
+        println!("  template: yes");
+    }
+    if let Some(folder) = &narrative.folder {
+        println!("  folder:   {folder}");
+    }
+    if !narrative.tags.is_empty() {
+        println!("  tags:     {}", narrative.tags.join(", "));
+    }
+    println!(
+        "  updated:  {}",
+        narrative.updated_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S")
+    );
+    println!();
+
+    for (index, item) in sorted_items(&narrative).iter().enumerate() {
+        println!("{:>3}. {}", index + 1, item.include_type.describe(&item.path));
+    }
+
+    Ok(())
+}
+
+pub fn create(args: CreateArgs, matches: &ArgMatches) -> Result<()> {
+    let store = open_store(&args.path)?;
+    warn_duplicate_name(&store, &args.name, None);
+
+    let contexts = numbered(args.items.into_items(matches)?);
+    warn_missing_files(&args.path, &contexts);
+
+    let mut output = OutputOptions::default();
+    if let Some(separator) = args.separator {
+        output.separator = unescape(&separator);
+    }
+    if let Some(source_label) = args.source_label {
+        output.source_label = source_label;
+    }
+
+    let now = chrono::Utc::now();
+    let narrative = store.create(NarrativeData {
+        id: Uuid::nil(),
+        name: args.name,
+        description: args.description.filter(|d| !d.is_empty()),
+        is_template: args.template,
+        tags: args.tags,
+        folder: args.folder,
+        contexts,
+        output,
+        created_at: now,
+        updated_at: now,
+    })?;
+
+    println!("Created '{}' ({})", narrative.name, narrative.id);
+    Ok(())
+}
+
+pub fn edit(args: EditArgs, matches: &ArgMatches) -> Result<()> {
+    let store = open_store(&args.path)?;
+    let mut narrative = store.find(&args.narrative)?;
+
+    if let Some(name) = args.name {
+        warn_duplicate_name(&store, &name, Some(narrative.id));
+        narrative.name = name;
+    }
+    if let Some(description) = args.description {
+        narrative.description = Some(description).filter(|d| !d.is_empty());
+    }
+    if args.template {
+        narrative.is_template = true;
+    }
+    if args.no_template {
+        narrative.is_template = false;
+    }
+    narrative.tags.retain(|tag| !args.remove_tag.contains(tag));
+    narrative.tags.extend(args.add_tag);
+    if let Some(folder) = args.folder {
+        narrative.folder = Some(folder);
+    }
+
+    let mut contexts = sorted_items(&narrative);
+    if args.clear {
+        contexts.clear();
+    } else if !args.remove.is_empty() {
+        for position in &args.remove {
+            if *position == 0 || *position > contexts.len() {
+                anyhow::bail!(
+                    "No item at position {} ('{}' has {} items)",
+                    position,
+                    narrative.name,
+                    contexts.len()
+                );
+            }
+        }
+        contexts = contexts
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !args.remove.contains(&(index + 1)))
+            .map(|(_, item)| item)
+            .collect();
+    }
+
+    let added = args.items.into_items(matches)?;
+    warn_missing_files(&args.path, &added);
+    contexts.extend(added);
+    narrative.contexts = numbered(contexts);
+
+    if let Some(separator) = args.separator {
+        narrative.output.separator = unescape(&separator);
+    }
+    if let Some(source_label) = args.source_label {
+        narrative.output.source_label = source_label;
+    }
+
+    let id = narrative.id;
+    let narrative = store.update(&id, narrative, args.message)?;
+    println!("Updated '{}' ({} items)", narrative.name, narrative.contexts.len());
+    Ok(())
+}
+
+pub fn remove(queries: &[String], path: PathBuf) -> Result<()> {
+    let store = open_store(&path)?;
+
+    // 途中で見つからないものがあっても一部だけ消えないよう、先にすべて解決する
+    let narratives = queries
+        .iter()
+        .map(|query| store.find(query))
+        .collect::<Result<Vec<_>>>()?;
+
+    for narrative in narratives {
+        store.delete(&narrative.id)?;
+        println!("Deleted '{}' ({})", narrative.name, narrative.id);
+    }
+
+    Ok(())
+}
+
+pub fn rename(query: &str, name: String, path: PathBuf) -> Result<()> {
+    let store = open_store(&path)?;
+    let mut narrative = store.find(query)?;
+    warn_duplicate_name(&store, &name, Some(narrative.id));
+
+    let old_name = std::mem::replace(&mut narrative.name, name);
+    let message = format!("Rename from '{old_name}'");
+    let id = narrative.id;
+    let narrative = store.update(&id, narrative, Some(message))?;
+
+    println!("Renamed '{}' to '{}'", old_name, narrative.name);
+    Ok(())
+}
+
+pub fn copy(query: &str, name: Option<String>, path: PathBuf) -> Result<()> {
+    let store = open_store(&path)?;
+    let source = store.find(query)?;
+
+    let name = name.unwrap_or_else(|| format!("{} (copy)", source.name));
+    warn_duplicate_name(&store, &name, None);
+
+    let narrative = store.create(NarrativeData {
+        name,
+        ..source.clone()
+    })?;
+
+    println!("Copied '{}' to '{}' ({})", source.name, narrative.name, narrative.id);
+    Ok(())
+}
+
+/// 壊れた参照を報告し、`fix` なら自動修正できるものを直す
+///
+/// 直せない問題が残った場合はエラー終了する（スクリプトから検出できるように）。
+pub fn check(query: Option<&str>, fix: bool, json: bool, path: PathBuf) -> Result<()> {
+    let scanner = FileScanner::new(path.clone());
+    scanner.scan()?;
+
+    let store = open_store(&path)?;
+    let mut narratives = match query {
+        Some(query) => vec![store.find(query)?],
+        None => store.list(),
+    };
+    narratives.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut total = 0;
+    let mut fixed = 0;
+    let mut report = Vec::new();
+
+    for mut narrative in narratives {
+        let issues = validation::check(&scanner, &narrative);
+        if issues.is_empty() {
+            continue;
+        }
+        total += issues.len();
+
+        if !json {
+            print_issues(&narrative, &issues, fix);
+        }
+
+        if fix {
+            let applied = validation::apply_fixes(&mut narrative, &issues);
+            if applied > 0 {
+                let id = narrative.id;
+                let message = format!("Fix {applied} stale reference(s)");
+                store.update(&id, narrative, Some(message))?;
+                fixed += applied;
+            }
+        }
+
+        report.extend(issues);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    let fixable = report.iter().filter(|issue| issue.fix.is_some()).count();
+    let remaining = total - fixed;
+    if !json {
+        if total == 0 {
+            println!("No problems found");
+        } else if fix {
+            println!("Fixed {fixed} of {total} problem(s)");
+        } else if fixable > 0 {
+            println!("{total} problem(s) found ({fixable} can be fixed with --fix)");
+        } else {
+            println!("{total} problem(s) found");
+        }
+    }
+
+    if remaining > 0 {
+        anyhow::bail!("{} problem(s) need attention", remaining);
+    }
+    Ok(())
+}
+
+fn print_issues(narrative: &NarrativeData, issues: &[ValidationIssue], fix: bool) {
+    println!("{} ({})", narrative.name, &narrative.id.to_string()[..8]);
+
+    for issue in issues {
+        let problem = match &issue.problem {
+            Problem::MissingFile => String::from("file not found"),
+            Problem::PathOutsideRoot => String::from("path is outside the project root"),
+            Problem::MissingSection { section } => format!("section '{section}' not found"),
+            Problem::LinesOutOfRange {
+                start,
+                end,
+                line_count,
+            } => format!("lines {start}-{end} out of range (file has {line_count} lines)"),
+            Problem::AnchorNotFound { reason } => format!("anchor not found: {reason}"),
+            Problem::AmbiguousAnchor => String::from("anchor matches more than one place"),
+        };
+        println!("{:>5}. {}: {}", issue.position, issue.path, problem);
+
+        if !issue.suggestions.is_empty() {
+            println!("        did you mean: {}", issue.suggestions.join(", "));
+        }
+        if let Some(item_fix) = &issue.fix {
+            let mut changes = Vec::new();
+            if let Some(path) = &item_fix.path {
+                changes.push(format!("path → {path}"));
+            }
+            match &item_fix.include_type {
+                Some(IncludeType::Section { section }) => {
+                    changes.push(format!("section → {section}"))
+                }
+                Some(IncludeType::Lines { start, end }) => {
+                    changes.push(format!("lines → {start}-{end}"))
+                }
+                _ => {}
+            }
+            let verb = if fix { "fixed" } else { "fix" };
+            println!("        {}: {}", verb, changes.join(", "));
+        }
+    }
+}
+
+/// `Lines` のアイテムを、内容で範囲を探すテキストアンカーに置き換える
+///
+/// 今と同じ範囲を一意に特定できないものは `Lines` のまま残して警告する。
+pub fn migrate_lines(query: Option<&str>, dry_run: bool, path: PathBuf) -> Result<()> {
+    let store = open_store(&path)?;
+    let narratives = match query {
+        Some(query) => vec![store.find(query)?],
+        None => store.list(),
+    };
+
+    let mut converted = 0;
+    let mut skipped = 0;
+
+    for mut narrative in narratives {
+        let mut changed = 0;
+
+        for item in narrative.contexts.iter_mut() {
+            let IncludeType::Lines { start, end } = item.include_type else {
+                continue;
+            };
+            if item.escapes_root() {
+                eprintln!(
+                    "Warning: {}: {} is outside the project root; kept as lines",
+                    narrative.name, item.path
+                );
+                skipped += 1;
+                continue;
+            }
+
+            let migrated = fs::read_to_string(path.join(&item.path))
+                .ok()
+                .and_then(|content| anchor::from_lines(&content, start, end));
+            match migrated {
+                Some(anchor) => {
+                    println!("{}: {}:{}-{} → anchor", narrative.name, item.path, start, end);
+                    item.include_type = IncludeType::Anchor { anchor };
+                    changed += 1;
+                }
+                None => {
+                    eprintln!(
+                        "Warning: {}: {}:{}-{} could not be matched to a unique range; kept as lines \
+                         (add <!-- weave:NAME-start --> / <!-- weave:NAME-end --> markers instead)",
+                        narrative.name, item.path, start, end
+                    );
+                    skipped += 1;
+                }
+            }
+        }
+
+        if changed > 0 && !dry_run {
+            let id = narrative.id;
+            let message = format!("Convert {changed} line range(s) to anchors");
+            store.update(&id, narrative, Some(message))?;
+        }
+        converted += changed;
+    }
+
+    let verb = if dry_run { "Would convert" } else { "Converted" };
+    println!("{verb} {converted} line range(s), {skipped} left as lines");
+    Ok(())
+}
+
+pub fn export(
+    queries: &[String],
+    all: bool,
+    output: Option<PathBuf>,
+    format: BundleFormat,
+    path: PathBuf,
+) -> Result<()> {
+    let store = open_store(&path)?;
+
+    let mut narratives = if all {
+        store.list()
+    } else if queries.is_empty() {
+        anyhow::bail!("Specify narratives to export, or --all");
+    } else {
+        queries
+            .iter()
+            .map(|query| store.find(query))
+            .collect::<Result<Vec<_>>>()?
+    };
+    narratives.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+    narratives.dedup_by_key(|n| n.id);
+
+    let source = path
+        .canonicalize()
+        .ok()
+        .and_then(|root| root.file_name().map(|name| name.to_string_lossy().to_string()));
+    let count = narratives.len();
+    let bundle = bundle::new_bundle(narratives, source);
+
+    match (output, format) {
+        (Some(output), format) => {
+            bundle::save(&bundle, &output, format)?;
+            eprintln!("Exported {} narrative(s) to {}", count, output.display());
+        }
+        (None, BundleFormat::Json) => print!("{}", bundle::to_json(&bundle)?),
+        (None, BundleFormat::Toml) => {
+            anyhow::bail!("--output is required for TOML bundles (they are written as a directory)")
+        }
+    }
+
+    Ok(())
+}
+
+pub fn import(
+    source: &Path,
+    on_conflict: ConflictPolicy,
+    path_map: &[String],
+    dry_run: bool,
+    path: PathBuf,
+) -> Result<()> {
+    let path_map = path_map
+        .iter()
+        .map(|mapping| {
+            mapping
+                .split_once('=')
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .with_context(|| format!("Expected OLD=NEW, got '{mapping}'"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let loaded = bundle::load(source)?;
+
+    let scanner = FileScanner::new(path.clone());
+    scanner.scan()?;
+    let store = open_store(&path)?;
+
+    let options = ImportOptions {
+        on_conflict,
+        path_map,
+        dry_run,
+    };
+    let outcomes = bundle::import(&store, &scanner, loaded, &options)?;
+
+    for outcome in &outcomes {
+        let action = match &outcome.action {
+            ImportAction::Created => String::from("imported"),
+            ImportAction::Renamed(name) => format!("imported as '{name}'"),
+            ImportAction::Overwritten => String::from("overwrote existing"),
+            ImportAction::Skipped => String::from("skipped (already exists)"),
+        };
+        println!("{}: {}", outcome.name, action);
+
+        if outcome.action == ImportAction::Skipped {
+            continue;
+        }
+        for (from, to) in &outcome.remapped {
+            println!("    {from} → {to}");
+        }
+        for missing in &outcome.missing {
+            eprintln!("Warning: {}: {} does not exist in this project", outcome.name, missing);
+        }
+    }
+
+    if dry_run {
+        println!("(dry run, nothing was saved)");
+    }
+    Ok(())
+}
+
+pub fn history(query: &str, path: PathBuf) -> Result<()> {
+    let store = open_store(&path)?;
+    let id = find_id(&store, query)?;
+    let revisions = store.history(&id)?;
+
+    if revisions.is_empty() {
+        anyhow::bail!("No revisions recorded for narrative {}", id);
+    }
+
+    for revision in revisions.iter().rev() {
+        let summary = RevisionSummary::from(revision);
+        let reordered = if summary.reordered { " (reordered)" } else { "" };
+        println!(
+            "{:>4}  {}  +{} -{}{}  {}",
+            summary.rev,
+            summary.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+            summary.added,
+            summary.removed,
+            reordered,
+            summary.message.as_deref().unwrap_or("")
+        );
+    }
+
+    Ok(())
+}
+
+pub fn restore(query: &str, rev: u32, message: Option<String>, path: PathBuf) -> Result<()> {
+    let store = open_store(&path)?;
+    let id = find_id(&store, query)?;
+
+    match store.restore(&id, rev, message)? {
+        Some(narrative) => {
+            println!("Restored '{}' to revision {}", narrative.name, rev);
+            Ok(())
+        }
+        None => anyhow::bail!("Revision {} of narrative {} not found", rev, id),
+    }
+}
+
+pub fn open_store(path: &Path) -> Result<NarrativeStore> {
+    let config = WeaverConfig::load(path)?;
+    NarrativeStore::open(path.to_path_buf(), &config)
+}
+
+/// 削除済みのナラティブも履歴は残っているため、UUID はストアになくてもそのまま使う
+fn find_id(store: &NarrativeStore, query: &str) -> Result<Uuid> {
+    match Uuid::parse_str(query) {
+        Ok(id) => Ok(id),
+        Err(_) => Ok(store.find(query)?.id),
+    }
+}
+
+impl ItemArgs {
+    /// フラグの出現位置を見て、コマンドラインに書かれた順のアイテム列にする
+    fn into_items(self, matches: &ArgMatches) -> Result<Vec<ContextItem>> {
+        let mut items: Vec<(usize, ContextItem)> = Vec::new();
+
+        let indexed = |id: &str, values: Vec<String>| -> Vec<(usize, String)> {
+            let indices = matches.indices_of(id).into_iter().flatten();
+            indices.zip(values).collect()
+        };
+
+        for (index, path) in indexed("add", self.add) {
+            items.push((index, file_item(path, IncludeType::Full)));
+        }
+        for (index, value) in indexed("add_section", self.add_section) {
+            let (path, section) = value
+                .split_once(':')
+                .filter(|(path, section)| !path.is_empty() && !section.is_empty())
+                .with_context(|| format!("Expected PATH:SECTION, got '{value}'"))?;
+            let include_type = IncludeType::Section {
+                section: section.to_string(),
+            };
+            items.push((index, file_item(path.to_string(), include_type)));
+        }
+        for (index, value) in indexed("add_lines", self.add_lines) {
+            let (path, start, end) = parse_lines(&value)
+                .with_context(|| format!("Expected PATH:START-END, got '{value}'"))?;
+            items.push((index, file_item(path, IncludeType::Lines { start, end })));
+        }
+        for (index, value) in indexed("add_anchor", self.add_anchor) {
+            let (path, name) = value
+                .rsplit_once(':')
+                .filter(|(path, name)| !path.is_empty() && !name.is_empty())
+                .with_context(|| format!("Expected PATH:NAME, got '{value}'"))?;
+            let include_type = IncludeType::Anchor {
+                anchor: AnchorRange::Marker {
+                    name: name.to_string(),
+                },
+            };
+            items.push((index, file_item(path.to_string(), include_type)));
+        }
+        for (index, body) in indexed("add_text", self.add_text) {
+            let include_type = IncludeType::Text {
+                body: unescape(&body),
+            };
+            items.push((index, file_item(String::new(), include_type)));
+        }
+
+        items.sort_by_key(|(index, _)| *index);
+        Ok(items.into_iter().map(|(_, item)| item).collect())
+    }
+}
+
+fn file_item(path: String, include_type: IncludeType) -> ContextItem {
+    ContextItem {
+        path,
+        include_type,
+        order: 0,
+        header: None,
+        footer: None,
+    }
+}
+
+fn parse_lines(value: &str) -> Option<(String, usize, usize)> {
+    let (path, range) = value.rsplit_once(':')?;
+    let (start, end) = range.split_once('-')?;
+    let start: usize = start.trim().parse().ok()?;
+    let end: usize = end.trim().parse().ok()?;
+
+    if path.is_empty() || start == 0 || end < start {
+        return None;
+    }
+    Some((path.to_string(), start, end))
+}
+
+fn sorted_items(narrative: &NarrativeData) -> Vec<ContextItem> {
+    let mut items = narrative.contexts.clone();
+    items.sort_by_key(|item| item.order);
+    items
+}
+
+/// Web UI と同じく、並び順をそのまま `order` に振り直す
+fn numbered(items: Vec<ContextItem>) -> Vec<ContextItem> {
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(order, item)| ContextItem { order, ..item })
+        .collect()
+}
+
+fn warn_duplicate_name(store: &NarrativeStore, name: &str, except: Option<Uuid>) {
+    let duplicate = store
+        .list()
+        .iter()
+        .any(|n| n.name == name && Some(n.id) != except);
+    if duplicate {
+        eprintln!("Warning: another narrative is already named '{name}'; use its ID to refer to it");
+    }
+}
+
+fn warn_missing_files(root: &Path, items: &[ContextItem]) {
+    for item in items {
+        if matches!(item.include_type, IncludeType::Text { .. }) {
+            continue;
+        }
+        if !root.join(&item.path).is_file() {
+            eprintln!("Warning: {} does not exist", item.path);
+        }
+    }
+}
+
+/// シェルから渡された `\n` `\t` を実際の制御文字に変換する
+fn unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
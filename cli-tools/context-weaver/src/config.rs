@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// `novelenv.toml` の `[context_weaver]` セクション
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WeaverConfig {
+    /// ナラティブごとに保持するリビジョン数
+    pub history_limit: usize,
+    /// ナラティブの保存形式
+    pub storage: StorageLayout,
+    /// `weaver serve` の設定（`[context_weaver.server]`）
+    pub server: ServerConfig,
+}
+
+impl Default for WeaverConfig {
+    fn default() -> Self {
+        Self {
+            history_limit: 50,
+            storage: StorageLayout::default(),
+            server: ServerConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageLayout {
+    /// すべてのナラティブを `.novelenv/narratives.json` にまとめて保存する
+    #[default]
+    File,
+    /// `.novelenv/narratives/<id>.toml` に1ナラティブ1ファイルで保存する（git で差分を追いやすい）
+    Directory,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// 待ち受けるアドレス（省略時は `127.0.0.1`）
+    pub host: Option<String>,
+    /// API に必要なベアラートークン（環境変数 `WEAVER_TOKEN` が優先）
+    pub token: Option<String>,
+    /// 変更系のルートを無効にする
+    pub read_only: bool,
+    /// クロスオリジンでの API 呼び出しを許可するオリジン（`"*"` ですべて許可）
+    pub cors_origins: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    context_weaver: WeaverConfig,
+    #[serde(default)]
+    profile: ProfileSection,
+}
+
+/// find-context と共有する `[profile]` セクション
+#[derive(Debug, Default, Deserialize)]
+struct ProfileSection {
+    #[serde(default)]
+    aliases: HashMap<String, String>,
+}
+
+impl WeaverConfig {
+    pub fn load(project_root: &Path) -> Result<Self> {
+        Ok(read_config_file(project_root)?.context_weaver)
+    }
+}
+
+/// `[profile.aliases]` の別名 → プロファイルのパス（プロジェクトルートからの相対パス）
+pub fn profile_aliases(project_root: &Path) -> Result<HashMap<String, String>> {
+    Ok(read_config_file(project_root)?.profile.aliases)
+}
+
+fn read_config_file(project_root: &Path) -> Result<ConfigFile> {
+    let config_path = project_root.join("novelenv.toml");
+    if !config_path.exists() {
+        return Ok(ConfigFile::default());
+    }
+
+    let content = fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read {}", config_path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse {}", config_path.display()))
+}
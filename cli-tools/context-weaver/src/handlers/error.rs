@@ -0,0 +1,223 @@
+use axum::{
+    body::to_bytes,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::services::ResolveError;
+
+/// エラー本文の `code`（クライアントはメッセージではなくこちらで分岐する）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Query, path parameter or request body is malformed
+    InvalidRequest,
+    Unauthorized,
+    /// No route matches the request (or it is disabled by `--read-only`)
+    NotFound,
+    MethodNotAllowed,
+    NarrativeNotFound,
+    RevisionNotFound,
+    /// `/diff` was requested before the narrative was ever resolved
+    NotResolved,
+    FileNotFound,
+    /// The file exists but is not valid UTF-8 or cannot be read
+    UnreadableFile,
+    SectionNotFound,
+    LinesOutOfRange,
+    AnchorNotFound,
+    /// A narrative item points outside the project root
+    PathOutsideRoot,
+    Internal,
+}
+
+/// すべての API エラーで返す JSON 本文
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Project-relative path of the file involved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Heading involved in a section lookup
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    body: ErrorBody,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: ErrorBody {
+                code,
+                message: message.into(),
+                path: None,
+                section: None,
+            },
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.body.path = Some(path.into());
+        self
+    }
+
+    pub fn with_section(mut self, section: impl Into<String>) -> Self {
+        self.body.section = Some(section.into());
+        self
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest, message)
+    }
+
+    pub fn narrative_not_found(id: &Uuid) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::NarrativeNotFound,
+            format!("Narrative {id} not found"),
+        )
+    }
+
+    pub fn revision_not_found(id: &Uuid, rev: u32) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::RevisionNotFound,
+            format!("Revision {rev} of narrative {id} not found"),
+        )
+    }
+
+    pub fn not_resolved(id: &Uuid) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::NotResolved,
+            format!("Narrative {id} has not been resolved yet"),
+        )
+    }
+
+    pub fn file_not_found(path: &str) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::FileNotFound,
+            format!("File not found: {path}"),
+        )
+        .with_path(path)
+    }
+
+    pub fn unreadable_file(path: &str, err: &anyhow::Error) -> Self {
+        Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::UnreadableFile,
+            format!("Failed to read {path}: {err}"),
+        )
+        .with_path(path)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    /// 解決時のエラーは原因ごとのコードに、それ以外は 500 にする
+    fn from(err: anyhow::Error) -> Self {
+        let Some(resolve_error) = err.downcast_ref::<ResolveError>() else {
+            tracing::error!("{:#}", err);
+            return Self::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::Internal,
+                format!("{err:#}"),
+            );
+        };
+
+        let message = resolve_error.to_string();
+        match resolve_error {
+            ResolveError::PathOutsideRoot { path } => {
+                Self::new(StatusCode::BAD_REQUEST, ErrorCode::PathOutsideRoot, message)
+                    .with_path(path)
+            }
+            ResolveError::FileNotFound { path } => {
+                Self::new(StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::FileNotFound, message)
+                    .with_path(path)
+            }
+            ResolveError::Unreadable { path, .. } => Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::UnreadableFile,
+                format!("{err:#}"),
+            )
+            .with_path(path),
+            ResolveError::SectionNotFound { path, section } => Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::SectionNotFound,
+                message,
+            )
+            .with_path(path)
+            .with_section(section),
+            ResolveError::LinesOutOfRange { path, .. } => Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::LinesOutOfRange,
+                message,
+            )
+            .with_path(path),
+            ResolveError::AnchorNotFound { path, .. } => Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::AnchorNotFound,
+                message,
+            )
+            .with_path(path),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
+
+/// axum の抽出エラーや未登録ルートなど、ハンドラーを通らないエラーも JSON 本文にそろえる
+pub async fn json_errors(response: Response) -> Response {
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let code = match status {
+        StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+        StatusCode::NOT_FOUND => ErrorCode::NotFound,
+        StatusCode::METHOD_NOT_ALLOWED => ErrorCode::MethodNotAllowed,
+        status if status.is_client_error() => ErrorCode::InvalidRequest,
+        _ => ErrorCode::Internal,
+    };
+
+    let (parts, body) = response.into_parts();
+    let text = to_bytes(body, 64 * 1024)
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string())
+        .unwrap_or_default();
+    let message = if text.is_empty() {
+        status.canonical_reason().unwrap_or("Error").to_string()
+    } else {
+        text
+    };
+
+    let mut response = ApiError::new(status, code, message).into_response();
+    // WWW-Authenticate などの元のヘッダーは残す
+    for (name, value) in parts.headers.iter() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            response.headers_mut().insert(name, value.clone());
+        }
+    }
+    response
+}
@@ -1,16 +1,48 @@
+mod error;
+pub mod openapi;
+
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{rejection::JsonRejection, Path, Query, State},
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
     Json,
 };
+use serde::Deserialize;
+use std::convert::Infallible;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use utoipa::IntoParams;
 use uuid::Uuid;
 
 use crate::{
-    models::{FileInfo, NarrativeData},
-    services::{FileScanner, NarrativeStore},
+    models::{
+        FileInfo, NarrativeData, NarrativeFilter, NarrativeSort, ResolveFormat, RevisionSummary,
+        ServerInfo, SourceLabel,
+        ValidationIssue,
+    },
+    services::{
+        browse, history::diff_contexts, render, validation, EventBus, FileScanner, NarrativeStore,
+    },
 };
 
+pub use error::{json_errors, ApiError, ErrorBody, ErrorCode};
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// リクエスト本文の JSON が読めないときも `ErrorBody` で返す
+fn json_body<T>(body: Result<Json<T>, JsonRejection>) -> ApiResult<T> {
+    body.map(|Json(value)| value)
+        .map_err(|rejection| ApiError::invalid_request(rejection.body_text()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/files",
+    tag = "files",
+    responses((status = 200, description = "Files and directories in the project", body = [FileInfo]))
+)]
 pub async fn list_files(State(scanner): State<FileScanner>) -> impl IntoResponse {
     let files: Vec<FileInfo> = scanner
         .get_file_map()
@@ -21,76 +53,521 @@ pub async fn list_files(State(scanner): State<FileScanner>) -> impl IntoResponse
     Json(files)
 }
 
-pub async fn refresh_files(State(scanner): State<FileScanner>) -> Result<impl IntoResponse, StatusCode> {
-    match scanner.scan() {
-        Ok(_) => {
-            let files: Vec<FileInfo> = scanner
-                .get_file_map()
-                .iter()
-                .map(|entry| entry.value().clone())
-                .collect();
-            Ok(Json(files))
+#[utoipa::path(
+    post,
+    path = "/api/files/refresh",
+    tag = "files",
+    responses(
+        (status = 200, description = "Rescanned file list", body = [FileInfo]),
+        (status = 500, body = ErrorBody)
+    )
+)]
+pub async fn refresh_files(State(scanner): State<FileScanner>) -> ApiResult<impl IntoResponse> {
+    scanner.scan()?;
+
+    let files: Vec<FileInfo> = scanner
+        .get_file_map()
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+    Ok(Json(files))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Case-insensitive text matched against file names and contents
+    pub q: String,
+    /// Maximum number of files (default 50)
+    pub limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/files/search",
+    tag = "files",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Matching files, name matches first", body = [FileSearchResult]),
+        (status = 400, body = ErrorBody)
+    )
+)]
+pub async fn search_files(
+    State(scanner): State<FileScanner>,
+    Query(query): Query<SearchQuery>,
+) -> impl IntoResponse {
+    Json(browse::search(&scanner, &query.q, query.limit.unwrap_or(50)))
+}
+
+/// `:path` はスラッシュを含めて URL エンコードしたプロジェクト相対パス
+#[utoipa::path(
+    get,
+    path = "/api/files/{path}/outline",
+    tag = "files",
+    params(("path" = String, Path, description = "URL-encoded project-relative path")),
+    responses(
+        (status = 200, description = "Heading tree", body = [OutlineHeading]),
+        (status = 404, body = ErrorBody),
+        (status = 422, body = ErrorBody)
+    )
+)]
+pub async fn file_outline(
+    State(scanner): State<FileScanner>,
+    Path(path): Path<String>,
+) -> ApiResult<impl IntoResponse> {
+    match browse::outline(&scanner, &path) {
+        Ok(Some(outline)) => Ok(Json(outline)),
+        Ok(None) => Err(ApiError::file_not_found(&path)),
+        Err(err) => Err(ApiError::unreadable_file(&path, &err)),
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ContentQuery {
+    /// First line (1-based, default 1)
+    pub start: Option<usize>,
+    /// Last line, inclusive (default: end of file)
+    pub end: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/files/{path}/content",
+    tag = "files",
+    params(
+        ("path" = String, Path, description = "URL-encoded project-relative path"),
+        ContentQuery
+    ),
+    responses(
+        (status = 200, description = "Requested lines", body = FileContent),
+        (status = 400, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 422, body = ErrorBody)
+    )
+)]
+pub async fn file_content(
+    State(scanner): State<FileScanner>,
+    Path(path): Path<String>,
+    Query(query): Query<ContentQuery>,
+) -> ApiResult<impl IntoResponse> {
+    if query.start == Some(0) {
+        return Err(ApiError::invalid_request("Line numbers start at 1").with_path(path));
+    }
+    if let (Some(start), Some(end)) = (query.start, query.end) {
+        if end < start {
+            return Err(ApiError::invalid_request(format!(
+                "End line {end} is before start line {start}"
+            ))
+            .with_path(path));
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    match browse::content(&scanner, &path, query.start, query.end) {
+        Ok(Some(content)) => Ok(Json(content)),
+        Ok(None) => Err(ApiError::file_not_found(&path)),
+        Err(err) => Err(ApiError::unreadable_file(&path, &err)),
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/narratives",
+    tag = "narratives",
+    request_body = NarrativeData,
+    responses(
+        (status = 200, description = "Created narrative with its new ID", body = NarrativeData),
+        (status = 400, body = ErrorBody)
+    )
+)]
 pub async fn create_narrative(
     State(store): State<NarrativeStore>,
-    Json(narrative): Json<NarrativeData>,
-) -> Result<impl IntoResponse, StatusCode> {
-    match store.create(narrative) {
-        Ok(created) => Ok(Json(created)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+    body: Result<Json<NarrativeData>, JsonRejection>,
+) -> ApiResult<impl IntoResponse> {
+    let created = store.create(json_body(body)?)?;
+    Ok(Json(created))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/narratives/{id}",
+    tag = "narratives",
+    params(("id" = Uuid, Path, description = "Narrative ID")),
+    responses(
+        (status = 200, body = NarrativeData),
+        (status = 404, body = ErrorBody)
+    )
+)]
 pub async fn get_narrative(
     State(store): State<NarrativeStore>,
     Path(id): Path<Uuid>,
-) -> Result<impl IntoResponse, StatusCode> {
+) -> ApiResult<impl IntoResponse> {
     match store.get(&id) {
         Some(narrative) => Ok(Json(narrative)),
-        None => Err(StatusCode::NOT_FOUND),
+        None => Err(ApiError::narrative_not_found(&id)),
     }
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RevisionMessage {
+    /// Message recorded with the new revision
+    pub message: Option<String>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/narratives/{id}",
+    tag = "narratives",
+    params(("id" = Uuid, Path, description = "Narrative ID"), RevisionMessage),
+    request_body = NarrativeData,
+    responses(
+        (status = 200, description = "Saved narrative (created if the ID is new)", body = NarrativeData),
+        (status = 400, body = ErrorBody)
+    )
+)]
 pub async fn update_narrative(
     State(store): State<NarrativeStore>,
     Path(id): Path<Uuid>,
-    Json(narrative): Json<NarrativeData>,
-) -> Result<impl IntoResponse, StatusCode> {
-    match store.update(&id, narrative) {
-        Ok(updated) => Ok(Json(updated)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+    Query(query): Query<RevisionMessage>,
+    body: Result<Json<NarrativeData>, JsonRejection>,
+) -> ApiResult<impl IntoResponse> {
+    let updated = store.update(&id, json_body(body)?, query.message)?;
+    Ok(Json(updated))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/narratives/{id}",
+    tag = "narratives",
+    params(("id" = Uuid, Path, description = "Narrative ID")),
+    responses((status = 204, description = "Deleted (history is kept)"))
+)]
 pub async fn delete_narrative(
     State(store): State<NarrativeStore>,
     Path(id): Path<Uuid>,
-) -> Result<impl IntoResponse, StatusCode> {
-    match store.delete(&id) {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+) -> ApiResult<impl IntoResponse> {
+    store.delete(&id)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/narratives/{id}/revisions",
+    tag = "revisions",
+    params(("id" = Uuid, Path, description = "Narrative ID")),
+    responses((status = 200, description = "Revisions, newest first", body = [RevisionSummary]))
+)]
+pub async fn list_revisions(
+    State(store): State<NarrativeStore>,
+    Path(id): Path<Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let revisions = store.history(&id)?;
+    let summaries: Vec<RevisionSummary> =
+        revisions.iter().rev().map(RevisionSummary::from).collect();
+    Ok(Json(summaries))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/narratives/{id}/revisions/{rev}",
+    tag = "revisions",
+    params(
+        ("id" = Uuid, Path, description = "Narrative ID"),
+        ("rev" = u32, Path, description = "Revision number")
+    ),
+    responses(
+        (status = 200, body = Revision),
+        (status = 404, body = ErrorBody)
+    )
+)]
+pub async fn get_revision(
+    State(store): State<NarrativeStore>,
+    Path((id, rev)): Path<(Uuid, u32)>,
+) -> ApiResult<impl IntoResponse> {
+    match store.revision(&id, rev)? {
+        Some(revision) => Ok(Json(revision)),
+        None => Err(ApiError::revision_not_found(&id, rev)),
     }
 }
 
-pub async fn list_narratives(State(store): State<NarrativeStore>) -> impl IntoResponse {
-    Json(store.list())
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RevisionRange {
+    pub from: u32,
+    pub to: u32,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/narratives/{id}/revisions/diff",
+    tag = "revisions",
+    params(("id" = Uuid, Path, description = "Narrative ID"), RevisionRange),
+    responses(
+        (status = 200, body = ContextsDiff),
+        (status = 404, body = ErrorBody)
+    )
+)]
+pub async fn diff_revisions(
+    State(store): State<NarrativeStore>,
+    Path(id): Path<Uuid>,
+    Query(range): Query<RevisionRange>,
+) -> ApiResult<impl IntoResponse> {
+    let from = store
+        .revision(&id, range.from)?
+        .ok_or_else(|| ApiError::revision_not_found(&id, range.from))?;
+    let to = store
+        .revision(&id, range.to)?
+        .ok_or_else(|| ApiError::revision_not_found(&id, range.to))?;
+
+    Ok(Json(diff_contexts(
+        &from.snapshot.contexts,
+        &to.snapshot.contexts,
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/narratives/{id}/revisions/{rev}/restore",
+    tag = "revisions",
+    params(
+        ("id" = Uuid, Path, description = "Narrative ID"),
+        ("rev" = u32, Path, description = "Revision number"),
+        RevisionMessage
+    ),
+    responses(
+        (status = 200, description = "Restored narrative", body = NarrativeData),
+        (status = 404, body = ErrorBody)
+    )
+)]
+pub async fn restore_revision(
+    State(store): State<NarrativeStore>,
+    Path((id, rev)): Path<(Uuid, u32)>,
+    Query(query): Query<RevisionMessage>,
+) -> ApiResult<impl IntoResponse> {
+    match store.restore(&id, rev, query.message)? {
+        Some(restored) => Ok(Json(restored)),
+        None => Err(ApiError::revision_not_found(&id, rev)),
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NarrativeListQuery {
+    /// Text searched in names, descriptions, tags and folders (case-insensitive)
+    pub q: Option<String>,
+    /// Comma-separated tags that must all be present
+    pub tags: Option<String>,
+    /// Folder; narratives in its subfolders are included
+    pub folder: Option<String>,
+    /// `true` for templates only, `false` for non-templates only
+    pub template: Option<bool>,
+    /// Sort order (default `updated`, newest first)
+    pub sort: Option<NarrativeSort>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/narratives",
+    tag = "narratives",
+    params(NarrativeListQuery),
+    responses(
+        (status = 200, body = [NarrativeData]),
+        (status = 400, body = ErrorBody)
+    )
+)]
+pub async fn list_narratives(
+    State(store): State<NarrativeStore>,
+    Query(query): Query<NarrativeListQuery>,
+) -> impl IntoResponse {
+    let tags = query
+        .tags
+        .as_deref()
+        .map(|tags| {
+            tags.split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Json(store.search(&NarrativeFilter {
+        query: query.q,
+        tags,
+        folder: query.folder,
+        template: query.template,
+        sort: query.sort.unwrap_or_default(),
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ResolveQuery {
+    /// Overrides the narrative's separator
+    pub separator: Option<String>,
+    /// Overrides the narrative's source label
+    pub source_label: Option<SourceLabel>,
+    /// Output format; takes precedence over the `Accept` header
+    pub format: Option<ResolveFormat>,
+    /// Record this resolve as the baseline for `/diff` (default `true`; previews pass `false`; never recorded on a read-only server)
+    pub record: Option<bool>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/narratives/{id}/resolve",
+    tag = "narratives",
+    params(("id" = Uuid, Path, description = "Narrative ID"), ResolveQuery),
+    responses(
+        (status = 200, description = "Resolved context in the requested format", content(
+            ("text/markdown" = String),
+            ("application/xml" = String),
+            ("application/json" = String),
+            ("application/x-ndjson" = String)
+        )),
+        (status = 400, description = "An item points outside the project root", body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 422, description = "An item refers to a missing file, section, line range or anchor", body = ErrorBody)
+    )
+)]
 pub async fn resolve_narrative(
+    State((store, scanner, info)): State<(NarrativeStore, FileScanner, ServerInfo)>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ResolveQuery>,
+    headers: HeaderMap,
+) -> ApiResult<impl IntoResponse> {
+    let narrative = store
+        .get(&id)
+        .ok_or_else(|| ApiError::narrative_not_found(&id))?;
+
+    let mut options = narrative.output.clone();
+    if let Some(separator) = query.separator {
+        options.separator = separator;
+    }
+    if let Some(source_label) = query.source_label {
+        options.source_label = source_label;
+    }
+
+    // ?format= が Accept ヘッダーより優先される
+    let format = query
+        .format
+        .or_else(|| {
+            headers
+                .get(header::ACCEPT)
+                .and_then(|value| value.to_str().ok())
+                .and_then(render::format_from_accept)
+        })
+        .unwrap_or_default();
+
+    // 読み取り専用のサーバーはディスクに書き込まない
+    let record = !info.read_only && query.record.unwrap_or(true);
+    let items = store.resolve(&scanner, &narrative, record)?;
+    let content = render::render(&narrative, &items, format, &options)?;
+    Ok(([(header::CONTENT_TYPE, render::content_type(format))], content))
+}
+
+/// 最後に記録した解決結果からの変化（アイテムごとの状態と unified diff）
+#[utoipa::path(
+    get,
+    path = "/api/narratives/{id}/diff",
+    tag = "narratives",
+    params(("id" = Uuid, Path, description = "Narrative ID")),
+    responses(
+        (status = 200, body = ResolveDiff),
+        (status = 400, description = "An item points outside the project root", body = ErrorBody),
+        (status = 404, description = "Narrative not found or never resolved", body = ErrorBody),
+        (status = 422, description = "An item refers to a missing file, section, line range or anchor", body = ErrorBody)
+    )
+)]
+pub async fn resolve_diff(
     State((store, scanner)): State<(NarrativeStore, FileScanner)>,
     Path(id): Path<Uuid>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let narrative = match store.get(&id) {
-        Some(n) => n,
-        None => return Err(StatusCode::NOT_FOUND),
-    };
+) -> ApiResult<impl IntoResponse> {
+    let narrative = store
+        .get(&id)
+        .ok_or_else(|| ApiError::narrative_not_found(&id))?;
 
-    match scanner.resolve_includes(&narrative) {
-        Ok(content) => Ok(content),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    match store.resolve_diff(&scanner, &narrative)? {
+        Some(diff) => Ok(Json(diff)),
+        None => Err(ApiError::not_resolved(&id)),
     }
 }
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ValidateQuery {
+    /// Only check this narrative
+    pub id: Option<Uuid>,
+}
+
+/// 全ナラティブ（`?id=` 指定時はその1件）の壊れた参照を報告する
+#[utoipa::path(
+    get,
+    path = "/api/narratives/validate",
+    tag = "narratives",
+    params(ValidateQuery),
+    responses(
+        (status = 200, description = "Stale references (empty when everything resolves)", body = [ValidationIssue]),
+        (status = 404, body = ErrorBody)
+    )
+)]
+pub async fn validate_narratives(
+    State((store, scanner)): State<(NarrativeStore, FileScanner)>,
+    Query(query): Query<ValidateQuery>,
+) -> ApiResult<impl IntoResponse> {
+    let narratives = match query.id {
+        Some(id) => vec![store
+            .get(&id)
+            .ok_or_else(|| ApiError::narrative_not_found(&id))?],
+        None => store.list(),
+    };
+
+    let issues: Vec<ValidationIssue> = narratives
+        .iter()
+        .flat_map(|narrative| validation::check(&scanner, narrative))
+        .collect();
+
+    Ok(Json(issues))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    tag = "events",
+    responses((status = 200, description = "Server-Sent Events stream of `file` and `narrative` changes", content_type = "text/event-stream"))
+)]
+pub async fn stream_events(
+    State(events): State<EventBus>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // 受信が追いつかず取りこぼしたイベントは捨てる（クライアントは再取得で追従する）
+    let stream = BroadcastStream::new(events.subscribe()).filter_map(|result| {
+        let change = result.ok()?;
+        let event = Event::default()
+            .event(change.event_name())
+            .json_data(&change)
+            .ok()?;
+        Some(Ok(event))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/server",
+    tag = "server",
+    responses((status = 200, body = ServerInfo))
+)]
+pub async fn server_info(State(info): State<ServerInfo>) -> impl IntoResponse {
+    Json(info)
+}
+
+/// 登録されていない `/api/` 以下のパス（静的ファイルの 404 と区別する）
+pub async fn api_not_found(uri: Uri) -> ApiError {
+    ApiError::new(
+        StatusCode::NOT_FOUND,
+        ErrorCode::NotFound,
+        format!("No API endpoint at {}", uri.path()),
+    )
+}
@@ -0,0 +1,96 @@
+use axum::{response::IntoResponse, Json};
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use super::{ErrorBody, ErrorCode};
+use crate::models::{
+    AnchorRange, ChangeStatus, ContextItem, ContextsDiff, FileContent, FileInfo,
+    FileSearchResult, IncludeType, ItemChange, ItemFix, LineMatch, NarrativeData, NarrativeSort,
+    OutlineHeading, OutputOptions, Problem, ResolveDiff, ResolveFormat, Revision,
+    RevisionSummary, ServerInfo, SourceLabel, ValidationIssue,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Context Weaver API",
+        description = "Narratives, project files and context resolution served by `weaver serve`"
+    ),
+    paths(
+        super::list_files,
+        super::refresh_files,
+        super::search_files,
+        super::file_outline,
+        super::file_content,
+        super::list_narratives,
+        super::create_narrative,
+        super::get_narrative,
+        super::update_narrative,
+        super::delete_narrative,
+        super::resolve_narrative,
+        super::resolve_diff,
+        super::validate_narratives,
+        super::list_revisions,
+        super::get_revision,
+        super::diff_revisions,
+        super::restore_revision,
+        super::stream_events,
+        super::server_info,
+        openapi_json,
+    ),
+    components(schemas(
+        NarrativeData,
+        NarrativeSort,
+        ContextItem,
+        IncludeType,
+        AnchorRange,
+        OutputOptions,
+        SourceLabel,
+        ResolveFormat,
+        FileInfo,
+        FileSearchResult,
+        LineMatch,
+        OutlineHeading,
+        FileContent,
+        Revision,
+        RevisionSummary,
+        ContextsDiff,
+        ResolveDiff,
+        ItemChange,
+        ChangeStatus,
+        ValidationIssue,
+        Problem,
+        ItemFix,
+        ServerInfo,
+        ErrorBody,
+        ErrorCode,
+    )),
+    modifiers(&BearerToken),
+    security(("bearer" = []))
+)]
+pub struct ApiDoc;
+
+/// `WEAVER_TOKEN` を設定したときに必要になるベアラートークン
+struct BearerToken;
+
+impl Modify for BearerToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/openapi.json",
+    tag = "server",
+    responses((status = 200, description = "This OpenAPI document", content_type = "application/json"))
+)]
+pub async fn openapi_json() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
@@ -1,20 +1,16 @@
+mod commands;
+mod config;
 mod handlers;
+mod mcp;
 mod models;
+mod server;
 mod services;
 
 use anyhow::Result;
-use axum::{
-    routing::{delete, get, post, put},
-    Router,
-};
-use clap::{Parser, Subcommand};
-use std::env;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use std::path::PathBuf;
-use tower_http::cors::CorsLayer;
-use tower_http::services::ServeDir;
-use uuid::Uuid;
 
-use services::{FileScanner, NarrativeStore};
+use models::{BundleFormat, ConflictPolicy, ResolveFormat, SourceLabel};
 
 #[derive(Parser)]
 #[command(name = "weaver")]
@@ -33,116 +29,357 @@ enum Commands {
 
         #[arg(short = 'P', long, default_value = ".")]
         path: PathBuf,
+
+        /// Address to bind (default: 127.0.0.1, or [context_weaver.server] host)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Disable creating, editing, deleting and restoring narratives
+        #[arg(long)]
+        read_only: bool,
+
+        /// Origin allowed to call the API cross-origin ("*" for any); repeatable
+        #[arg(long = "cors-origin", value_name = "ORIGIN")]
+        cors_origins: Vec<String>,
+    },
+
+    /// Serve narratives, profiles, episodes and files to MCP clients over stdio
+    Mcp {
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
     },
 
     /// Resolve and output a narrative context
     Resolve {
-        /// The narrative ID to resolve
-        id: Uuid,
+        /// Narrative ID, name or unique prefix
+        narrative: String,
 
         #[arg(short = 'P', long, default_value = ".")]
         path: PathBuf,
+
+        /// Separator between items (overrides the narrative setting, accepts \n and \t escapes)
+        #[arg(long)]
+        separator: Option<String>,
+
+        /// Prefix each item with its source path
+        #[arg(long, value_enum)]
+        source_label: Option<SourceLabel>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "markdown")]
+        format: ResolveFormat,
+
+        /// Do not record this resolve as the baseline for `weaver diff`
+        #[arg(long)]
+        no_record: bool,
     },
-}
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
+    /// Show what changed in a narrative's resolved content since it was last resolved
+    Diff {
+        /// Narrative ID, name or unique prefix
+        narrative: String,
 
-    let cli = Cli::parse();
+        /// Only list the changed items
+        #[arg(long)]
+        stat: bool,
 
-    match cli.command {
-        Commands::Serve { port, path } => {
-            serve(port, path).await?;
-        }
-        Commands::Resolve { id, path } => {
-            resolve(id, path).await?;
-        }
-    }
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
 
-    Ok(())
-}
+        /// Exit with status 1 if anything changed
+        #[arg(long)]
+        exit_code: bool,
 
-fn get_static_dir() -> PathBuf {
-    // 実行ファイルの場所を取得
-    let current_exe = env::current_exe()
-        .expect("Failed to get current executable path");
-    
-    let exe_dir = current_exe.parent()
-        .expect("Failed to get executable directory");
-    
-    // まず同じディレクトリ内でstaticフォルダを探す（インストール済み環境）
-    let installed_static = exe_dir.join("static");
-    if installed_static.exists() {
-        return installed_static;
-    }
-    
-    // 開発環境のパスを試す
-    if let Some(cli_tools_dir) = exe_dir
-        .parent() // target
-        .and_then(|p| p.parent()) // release
-        .and_then(|p| p.parent()) // context-weaver
-    {
-        let dev_static = cli_tools_dir
-            .join("context-weaver")
-            .join("static");
-        if dev_static.exists() {
-            return dev_static;
-        }
-    }
-    
-    // フォールバック: 相対パス
-    PathBuf::from("cli-tools/context-weaver/static")
-}
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
 
-async fn serve(port: u16, path: PathBuf) -> Result<()> {
-    let scanner = FileScanner::new(path.clone());
-    scanner.scan()?;
-
-    let store = NarrativeStore::new(path.clone());
-    
-    let static_dir = get_static_dir();
-    tracing::info!("Static directory: {}", static_dir.display());
-
-    let app = Router::new()
-        .route("/api/files", get(handlers::list_files))
-        .route("/api/files/refresh", post(handlers::refresh_files))
-        .with_state(scanner.clone())
-        .route("/api/narratives", get(handlers::list_narratives))
-        .route("/api/narratives", post(handlers::create_narrative))
-        .route("/api/narratives/:id", get(handlers::get_narrative))
-        .route("/api/narratives/:id", put(handlers::update_narrative))
-        .route("/api/narratives/:id", delete(handlers::delete_narrative))
-        .with_state(store.clone())
-        .route(
-            "/api/narratives/:id/resolve",
-            get(handlers::resolve_narrative),
-        )
-        .with_state((store, scanner))
-        .nest_service("/", ServeDir::new(static_dir))
-        .layer(CorsLayer::permissive());
-
-    let addr = format!("0.0.0.0:{port}");
-    tracing::info!("Server running at http://{}", addr);
-
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    /// List saved narratives
+    List(commands::ListArgs),
 
-    Ok(())
+    /// Show a narrative and its items
+    Show {
+        /// Narrative ID, name or unique prefix
+        narrative: String,
+
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Create a narrative
+    Create(commands::CreateArgs),
+
+    /// Change a narrative's settings or items
+    Edit(commands::EditArgs),
+
+    /// Delete narratives
+    Rm {
+        /// Narrative IDs, names or unique prefixes
+        #[arg(required = true)]
+        narratives: Vec<String>,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Rename a narrative
+    Rename {
+        /// Narrative ID, name or unique prefix
+        narrative: String,
+
+        /// New name
+        name: String,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Duplicate a narrative
+    Copy {
+        /// Narrative ID, name or unique prefix
+        narrative: String,
+
+        /// Name of the copy (defaults to "<name> (copy)")
+        name: Option<String>,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Report items that point at missing files, sections or lines
+    Check {
+        /// Only check this narrative (ID, name or unique prefix)
+        narrative: Option<String>,
+
+        /// Apply the suggested replacement when one candidate is clearly the best
+        #[arg(long)]
+        fix: bool,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Convert line-range items into anchors that survive edits
+    MigrateLines {
+        /// Only migrate this narrative (ID, name or unique prefix)
+        narrative: Option<String>,
+
+        /// Report what would change without saving
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Write narratives to a bundle that can be imported into another project
+    Export {
+        /// Narrative IDs, names or unique prefixes
+        narratives: Vec<String>,
+
+        /// Export every narrative
+        #[arg(long, conflicts_with = "narratives")]
+        all: bool,
+
+        /// Bundle file (JSON) or directory (TOML); JSON is printed to stdout if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[arg(short, long, value_enum, default_value = "json")]
+        format: BundleFormat,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Load narratives from a bundle written by `weaver export`
+    Import {
+        /// Bundle file or directory
+        bundle: PathBuf,
+
+        /// What to do when a narrative with the same ID or name exists
+        #[arg(long, value_enum, default_value = "skip")]
+        on_conflict: ConflictPolicy,
+
+        /// Rewrite path prefixes, e.g. --map chars/=character/ (repeatable)
+        #[arg(long = "map", value_name = "OLD=NEW")]
+        path_map: Vec<String>,
+
+        /// Report what would happen without saving
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Show the revision history of a narrative
+    History {
+        /// Narrative ID, name or unique prefix
+        narrative: String,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
+
+    /// Restore a narrative to a previous revision
+    Restore {
+        /// Narrative ID, name or unique prefix
+        narrative: String,
+
+        /// Revision number to restore (see `weaver history`)
+        rev: u32,
+
+        /// Message recorded with the restore
+        #[arg(short, long)]
+        message: Option<String>,
+
+        #[arg(short = 'P', long, default_value = ".")]
+        path: PathBuf,
+    },
 }
 
-async fn resolve(id: Uuid, path: PathBuf) -> Result<()> {
-    let scanner = FileScanner::new(path.clone());
-    scanner.scan()?;
+#[tokio::main]
+async fn main() -> Result<()> {
+    // 標準出力は resolve の結果や MCP のメッセージに使うため、ログは標準エラーへ
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
 
-    let store = NarrativeStore::new(path.clone());
+    // create / edit はフラグの出現順を見るため ArgMatches も残しておく
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    let sub_matches = matches
+        .subcommand()
+        .map(|(_, sub_matches)| sub_matches)
+        .expect("subcommand is required");
 
-    match store.get(&id) {
-        Some(narrative) => {
-            let content = scanner.resolve_includes(&narrative)?;
-            print!("{content}");
-            Ok(())
+    match cli.command {
+        Commands::Serve {
+            port,
+            path,
+            host,
+            read_only,
+            cors_origins,
+        } => {
+            let args = server::ServeArgs {
+                port,
+                host,
+                read_only,
+                cors_origins,
+            };
+            server::serve(path, args).await?;
+        }
+        Commands::Mcp { path } => {
+            mcp::run(path)?;
+        }
+        Commands::Resolve {
+            narrative,
+            path,
+            separator,
+            source_label,
+            format,
+            no_record,
+        } => {
+            commands::resolve(&narrative, path, separator, source_label, format, !no_record)?;
+        }
+        Commands::Diff {
+            narrative,
+            stat,
+            json,
+            exit_code,
+            path,
+        } => {
+            let changed = commands::diff(&narrative, stat, json, path)?;
+            if exit_code && changed {
+                std::process::exit(1);
+            }
+        }
+        Commands::List(args) => {
+            commands::list(args)?;
+        }
+        Commands::Show {
+            narrative,
+            json,
+            path,
+        } => {
+            commands::show(&narrative, path, json)?;
+        }
+        Commands::Create(args) => {
+            commands::create(args, sub_matches)?;
+        }
+        Commands::Edit(args) => {
+            commands::edit(args, sub_matches)?;
+        }
+        Commands::Rm { narratives, path } => {
+            commands::remove(&narratives, path)?;
+        }
+        Commands::Rename {
+            narrative,
+            name,
+            path,
+        } => {
+            commands::rename(&narrative, name, path)?;
+        }
+        Commands::Copy {
+            narrative,
+            name,
+            path,
+        } => {
+            commands::copy(&narrative, name, path)?;
+        }
+        Commands::Check {
+            narrative,
+            fix,
+            json,
+            path,
+        } => {
+            commands::check(narrative.as_deref(), fix, json, path)?;
+        }
+        Commands::MigrateLines {
+            narrative,
+            dry_run,
+            path,
+        } => {
+            commands::migrate_lines(narrative.as_deref(), dry_run, path)?;
+        }
+        Commands::Export {
+            narratives,
+            all,
+            output,
+            format,
+            path,
+        } => {
+            commands::export(&narratives, all, output, format, path)?;
+        }
+        Commands::Import {
+            bundle,
+            on_conflict,
+            path_map,
+            dry_run,
+            path,
+        } => {
+            commands::import(&bundle, on_conflict, &path_map, dry_run, path)?;
+        }
+        Commands::History { narrative, path } => {
+            commands::history(&narrative, path)?;
+        }
+        Commands::Restore {
+            narrative,
+            rev,
+            message,
+            path,
+        } => {
+            commands::restore(&narrative, rev, message, path)?;
         }
-        None => anyhow::bail!("Narrative with ID {} not found", id),
     }
+
+    Ok(())
 }
@@ -0,0 +1,579 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use url::Url;
+use uuid::Uuid;
+
+use crate::config::{self, WeaverConfig};
+use crate::models::{NarrativeFilter, NarrativeSort, ResolveFormat};
+use crate::services::{
+    self, browse, find_context, lookup, markdown, render, EventBus, FileScanner, NarrativeStore,
+};
+
+/// 対応する MCP のプロトコルバージョン（新しい順）
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+const PAGE_SIZE: usize = 200;
+const NARRATIVE_URI_PREFIX: &str = "weaver://narrative/";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const RESOURCE_NOT_FOUND: i64 = -32002;
+
+/// 標準入出力で MCP（改行区切りの JSON-RPC 2.0）を話す
+///
+/// 標準出力はプロトコル専用のため、ログはすべて標準エラーに出す。
+pub fn run(path: PathBuf) -> Result<()> {
+    let scanner = FileScanner::new(path.clone());
+    scanner.scan()?;
+
+    let config = WeaverConfig::load(&path)?;
+    let store = NarrativeStore::open(path.clone(), &config)?;
+    let aliases = config::profile_aliases(&path)?;
+
+    // 長時間つながるセッションでも、ファイルやナラティブの変更を反映する
+    services::watcher::spawn(scanner.clone(), store.clone(), EventBus::new())?;
+
+    let server = McpServer {
+        root: path,
+        scanner,
+        store,
+        aliases,
+    };
+    tracing::info!("MCP server ready on stdio");
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(Value::Array(batch)) => {
+                let responses: Vec<Value> = batch
+                    .into_iter()
+                    .filter_map(|message| server.handle(message))
+                    .collect();
+                (!responses.is_empty()).then_some(Value::Array(responses))
+            }
+            Ok(message) => server.handle(message),
+            Err(err) => Some(error_response(Value::Null, PARSE_ERROR, err.to_string())),
+        };
+
+        if let Some(response) = response {
+            serde_json::to_writer(&mut stdout, &response)?;
+            stdout.write_all(b"\n")?;
+            stdout.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// 通知には ID がなく、応答も返さない
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// JSON-RPC のエラーとして返すもの（ツールの実行失敗は `isError` の結果として返す）
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.into(),
+        }
+    }
+
+    fn resource_not_found(uri: &str) -> Self {
+        Self {
+            code: RESOURCE_NOT_FOUND,
+            message: format!("Resource not found: {uri}"),
+        }
+    }
+}
+
+struct McpServer {
+    root: PathBuf,
+    scanner: FileScanner,
+    store: NarrativeStore,
+    aliases: HashMap<String, String>,
+}
+
+impl McpServer {
+    fn handle(&self, message: Value) -> Option<Value> {
+        let request: Request = match serde_json::from_value(message) {
+            Ok(request) => request,
+            Err(err) => {
+                return Some(error_response(Value::Null, INVALID_REQUEST, err.to_string()))
+            }
+        };
+
+        let Some(id) = request.id else {
+            // notifications/initialized などの通知は受け取るだけ
+            tracing::debug!("Notification: {}", request.method);
+            return None;
+        };
+
+        let result = match request.method.as_str() {
+            "initialize" => Ok(self.initialize(&request.params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => self.call_tool(&request.params),
+            "resources/list" => self.list_resources(&request.params),
+            "resources/templates/list" => Ok(json!({ "resourceTemplates": [] })),
+            "resources/read" => self.read_resource(&request.params),
+            method => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Method not found: {method}"),
+            }),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => error_response(id, err.code, err.message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params["protocolVersion"].as_str().unwrap_or_default();
+        let version = PROTOCOL_VERSIONS
+            .iter()
+            .find(|version| **version == requested)
+            .unwrap_or(&PROTOCOL_VERSIONS[0]);
+
+        json!({
+            "protocolVersion": version,
+            "capabilities": {
+                "tools": { "listChanged": false },
+                "resources": { "listChanged": false, "subscribe": false },
+            },
+            "serverInfo": {
+                "name": "weaver",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "instructions": "Context Weaver for a NovelEnv project. Use list_narratives / resolve_narrative for curated context bundles (diff_narrative tells whether a bundle changed since it was last resolved), read_profile for character profiles, read_section for one heading of a file, search_episodes to find episodes featuring a character, and list_files to browse or search the manuscript tree.",
+        })
+    }
+
+    fn call_tool(&self, params: &Value) -> Result<Value, RpcError> {
+        let name = params["name"]
+            .as_str()
+            .ok_or_else(|| RpcError::invalid_params("Missing tool name"))?;
+        let arguments = &params["arguments"];
+
+        let output = match name {
+            "list_narratives" => self.list_narratives(arguments),
+            "resolve_narrative" => self.resolve_narrative(arguments),
+            "diff_narrative" => self.diff_narrative(arguments),
+            "read_profile" => self.read_profile(arguments),
+            "read_section" => self.read_section(arguments),
+            "search_episodes" => self.search_episodes(arguments),
+            "list_files" => self.list_files(arguments),
+            _ => return Err(RpcError::invalid_params(format!("Unknown tool: {name}"))),
+        };
+
+        // ツール側の失敗はエージェントが読んで対処できるよう結果として返す
+        Ok(match output {
+            Ok(text) => json!({ "content": [{ "type": "text", "text": text }], "isError": false }),
+            Err(err) => json!({ "content": [{ "type": "text", "text": format!("{err:#}") }], "isError": true }),
+        })
+    }
+
+    fn list_narratives(&self, arguments: &Value) -> Result<String> {
+        let narratives = self.store.search(&NarrativeFilter {
+            query: arguments["query"].as_str().map(String::from),
+            tags: string_list(&arguments["tags"]),
+            folder: arguments["folder"].as_str().map(String::from),
+            template: arguments["templates"].as_bool(),
+            sort: NarrativeSort::Name,
+        });
+
+        let summaries: Vec<Value> = narratives
+            .iter()
+            .map(|narrative| {
+                json!({
+                    "id": narrative.id,
+                    "name": narrative.name,
+                    "description": narrative.description,
+                    "is_template": narrative.is_template,
+                    "tags": narrative.tags,
+                    "folder": narrative.folder,
+                    "items": narrative.contexts.len(),
+                    "updated_at": narrative.updated_at,
+                })
+            })
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&summaries)?)
+    }
+
+    fn resolve_narrative(&self, arguments: &Value) -> Result<String> {
+        let query = required(arguments, "narrative")?;
+        let format = match arguments["format"].as_str() {
+            Some(format) => serde_json::from_value(json!(format))
+                .with_context(|| format!("Unknown format '{format}' (markdown, xml, json, jsonl)"))?,
+            None => ResolveFormat::Markdown,
+        };
+
+        let narrative = self.store.find(query)?;
+        let items = self.store.resolve(&self.scanner, &narrative, true)?;
+        render::render(&narrative, &items, format, &narrative.output)
+    }
+
+    fn diff_narrative(&self, arguments: &Value) -> Result<String> {
+        let query = required(arguments, "narrative")?;
+        let narrative = self.store.find(query)?;
+
+        match self.store.resolve_diff(&self.scanner, &narrative)? {
+            Some(diff) if diff.changed => Ok(serde_json::to_string_pretty(&diff)?),
+            Some(diff) => Ok(format!(
+                "No changes in '{}' since it was resolved at {}",
+                diff.name,
+                diff.recorded_at.format("%Y-%m-%d %H:%M:%S UTC")
+            )),
+            None => anyhow::bail!(
+                "'{}' has not been resolved yet; call resolve_narrative first",
+                narrative.name
+            ),
+        }
+    }
+
+    fn read_profile(&self, arguments: &Value) -> Result<String> {
+        let name = required(arguments, "name")?;
+        let profile = find_context::profile(&self.scanner, &self.aliases, name)?;
+
+        if profile.substituted {
+            Ok(format!(
+                "Profile '{}' not found. Using {}:\n\n{}",
+                name, profile.path, profile.content
+            ))
+        } else {
+            Ok(profile.content)
+        }
+    }
+
+    fn read_section(&self, arguments: &Value) -> Result<String> {
+        let path = required(arguments, "path")?;
+        let section = required(arguments, "section")?;
+
+        let Some(full_path) = self.scanner.file_path(path).filter(|_| !is_internal(path)) else {
+            let files = self.file_paths();
+            let suggestions =
+                lookup::similar_paths(path, files.iter().map(String::as_str), 5);
+            anyhow::bail!("File not found: {}{}", path, did_you_mean(&suggestions));
+        };
+
+        let content = fs::read_to_string(full_path)
+            .with_context(|| format!("Failed to read {path}"))?;
+        match markdown::find_section(&content, section) {
+            Some(section_content) => Ok(section_content.to_string()),
+            None => {
+                let suggestions =
+                    lookup::similar_headings(section, markdown::headings(&content), 5);
+                anyhow::bail!(
+                    "Section '{}' not found in {}{}",
+                    section,
+                    path,
+                    did_you_mean(&suggestions)
+                )
+            }
+        }
+    }
+
+    fn search_episodes(&self, arguments: &Value) -> Result<String> {
+        let character = required(arguments, "character")?;
+        let (resolved, episodes) = find_context::episodes(&self.root, &self.aliases, character)?;
+
+        if episodes.is_empty() {
+            return Ok(format!(
+                "No episodes found featuring the character: {resolved} (resolved from: {character})"
+            ));
+        }
+        Ok(serde_json::to_string_pretty(&episodes)?)
+    }
+
+    fn list_files(&self, arguments: &Value) -> Result<String> {
+        let limit = arguments["limit"].as_u64().map_or(100, |limit| limit as usize);
+
+        if let Some(query) = arguments["query"].as_str().filter(|query| !query.trim().is_empty()) {
+            let mut results = browse::search(&self.scanner, query, limit);
+            results.retain(|result| !is_internal(&result.path));
+            return Ok(serde_json::to_string_pretty(&results)?);
+        }
+
+        let prefix = arguments["prefix"].as_str().unwrap_or_default();
+        let mut files: Vec<Value> = self
+            .scanner
+            .get_file_map()
+            .iter()
+            .filter(|entry| {
+                !entry.value().is_directory
+                    && entry.key().starts_with(prefix)
+                    && !is_internal(entry.key())
+            })
+            .map(|entry| {
+                let info = entry.value();
+                json!({ "path": info.path, "chars": info.chars, "size": info.size })
+            })
+            .collect();
+        files.sort_by(|a, b| a["path"].as_str().cmp(&b["path"].as_str()));
+        files.truncate(limit);
+
+        Ok(serde_json::to_string_pretty(&files)?)
+    }
+
+    /// プロジェクトのファイル（`file://` の絶対パス）と、解決済みナラティブ（`weaver://narrative/<id>`）
+    fn list_resources(&self, params: &Value) -> Result<Value, RpcError> {
+        let offset = match params["cursor"].as_str() {
+            Some(cursor) => cursor
+                .parse::<usize>()
+                .map_err(|_| RpcError::invalid_params(format!("Invalid cursor: {cursor}")))?,
+            None => 0,
+        };
+
+        let mut narratives = self.store.list();
+        narratives.sort_by(|a, b| a.name.cmp(&b.name));
+        let narrative_resources = narratives.into_iter().map(|narrative| {
+            json!({
+                "uri": format!("{NARRATIVE_URI_PREFIX}{}", narrative.id),
+                "name": narrative.name,
+                "description": narrative.description.unwrap_or_else(|| String::from("Resolved narrative context")),
+                "mimeType": "text/markdown",
+            })
+        });
+
+        let file_resources = self.file_paths().into_iter().map(|path| {
+            json!({
+                "uri": self.file_uri(&path),
+                "name": path,
+                "mimeType": mime_type(&path),
+            })
+        });
+
+        let resources: Vec<Value> = narrative_resources.chain(file_resources).collect();
+        let page: Vec<Value> = resources.iter().skip(offset).take(PAGE_SIZE).cloned().collect();
+
+        let mut result = json!({ "resources": page });
+        if offset + PAGE_SIZE < resources.len() {
+            result["nextCursor"] = json!((offset + PAGE_SIZE).to_string());
+        }
+        Ok(result)
+    }
+
+    fn read_resource(&self, params: &Value) -> Result<Value, RpcError> {
+        let uri = params["uri"]
+            .as_str()
+            .ok_or_else(|| RpcError::invalid_params("Missing resource uri"))?;
+
+        let (mime_type, text) = if let Some(id) = uri.strip_prefix(NARRATIVE_URI_PREFIX) {
+            let id = Uuid::parse_str(id)
+                .map_err(|_| RpcError::invalid_params(format!("Invalid narrative id: {id}")))?;
+            let narrative = self
+                .store
+                .get(&id)
+                .ok_or_else(|| RpcError::resource_not_found(uri))?;
+            let text = self
+                .store
+                .resolve(&self.scanner, &narrative, true)
+                .and_then(|items| render::render(&narrative, &items, ResolveFormat::Markdown, &narrative.output))
+                .map_err(|err| RpcError::invalid_params(format!("{err:#}")))?;
+            ("text/markdown", text)
+        } else {
+            let path = self
+                .relative_path(uri)
+                .filter(|path| !is_internal(path))
+                .ok_or_else(|| RpcError::resource_not_found(uri))?;
+            let full_path = self
+                .scanner
+                .file_path(&path)
+                .ok_or_else(|| RpcError::resource_not_found(uri))?;
+            let text = fs::read_to_string(full_path)
+                .map_err(|err| RpcError::invalid_params(format!("Failed to read {path}: {err}")))?;
+            (mime_type(&path), text)
+        };
+
+        Ok(json!({
+            "contents": [{ "uri": uri, "mimeType": mime_type, "text": text }]
+        }))
+    }
+
+    fn file_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self
+            .scanner
+            .get_file_map()
+            .iter()
+            .filter(|entry| !entry.value().is_directory && !is_internal(entry.key()))
+            .map(|entry| entry.key().clone())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    fn file_uri(&self, path: &str) -> String {
+        let root = self.root.canonicalize().unwrap_or_else(|_| self.root.clone());
+        match Url::from_file_path(root.join(path)) {
+            Ok(url) => url.into(),
+            Err(()) => format!("file://{}", root.join(path).display()),
+        }
+    }
+
+    fn relative_path(&self, uri: &str) -> Option<String> {
+        let absolute = Url::parse(uri).ok()?.to_file_path().ok()?;
+        let root = self.root.canonicalize().unwrap_or_else(|_| self.root.clone());
+        let relative = absolute.strip_prefix(root).ok()?;
+        Some(relative.to_string_lossy().into_owned())
+    }
+}
+
+/// `.novelenv/` 以下（ナラティブの保存先、ロック、履歴、キャッシュ）は公開しない
+fn is_internal(path: &str) -> bool {
+    Path::new(path).components().any(|component| component.as_os_str() == ".novelenv")
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "list_narratives",
+            "description": "List saved narratives (curated context bundles) with their IDs, descriptions, tags, folders and item counts.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Only narratives whose name, description, tags or folder contain this text" },
+                    "tags": { "type": "array", "items": { "type": "string" }, "description": "Only narratives that have all of these tags" },
+                    "folder": { "type": "string", "description": "Only narratives in this folder or its subfolders" },
+                    "templates": { "type": "boolean", "description": "true for templates only, false for non-templates only" }
+                }
+            }
+        },
+        {
+            "name": "resolve_narrative",
+            "description": "Resolve a narrative into the concatenated text of its files, sections and text blocks.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "narrative": { "type": "string", "description": "Narrative ID, name or unique prefix" },
+                    "format": { "type": "string", "enum": ["markdown", "xml", "json", "jsonl"], "description": "Output format (default markdown)" }
+                },
+                "required": ["narrative"]
+            }
+        },
+        {
+            "name": "diff_narrative",
+            "description": "Compare a narrative's resolved content with the last time it was resolved: which items were added, removed or changed, with a unified diff. Use it to decide whether previously loaded context is stale.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "narrative": { "type": "string", "description": "Narrative ID, name or unique prefix" }
+                },
+                "required": ["narrative"]
+            }
+        },
+        {
+            "name": "read_profile",
+            "description": "Read a character profile from character/ by name, alias from novelenv.toml, or subdirectory path (same lookup as `novel find-context profile`).",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Character name, alias or path such as main/alice" }
+                },
+                "required": ["name"]
+            }
+        },
+        {
+            "name": "read_section",
+            "description": "Read one Markdown section (from its heading to the next heading) of a project file.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Project-relative file path" },
+                    "section": { "type": "string", "description": "Heading text without the leading #" }
+                },
+                "required": ["path", "section"]
+            }
+        },
+        {
+            "name": "search_episodes",
+            "description": "Find episodes featuring a character using the episode index written by dump-episode-info.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "character": { "type": "string", "description": "Character name or alias" }
+                },
+                "required": ["character"]
+            }
+        },
+        {
+            "name": "list_files",
+            "description": "List project files, or search file names and contents when query is given.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "prefix": { "type": "string", "description": "Only files under this path prefix, e.g. character/" },
+                    "query": { "type": "string", "description": "Case-insensitive search in file names and contents" },
+                    "limit": { "type": "integer", "minimum": 1, "description": "Maximum number of results (default 100)" }
+                }
+            }
+        }
+    ])
+}
+
+fn required<'a>(arguments: &'a Value, name: &str) -> Result<&'a str> {
+    arguments[name]
+        .as_str()
+        .filter(|value| !value.is_empty())
+        .with_context(|| format!("Missing required argument '{name}'"))
+}
+
+/// 文字列の配列か、1つだけの文字列を受け付ける
+fn string_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(item) => vec![item.clone()],
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(String::from))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn did_you_mean(suggestions: &[lookup::Match]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+    let values: Vec<&str> = suggestions.iter().map(|m| m.value.as_str()).collect();
+    format!(". Did you mean one of these?\n  {}", values.join("\n  "))
+}
+
+fn mime_type(path: &str) -> &'static str {
+    if path.ends_with(".md") {
+        "text/markdown"
+    } else if path.ends_with(".json") {
+        "application/json"
+    } else {
+        "text/plain"
+    }
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
@@ -1,36 +1,414 @@
 use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NarrativeData {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
     #[serde(default)]
     pub is_template: bool,
+    /// Labels for filtering, e.g. a character or story arc
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Slash-separated folder used to group narratives (e.g. `第1部/戦闘`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
     pub contexts: Vec<ContextItem>,
+    #[serde(default)]
+    pub output: OutputOptions,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct ContextItem {
+    /// Source file path relative to the project root (empty for `Text` items)
+    #[serde(default)]
     pub path: String,
     pub include_type: IncludeType,
     pub order: usize,
+    /// Text emitted before the item's content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Text emitted after the item's content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ContextItem {
+    /// `..` や絶対パスでプロジェクトルートの外を指している
+    pub fn escapes_root(&self) -> bool {
+        !matches!(self.include_type, IncludeType::Text { .. }) && path_escapes_root(&self.path)
+    }
+}
+
+/// プロジェクトルートからの相対パスとして扱えない（`..` を含む・絶対パス）
+pub fn path_escapes_root(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type")]
 pub enum IncludeType {
     Full,
     Section { section: String },
     Lines { start: usize, end: usize },
+    Text { body: String },
+    /// A range located by its content, so it survives edits above it
+    Anchor { anchor: AnchorRange },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl IncludeType {
+    /// `weaver show` や差分の一覧に出す1行の説明
+    pub fn describe(&self, path: &str) -> String {
+        match self {
+            IncludeType::Full => path.to_string(),
+            IncludeType::Section { section } => format!("{path} § {section}"),
+            IncludeType::Lines { start, end } => format!("{path}:{start}-{end}"),
+            IncludeType::Anchor {
+                anchor: AnchorRange::Marker { name },
+            } => format!("{path} ⚓ {name}"),
+            IncludeType::Anchor {
+                anchor: AnchorRange::Text { start, end },
+            } => format!("{path} ⚓ {} … {}", snippet(start), snippet(end)),
+            IncludeType::Text { body } => {
+                let first_line = body.lines().next().unwrap_or("");
+                let preview: String = first_line.chars().take(40).collect();
+                let ellipsis = if preview.len() < body.trim_end().len() { "…" } else { "" };
+                format!("[text] {preview}{ellipsis}")
+            }
+        }
+    }
+}
+
+/// 複数行のアンカーは1行目だけを見せる
+fn snippet(text: &str) -> String {
+    let first_line = text.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+    let preview: String = first_line.trim().chars().take(20).collect();
+    format!("{preview:?}")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AnchorRange {
+    /// From the line containing `start` through the line containing the next `end`
+    Text { start: String, end: String },
+    /// Lines between `<!-- weave:NAME-start -->` and `<!-- weave:NAME-end -->`
+    Marker { name: String },
+}
+
+/// How resolved items are joined into a single context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct OutputOptions {
+    #[serde(default = "default_separator")]
+    pub separator: String,
+    #[serde(default)]
+    pub source_label: SourceLabel,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            separator: default_separator(),
+            source_label: SourceLabel::default(),
+        }
+    }
+}
+
+fn default_separator() -> String {
+    String::from("\n\n")
+}
+
+/// Prefix style announcing which file an item came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceLabel {
+    /// No label
+    #[default]
+    None,
+    /// Markdown heading: `## path`
+    Heading,
+    /// XML-style tag: `<source path="...">...</source>`
+    Tag,
+    /// HTML comment: `<!-- source: path -->`
+    Comment,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FileInfo {
     pub path: String,
     pub preview: String,
     pub is_directory: bool,
+    /// Size in bytes (0 for directories)
+    #[serde(default)]
+    pub size: u64,
+    /// Number of characters in a text file (0 for directories and binary files)
+    #[serde(default)]
+    pub chars: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A file whose name or content matches a search query
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FileSearchResult {
+    pub path: String,
+    /// The query appears in the file path
+    pub name_match: bool,
+    /// Matching lines (at most a few per file)
+    pub matches: Vec<LineMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LineMatch {
+    /// 1-based line number
+    pub line: usize,
+    pub text: String,
+}
+
+/// A markdown heading and the headings nested under it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OutlineHeading {
+    pub level: usize,
+    pub text: String,
+    /// 1-based line of the heading
+    pub line: usize,
+    /// Last line of the section, before the next heading of any level (subsections are not included)
+    pub end_line: usize,
+    pub children: Vec<OutlineHeading>,
+}
+
+/// A range of lines read from a project file
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FileContent {
+    pub path: String,
+    pub start: usize,
+    pub end: usize,
+    pub total_lines: usize,
+    pub content: String,
+}
+
+/// A single context item after its content has been read
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResolvedItem {
+    pub path: String,
+    pub include_type: IncludeType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+    pub bytes: usize,
+    pub chars: usize,
+    pub content: String,
+}
+
+/// Conditions for listing narratives; all given conditions must match
+#[derive(Debug, Clone, Default)]
+pub struct NarrativeFilter {
+    /// Case-insensitive text matched against names, descriptions, tags and folders
+    pub query: Option<String>,
+    /// Tags that must all be present
+    pub tags: Vec<String>,
+    /// Folder, including its subfolders
+    pub folder: Option<String>,
+    /// `Some(true)` for templates only, `Some(false)` for non-templates only
+    pub template: Option<bool>,
+    pub sort: NarrativeSort,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NarrativeSort {
+    /// Most recently updated first
+    #[default]
+    Updated,
+    /// Alphabetical by name
+    Name,
+}
+
+/// Output format of `weaver resolve` and the resolve endpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolveFormat {
+    /// Items joined into one document (separator and source label apply)
+    #[default]
+    Markdown,
+    /// Each item wrapped in `<document path=...>` tags
+    Xml,
+    /// A single JSON object listing every item with counts
+    Json,
+    /// One JSON object per line, one line per item
+    Jsonl,
+}
+
+/// A recorded change to a narrative, with the full state after the change
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Revision {
+    pub rev: u32,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub diff: ContextsDiff,
+    pub snapshot: NarrativeData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RevisionSummary {
+    pub rev: u32,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub added: usize,
+    pub removed: usize,
+    pub reordered: bool,
+}
+
+impl From<&Revision> for RevisionSummary {
+    fn from(revision: &Revision) -> Self {
+        Self {
+            rev: revision.rev,
+            timestamp: revision.timestamp,
+            message: revision.message.clone(),
+            added: revision.diff.added.len(),
+            removed: revision.diff.removed.len(),
+            reordered: revision.diff.reordered,
+        }
+    }
+}
+
+/// Difference between two versions of a narrative's `contexts`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ContextsDiff {
+    pub added: Vec<ContextItem>,
+    pub removed: Vec<ContextItem>,
+    /// Items present in both versions appear in a different order
+    pub reordered: bool,
+}
+
+/// Changes in a narrative's resolved content since its last recorded resolve
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ResolveDiff {
+    pub id: Uuid,
+    pub name: String,
+    /// Narrative revision at the recorded resolve
+    pub recorded_rev: u32,
+    pub recorded_at: chrono::DateTime<chrono::Utc>,
+    pub current_rev: u32,
+    /// Whether any item was added, removed or now resolves to different content
+    pub changed: bool,
+    /// Current items in order, followed by the removed ones
+    pub items: Vec<ItemChange>,
+    /// Unified diff of the changed items' content
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ItemChange {
+    /// Item as printed by `weaver show`
+    pub label: String,
+    pub status: ChangeStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_chars: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_chars: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeStatus {
+    Unchanged,
+    Modified,
+    Added,
+    Removed,
+}
+
+/// A stale reference found in one narrative item
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ValidationIssue {
+    pub narrative_id: Uuid,
+    pub narrative_name: String,
+    /// 1-based position of the item in `order` (as printed by `weaver show`)
+    pub position: usize,
+    pub path: String,
+    pub problem: Problem,
+    /// Closest existing paths or headings, best first
+    pub suggestions: Vec<String>,
+    /// Replacement applied by `weaver check --fix`, if one candidate is clearly the best
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<ItemFix>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Problem {
+    MissingFile,
+    /// The path is absolute or leaves the project with `..`; resolve refuses it
+    PathOutsideRoot,
+    MissingSection { section: String },
+    AnchorNotFound { reason: String },
+    /// The anchor matches more than one place; the first match is used
+    AmbiguousAnchor,
+    LinesOutOfRange { start: usize, end: usize, line_count: usize },
+}
+
+/// New values for the broken fields of an item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ItemFix {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_type: Option<IncludeType>,
+}
+
+/// A portable set of narratives written by `weaver export`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    #[serde(flatten)]
+    pub manifest: BundleManifest,
+    pub narratives: Vec<NarrativeData>,
+}
+
+/// Identifies a bundle and where it came from (`bundle.toml` in directory bundles)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    /// Always `context-weaver-bundle`
+    pub format: String,
+    pub version: u32,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    /// Name of the project the narratives were exported from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// File layout of an exported bundle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BundleFormat {
+    /// A single JSON file
+    #[default]
+    Json,
+    /// A directory with `bundle.toml` and one TOML file per narrative
+    Toml,
+}
+
+/// What to do when an imported narrative has the same ID or name as an existing one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConflictPolicy {
+    /// Keep the existing narrative
+    #[default]
+    Skip,
+    /// Import under a new name and ID
+    Rename,
+    /// Replace the existing narrative (recorded as a revision)
+    Overwrite,
+}
+
+/// `GET /api/server` で UI に伝えるサーバーの動作モード
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ServerInfo {
+    pub read_only: bool,
 }
@@ -0,0 +1,272 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::{any, delete, get, post, put},
+    Router,
+};
+use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::services::ServeDir;
+
+use crate::config::WeaverConfig;
+use crate::handlers::{self, ApiError, ErrorCode};
+use crate::models::ServerInfo;
+use crate::services::{self, EventBus, FileScanner, NarrativeStore};
+
+const TOKEN_ENV: &str = "WEAVER_TOKEN";
+const DEFAULT_HOST: &str = "127.0.0.1";
+
+/// コマンドラインで指定された `weaver serve` のオプション（未指定のものは設定ファイルに従う）
+pub struct ServeArgs {
+    pub port: u16,
+    pub host: Option<String>,
+    pub read_only: bool,
+    pub cors_origins: Vec<String>,
+}
+
+pub async fn serve(path: PathBuf, args: ServeArgs) -> Result<()> {
+    let config = WeaverConfig::load(&path)?;
+    let server = &config.server;
+
+    let host = args
+        .host
+        .or_else(|| server.host.clone())
+        .unwrap_or_else(|| DEFAULT_HOST.to_string());
+    let read_only = args.read_only || server.read_only;
+    let token = env::var(TOKEN_ENV)
+        .ok()
+        .or_else(|| server.token.clone())
+        .filter(|token| !token.is_empty());
+    let mut cors_origins = server.cors_origins.clone();
+    cors_origins.extend(args.cors_origins);
+
+    let scanner = FileScanner::new(path.clone());
+    scanner.scan()?;
+
+    let events = EventBus::new();
+    let store = NarrativeStore::open(path, &config)?.with_events(events.clone());
+
+    services::watcher::spawn(scanner.clone(), store.clone(), events.clone())?;
+
+    let static_dir = get_static_dir();
+    tracing::info!("Static directory: {}", static_dir.display());
+
+    let mut api = api_routes(scanner, store, events, read_only);
+    if let Some(token) = &token {
+        api = api.layer(middleware::from_fn_with_state(
+            Arc::new(token.clone()),
+            require_token,
+        ));
+    }
+
+    // トークンの検査で弾いた 401 も JSON 本文にするため、エラー変換は最後に重ねる
+    let api = api.layer(middleware::map_response(handlers::json_errors));
+
+    let mut app = api
+        .route("/api/openapi.json", get(handlers::openapi::openapi_json))
+        .nest_service("/", ServeDir::new(static_dir));
+    if let Some(cors) = cors_layer(&cors_origins)? {
+        app = app.layer(cors);
+    }
+
+    let addr = format!("{host}:{}", args.port);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .with_context(|| format!("Failed to bind {addr}"))?;
+
+    let is_loopback = host == "localhost"
+        || host
+            .parse::<IpAddr>()
+            .map(|ip| ip.is_loopback())
+            .unwrap_or(false);
+    if !is_loopback && token.is_none() {
+        tracing::warn!(
+            "Listening on {} without an access token; anyone on the network can read the project (set {} or [context_weaver.server] token)",
+            host,
+            TOKEN_ENV
+        );
+    }
+    if read_only {
+        tracing::info!("Read-only mode: narratives cannot be created, edited or deleted");
+    }
+
+    match &token {
+        // UI はクエリのトークンを読み取って以降の API 呼び出しに使う
+        Some(token) => tracing::info!(
+            "Server running at http://{}/?token={}",
+            addr,
+            form_urlencoded::byte_serialize(token.as_bytes()).collect::<String>()
+        ),
+        None => tracing::info!("Server running at http://{}", addr),
+    }
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// 読み取り専用モードでは作成・更新・削除・復元のルートを登録しない
+fn api_routes(
+    scanner: FileScanner,
+    store: NarrativeStore,
+    events: EventBus,
+    read_only: bool,
+) -> Router {
+    let files = Router::new()
+        .route("/api/files", get(handlers::list_files))
+        .route("/api/files/refresh", post(handlers::refresh_files))
+        .route("/api/files/search", get(handlers::search_files))
+        .route("/api/files/:path/outline", get(handlers::file_outline))
+        .route("/api/files/:path/content", get(handlers::file_content))
+        .with_state(scanner.clone());
+
+    let mut narratives = Router::new()
+        .route("/api/narratives", get(handlers::list_narratives))
+        .route("/api/narratives/:id", get(handlers::get_narrative))
+        .route("/api/narratives/:id/revisions", get(handlers::list_revisions))
+        .route(
+            "/api/narratives/:id/revisions/diff",
+            get(handlers::diff_revisions),
+        )
+        .route(
+            "/api/narratives/:id/revisions/:rev",
+            get(handlers::get_revision),
+        );
+    if !read_only {
+        narratives = narratives
+            .route("/api/narratives", post(handlers::create_narrative))
+            .route("/api/narratives/:id", put(handlers::update_narrative))
+            .route("/api/narratives/:id", delete(handlers::delete_narrative))
+            .route(
+                "/api/narratives/:id/revisions/:rev/restore",
+                post(handlers::restore_revision),
+            );
+    }
+
+    // 読み取り専用モードでは解決結果をキャッシュに記録しない
+    let resolve = Router::new()
+        .route(
+            "/api/narratives/:id/resolve",
+            get(handlers::resolve_narrative),
+        )
+        .with_state((store.clone(), scanner.clone(), ServerInfo { read_only }));
+
+    Router::new()
+        .merge(files)
+        .merge(narratives.with_state(store.clone()))
+        .merge(resolve)
+        .route("/api/narratives/:id/diff", get(handlers::resolve_diff))
+        .route(
+            "/api/narratives/validate",
+            get(handlers::validate_narratives),
+        )
+        .with_state((store, scanner))
+        .route("/api/events", get(handlers::stream_events))
+        .with_state(events)
+        .route("/api/server", get(handlers::server_info))
+        .with_state(ServerInfo { read_only })
+        .route("/api/*rest", any(handlers::api_not_found))
+}
+
+/// `Authorization: Bearer <token>` か、ヘッダーを付けられない EventSource 向けの `?token=` を確かめる
+async fn require_token(
+    State(token): State<Arc<String>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let from_header = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // UI は encodeURIComponent したトークンを付けるので、デコードしてから比べる
+    let from_query = request.uri().query().and_then(|query| {
+        form_urlencoded::parse(query.as_bytes())
+            .find(|(name, _)| name == "token")
+            .map(|(_, value)| value.into_owned())
+    });
+
+    let authorized = [from_header, from_query.as_deref()]
+        .into_iter()
+        .flatten()
+        .any(|given| constant_time_eq(given.as_bytes(), token.as_bytes()));
+
+    if authorized {
+        Ok(next.run(request).await)
+    } else {
+        Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            ErrorCode::Unauthorized,
+            "Missing or invalid access token",
+        ))
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// オリジンの指定がなければ CORS ヘッダーを付けない（同一オリジンの UI だけが API を使える）
+fn cors_layer(origins: &[String]) -> Result<Option<CorsLayer>> {
+    if origins.is_empty() {
+        return Ok(None);
+    }
+
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        let values = origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin.trim_end_matches('/'))
+                    .with_context(|| format!("Invalid CORS origin: {origin}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        AllowOrigin::list(values)
+    };
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE, header::ACCEPT]),
+    ))
+}
+
+fn get_static_dir() -> PathBuf {
+    // 実行ファイルの場所を取得
+    let current_exe = env::current_exe()
+        .expect("Failed to get current executable path");
+
+    let exe_dir = current_exe.parent()
+        .expect("Failed to get executable directory");
+
+    // まず同じディレクトリ内でstaticフォルダを探す（インストール済み環境）
+    let installed_static = exe_dir.join("static");
+    if installed_static.exists() {
+        return installed_static;
+    }
+
+    // 開発環境のパスを試す
+    if let Some(cli_tools_dir) = exe_dir
+        .parent() // target
+        .and_then(|p| p.parent()) // release
+        .and_then(|p| p.parent()) // context-weaver
+    {
+        let dev_static = cli_tools_dir
+            .join("context-weaver")
+            .join("static");
+        if dev_static.exists() {
+            return dev_static;
+        }
+    }
+
+    // フォールバック: 相対パス
+    PathBuf::from("cli-tools/context-weaver/static")
+}
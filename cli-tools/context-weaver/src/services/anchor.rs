@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use std::ops::Range;
+
+use crate::models::AnchorRange;
+
+/// アンカーが指す本文中の範囲（バイト位置、行単位）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Located {
+    pub range: Range<usize>,
+    /// 開始アンカーが複数箇所に現れる（最初のものを使っている）
+    pub ambiguous: bool,
+}
+
+pub fn start_marker(name: &str) -> String {
+    format!("<!-- weave:{name}-start -->")
+}
+
+pub fn end_marker(name: &str) -> String {
+    format!("<!-- weave:{name}-end -->")
+}
+
+pub fn locate(content: &str, anchor: &AnchorRange) -> Result<Located> {
+    match anchor {
+        AnchorRange::Text { start, end } => locate_text(content, start, end),
+        AnchorRange::Marker { name } => locate_marker(content, name),
+    }
+}
+
+/// 開始アンカーを含む行から、その後に最初に現れる終了アンカーを含む行まで
+fn locate_text(content: &str, start: &str, end: &str) -> Result<Located> {
+    if start.is_empty() || end.is_empty() {
+        anyhow::bail!("Anchor text must not be empty");
+    }
+
+    let start_idx = content
+        .find(start)
+        .with_context(|| format!("Start anchor {start:?} not found"))?;
+    let end_idx = content[start_idx..]
+        .find(end)
+        .map(|idx| start_idx + idx)
+        .with_context(|| format!("End anchor {end:?} not found after the start anchor"))?;
+
+    Ok(Located {
+        range: line_start(content, start_idx)..line_end(content, end_idx + end.len()),
+        ambiguous: content.matches(start).count() > 1,
+    })
+}
+
+/// マーカー行そのものは含めず、その間の行だけを取り出す
+fn locate_marker(content: &str, name: &str) -> Result<Located> {
+    let start = start_marker(name);
+    let end = end_marker(name);
+
+    let start_idx = content
+        .find(&start)
+        .with_context(|| format!("Marker {start} not found"))?;
+    let body_start = line_end(content, start_idx + start.len());
+    let end_idx = content[body_start..]
+        .find(&end)
+        .map(|idx| body_start + idx)
+        .with_context(|| format!("Marker {end} not found after {start}"))?;
+
+    Ok(Located {
+        range: body_start..line_start(content, end_idx),
+        ambiguous: content.matches(&start).count() > 1,
+    })
+}
+
+/// `Lines` の範囲を、同じ範囲をただ1つに特定できるテキストアンカーに変換する
+///
+/// 開始側は範囲の先頭行から下へ、終了側は末尾行から上へ、一意になるまで行を足していく。
+/// 範囲内で一意にならない場合は `None`。
+pub fn from_lines(content: &str, start: usize, end: usize) -> Option<AnchorRange> {
+    let lines = line_spans(content);
+    if start == 0 || start > end || end > lines.len() {
+        return None;
+    }
+
+    let first = start - 1;
+    let last = end - 1;
+    let target = lines[first].start..line_end(content, lines[last].end);
+
+    let start_snippet = (first..=last)
+        .map(|until| &content[lines[first].start..lines[until].end])
+        .find(|snippet| {
+            !snippet.trim().is_empty()
+                && content.find(snippet) == Some(lines[first].start)
+                && content.matches(snippet).count() == 1
+        })?;
+
+    (first..=last).rev().find_map(|from| {
+        let end_snippet = &content[lines[from].start..lines[last].end];
+        if end_snippet.trim().is_empty() {
+            return None;
+        }
+        let located = locate_text(content, start_snippet, end_snippet).ok()?;
+        (located.range == target).then(|| AnchorRange::Text {
+            start: start_snippet.to_string(),
+            end: end_snippet.to_string(),
+        })
+    })
+}
+
+/// 各行の本文部分（改行を除く）のバイト範囲
+fn line_spans(content: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let body = line.trim_end_matches('\n').trim_end_matches('\r');
+        spans.push(offset..offset + body.len());
+        offset += line.len();
+    }
+    spans
+}
+
+fn line_start(content: &str, idx: usize) -> usize {
+    content[..idx].rfind('\n').map_or(0, |pos| pos + 1)
+}
+
+fn line_end(content: &str, idx: usize) -> usize {
+    content[idx..].find('\n').map_or(content.len(), |pos| idx + pos + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# 人物\n太郎は剣士。\n花子は魔法使い。\n次郎は商人。\n";
+
+    fn text(start: &str, end: &str) -> AnchorRange {
+        AnchorRange::Text {
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    fn located_text<'a>(content: &'a str, anchor: &AnchorRange) -> &'a str {
+        &content[locate(content, anchor).unwrap().range]
+    }
+
+    #[test]
+    fn unique_text_anchor_covers_whole_lines() {
+        let located = locate(DOC, &text("剣士", "花子")).unwrap();
+        assert!(!located.ambiguous);
+        assert_eq!(&DOC[located.range], "太郎は剣士。\n花子は魔法使い。\n");
+    }
+
+    #[test]
+    fn repeated_start_anchor_is_ambiguous_and_uses_the_first() {
+        let content = "太郎\n一\n太郎\n二\n";
+        let located = locate(content, &text("太郎", "二")).unwrap();
+        assert!(located.ambiguous);
+        assert_eq!(&content[located.range], content);
+    }
+
+    #[test]
+    fn missing_anchors_are_errors() {
+        assert!(locate(DOC, &text("三郎", "商人")).is_err());
+        // 終了アンカーは開始アンカーより後ろにしか探さない
+        assert!(locate(DOC, &text("花子", "剣士")).is_err());
+        assert!(locate(DOC, &text("", "商人")).is_err());
+
+        let marker = AnchorRange::Marker {
+            name: "cast".to_string(),
+        };
+        assert!(locate(DOC, &marker).is_err());
+        assert!(locate("<!-- weave:cast-start -->\n本文\n", &marker).is_err());
+    }
+
+    #[test]
+    fn marker_anchor_excludes_the_marker_lines() {
+        let content = "前\n<!-- weave:cast-start -->\n太郎\n花子\n<!-- weave:cast-end -->\n後\n";
+        let marker = AnchorRange::Marker {
+            name: "cast".to_string(),
+        };
+        assert_eq!(located_text(content, &marker), "太郎\n花子\n");
+    }
+
+    #[test]
+    fn from_lines_round_trips_and_survives_edits() {
+        let anchor = from_lines(DOC, 2, 3).unwrap();
+        assert_eq!(located_text(DOC, &anchor), "太郎は剣士。\n花子は魔法使い。\n");
+
+        let edited = format!("前書き\n{DOC}");
+        assert_eq!(located_text(&edited, &anchor), "太郎は剣士。\n花子は魔法使い。\n");
+    }
+
+    #[test]
+    fn from_lines_grows_snippets_until_unique() {
+        let content = "a\nb\na\nc\nb\nc\n";
+        let anchor = from_lines(content, 3, 4).unwrap();
+        assert_eq!(anchor, text("a\nc", "c"));
+        assert_eq!(located_text(content, &anchor), "a\nc\n");
+    }
+
+    #[test]
+    fn from_lines_gives_up_on_duplicated_or_invalid_ranges() {
+        let content = "x\ny\nx\ny\n";
+        assert_eq!(from_lines(content, 3, 4), None);
+        assert_eq!(from_lines(DOC, 0, 1), None);
+        assert_eq!(from_lines(DOC, 3, 2), None);
+        assert_eq!(from_lines(DOC, 4, 5), None);
+    }
+}
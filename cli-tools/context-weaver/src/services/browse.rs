@@ -0,0 +1,141 @@
+use anyhow::Result;
+use std::fs;
+
+use crate::models::{FileContent, FileSearchResult, LineMatch, OutlineHeading};
+use crate::services::{markdown, FileScanner};
+
+/// 1ファイルあたりに返す一致行の数
+const MAX_LINE_MATCHES: usize = 5;
+/// これより大きいファイルは本文を検索しない
+const MAX_SEARCH_BYTES: u64 = 2 * 1024 * 1024;
+const EXCERPT_CHARS: usize = 80;
+
+/// ファイル名と本文を大文字小文字を区別せずに検索する
+///
+/// ファイル名に一致したもの、本文の一致行が多いものから順に返す。
+pub fn search(scanner: &FileScanner, query: &str, limit: usize) -> Vec<FileSearchResult> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let files: Vec<(String, u64)> = scanner
+        .get_file_map()
+        .iter()
+        .filter(|entry| !entry.value().is_directory)
+        .map(|entry| (entry.key().clone(), entry.value().size))
+        .collect();
+
+    let mut results: Vec<FileSearchResult> = files
+        .into_iter()
+        .filter_map(|(path, size)| {
+            let name_match = path.to_lowercase().contains(&query);
+            let matches = if size <= MAX_SEARCH_BYTES {
+                fs::read_to_string(scanner.root_path().join(&path))
+                    .map(|content| line_matches(&content, &query))
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+
+            (name_match || !matches.is_empty()).then_some(FileSearchResult {
+                path,
+                name_match,
+                matches,
+            })
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        b.name_match
+            .cmp(&a.name_match)
+            .then(b.matches.len().cmp(&a.matches.len()))
+            .then(a.path.cmp(&b.path))
+    });
+    results.truncate(limit);
+    results
+}
+
+fn line_matches(content: &str, query: &str) -> Vec<LineMatch> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let lower = line.to_lowercase();
+            let byte_idx = lower.find(query)?;
+            Some(LineMatch {
+                line: index + 1,
+                text: excerpt(&lower, line, byte_idx),
+            })
+        })
+        .take(MAX_LINE_MATCHES)
+        .collect()
+}
+
+/// 一致箇所が見えるように前後を切り詰める
+fn excerpt(lower: &str, line: &str, byte_idx: usize) -> String {
+    let chars: Vec<char> = line.trim_end().chars().collect();
+    if chars.len() <= EXCERPT_CHARS {
+        return chars.into_iter().collect::<String>().trim_start().to_string();
+    }
+
+    let match_char = lower[..byte_idx].chars().count().min(chars.len());
+    let start = match_char.saturating_sub(EXCERPT_CHARS / 3);
+    let end = (start + EXCERPT_CHARS).min(chars.len());
+
+    let mut text: String = chars[start..end].iter().collect();
+    if start > 0 {
+        text.insert(0, '…');
+    }
+    if end < chars.len() {
+        text.push('…');
+    }
+    text
+}
+
+/// ファイルの見出しツリー（ファイルマップにないパスは `None`）
+pub fn outline(scanner: &FileScanner, path: &str) -> Result<Option<Vec<OutlineHeading>>> {
+    let Some(full_path) = scanner.file_path(path) else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(full_path)?;
+    Ok(Some(markdown::outline(&content)))
+}
+
+/// `start` 行目から `end` 行目まで（1始まり、両端を含む）を読む
+///
+/// 省略時はファイルの先頭・末尾。範囲がファイルを超える分は切り詰める。
+pub fn content(
+    scanner: &FileScanner,
+    path: &str,
+    start: Option<usize>,
+    end: Option<usize>,
+) -> Result<Option<FileContent>> {
+    let Some(full_path) = scanner.file_path(path) else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(full_path)?;
+    let lines: Vec<&str> = content.lines().collect();
+    let total_lines = lines.len();
+
+    let start = start.unwrap_or(1).max(1);
+    let end = end.unwrap_or(total_lines).min(total_lines);
+
+    let mut text = String::new();
+    if start <= end {
+        for line in &lines[start - 1..end] {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+
+    Ok(Some(FileContent {
+        path: path.to_string(),
+        start,
+        end,
+        total_lines,
+        content: text,
+    }))
+}
@@ -0,0 +1,307 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::models::{
+    Bundle, BundleFormat, BundleManifest, ConflictPolicy, IncludeType, NarrativeData,
+};
+use crate::services::{lookup, FileScanner, NarrativeStore};
+
+pub const FORMAT: &str = "context-weaver-bundle";
+pub const VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "bundle.toml";
+
+pub fn new_bundle(narratives: Vec<NarrativeData>, source: Option<String>) -> Bundle {
+    Bundle {
+        manifest: BundleManifest {
+            format: FORMAT.to_string(),
+            version: VERSION,
+            exported_at: chrono::Utc::now(),
+            source,
+        },
+        narratives,
+    }
+}
+
+pub fn to_json(bundle: &Bundle) -> Result<String> {
+    Ok(serde_json::to_string_pretty(bundle)? + "\n")
+}
+
+pub fn to_toml(narrative: &NarrativeData) -> Result<String> {
+    toml::to_string_pretty(narrative)
+        .with_context(|| format!("Failed to serialize narrative '{}'", narrative.name))
+}
+
+pub fn from_toml(content: &str) -> Result<NarrativeData> {
+    Ok(toml::from_str(content)?)
+}
+
+pub fn save(bundle: &Bundle, output: &Path, format: BundleFormat) -> Result<()> {
+    match format {
+        BundleFormat::Json => fs::write(output, to_json(bundle)?)
+            .with_context(|| format!("Failed to write {}", output.display())),
+        BundleFormat::Toml => save_directory(bundle, output),
+    }
+}
+
+/// `bundle.toml` と、ナラティブ名から付けたファイル名の TOML を書き出す
+fn save_directory(bundle: &Bundle, output: &Path) -> Result<()> {
+    if output.exists() && fs::read_dir(output)?.next().is_some() {
+        if !output.join(MANIFEST_FILE).exists() {
+            anyhow::bail!("{} exists and is not a bundle directory", output.display());
+        }
+        // 前回の書き出しで残ったナラティブを消してから書き直す
+        for entry in fs::read_dir(output)? {
+            let path = entry?.path();
+            if path.extension() == Some(OsStr::new("toml")) {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+    fs::create_dir_all(output).with_context(|| format!("Failed to create {}", output.display()))?;
+
+    let manifest = toml::to_string_pretty(&bundle.manifest)?;
+    fs::write(output.join(MANIFEST_FILE), manifest)?;
+
+    let mut used = HashSet::new();
+    used.insert(String::from("bundle"));
+    for narrative in &bundle.narratives {
+        let stem = file_stem(narrative, &mut used);
+        fs::write(output.join(format!("{stem}.toml")), to_toml(narrative)?)?;
+    }
+
+    Ok(())
+}
+
+/// JSON ファイルとディレクトリのどちらの形式も読み込む
+pub fn load(path: &Path) -> Result<Bundle> {
+    let bundle = if path.is_dir() {
+        load_directory(path)?
+    } else {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("{} is not a valid bundle", path.display()))?
+    };
+
+    if bundle.manifest.format != FORMAT {
+        anyhow::bail!("{} is not a {} file", path.display(), FORMAT);
+    }
+    if bundle.manifest.version > VERSION {
+        anyhow::bail!(
+            "{} was written by a newer weaver (bundle version {}, supported up to {})",
+            path.display(),
+            bundle.manifest.version,
+            VERSION
+        );
+    }
+
+    Ok(bundle)
+}
+
+fn load_directory(dir: &Path) -> Result<Bundle> {
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("{} is missing", manifest_path.display()))?;
+    let manifest: BundleManifest = toml::from_str(&manifest)
+        .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    paths.sort();
+
+    let mut narratives = Vec::new();
+    for path in paths {
+        if path.extension() != Some(OsStr::new("toml")) || path == manifest_path {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        let narrative =
+            from_toml(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+        narratives.push(narrative);
+    }
+
+    Ok(Bundle {
+        manifest,
+        narratives,
+    })
+}
+
+fn file_stem(narrative: &NarrativeData, used: &mut HashSet<String>) -> String {
+    let mut slug = String::new();
+    for ch in narrative.name.chars() {
+        if ch.is_alphanumeric() || ch == '_' {
+            slug.push(ch);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-').to_string();
+
+    let short_id = narrative.id.to_string()[..8].to_string();
+    let stem = if slug.is_empty() {
+        short_id
+    } else if used.contains(&slug) {
+        format!("{slug}-{short_id}")
+    } else {
+        slug
+    };
+
+    used.insert(stem.clone());
+    stem
+}
+
+pub struct ImportOptions {
+    pub on_conflict: ConflictPolicy,
+    /// Path prefixes to rewrite, applied before looking for moved files
+    pub path_map: Vec<(String, String)>,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportAction {
+    Created,
+    Renamed(String),
+    Overwritten,
+    Skipped,
+}
+
+pub struct ImportOutcome {
+    pub name: String,
+    pub action: ImportAction,
+    /// Paths rewritten to match the target project (old, new)
+    pub remapped: Vec<(String, String)>,
+    /// Paths that do not exist in the target project
+    pub missing: Vec<String>,
+}
+
+/// バンドルのナラティブを取り込む
+///
+/// 同じ ID か同じ名前のナラティブがあれば衝突として `on_conflict` に従う。
+/// パスは `path_map` で書き換えたうえで、見つからないものは同名ファイルが1つだけあればそこへ付け替える。
+pub fn import(
+    store: &NarrativeStore,
+    scanner: &FileScanner,
+    bundle: Bundle,
+    options: &ImportOptions,
+) -> Result<Vec<ImportOutcome>> {
+    let files: Vec<String> = scanner
+        .get_file_map()
+        .iter()
+        .filter(|entry| !entry.value().is_directory)
+        .map(|entry| entry.key().clone())
+        .collect();
+    let message = match &bundle.manifest.source {
+        Some(source) => format!("Import from {source}"),
+        None => String::from("Import from bundle"),
+    };
+
+    let mut names: HashSet<String> = store.list().into_iter().map(|n| n.name).collect();
+    let mut outcomes = Vec::new();
+
+    for mut narrative in bundle.narratives {
+        let (remapped, missing) = remap_paths(&mut narrative, &options.path_map, &files);
+
+        let existing = store.get(&narrative.id).or_else(|| {
+            store
+                .list()
+                .into_iter()
+                .find(|n| n.name == narrative.name)
+        });
+
+        let action = match (existing, options.on_conflict) {
+            (None, _) => {
+                if !options.dry_run {
+                    store.import(narrative.clone(), message.clone())?;
+                }
+                ImportAction::Created
+            }
+            (Some(_), ConflictPolicy::Skip) => ImportAction::Skipped,
+            (Some(existing), ConflictPolicy::Overwrite) => {
+                if !options.dry_run {
+                    store.update(&existing.id, narrative.clone(), Some(message.clone()))?;
+                }
+                ImportAction::Overwritten
+            }
+            (Some(_), ConflictPolicy::Rename) => {
+                let name = unique_name(&narrative.name, &names);
+                if !options.dry_run {
+                    store.create(NarrativeData {
+                        id: Uuid::nil(),
+                        name: name.clone(),
+                        ..narrative.clone()
+                    })?;
+                }
+                ImportAction::Renamed(name)
+            }
+        };
+
+        if let ImportAction::Renamed(name) = &action {
+            names.insert(name.clone());
+        }
+        names.insert(narrative.name.clone());
+
+        outcomes.push(ImportOutcome {
+            name: narrative.name,
+            action,
+            remapped,
+            missing,
+        });
+    }
+
+    Ok(outcomes)
+}
+
+type Remapped = Vec<(String, String)>;
+
+fn remap_paths(
+    narrative: &mut NarrativeData,
+    path_map: &[(String, String)],
+    files: &[String],
+) -> (Remapped, Vec<String>) {
+    let mut remapped = Vec::new();
+    let mut missing = Vec::new();
+
+    for item in narrative.contexts.iter_mut() {
+        if matches!(item.include_type, IncludeType::Text { .. }) {
+            continue;
+        }
+
+        let original = item.path.clone();
+        let mut path = original.clone();
+        if let Some((from, to)) = path_map.iter().find(|(from, _)| path.starts_with(from.as_str())) {
+            path = format!("{}{}", to, &path[from.len()..]);
+        }
+
+        if !files.contains(&path) {
+            // 別のディレクトリ構成でも、同じファイル名が1つだけならそれを使う
+            let matches = lookup::similar_paths(&path, files.iter().map(String::as_str), 2);
+            match lookup::unique_best(&matches) {
+                Some(best) if best.score == 100 => path = best.value.clone(),
+                _ if !missing.contains(&path) => missing.push(path.clone()),
+                _ => {}
+            }
+        }
+
+        if path != original {
+            let pair = (original, path.clone());
+            if !remapped.contains(&pair) {
+                remapped.push(pair);
+            }
+            item.path = path;
+        }
+    }
+
+    (remapped, missing)
+}
+
+fn unique_name(name: &str, names: &HashSet<String>) -> String {
+    (2..)
+        .map(|n| format!("{name} ({n})"))
+        .find(|candidate| !names.contains(candidate))
+        .expect("an unused name always exists")
+}
@@ -0,0 +1,54 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Change notifications pushed to web UI clients
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangeEvent {
+    FileChanged { path: String },
+    FileRemoved { path: String },
+    FilesRescanned,
+    NarrativeChanged { id: Uuid },
+    NarrativeDeleted { id: Uuid },
+    NarrativesReloaded,
+}
+
+impl ChangeEvent {
+    /// SSE のイベント名（クライアント側の addEventListener に対応）
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            Self::FileChanged { .. } | Self::FileRemoved { .. } | Self::FilesRescanned => "file",
+            Self::NarrativeChanged { .. }
+            | Self::NarrativeDeleted { .. }
+            | Self::NarrativesReloaded => "narrative",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ChangeEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(256);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: ChangeEvent) {
+        // 購読者がいない場合の送信エラーは無視してよい
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+}
@@ -1,17 +1,20 @@
 use anyhow::Result;
 use dashmap::DashMap;
-use ignore::gitignore::GitignoreBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use walkdir::WalkDir;
 
-use crate::models::FileInfo;
+use crate::models::{ContextItem, FileInfo, IncludeType, NarrativeData, ResolvedItem};
+use crate::services::events::ChangeEvent;
+use crate::services::{anchor, markdown, narrative_store};
 
 #[derive(Clone)]
 pub struct FileScanner {
     root_path: PathBuf,
     file_map: Arc<DashMap<String, FileInfo>>,
+    ignore: Arc<RwLock<Gitignore>>,
 }
 
 impl FileScanner {
@@ -19,21 +22,19 @@ impl FileScanner {
         Self {
             root_path,
             file_map: Arc::new(DashMap::new()),
+            ignore: Arc::new(RwLock::new(Gitignore::empty())),
         }
     }
 
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
     pub fn scan(&self) -> Result<()> {
         // Clear existing file map before scanning
         self.file_map.clear();
-        
-        let wvignore_path = self.root_path.join(".wvignore");
-        let mut gitignore = GitignoreBuilder::new(&self.root_path);
 
-        if wvignore_path.exists() {
-            gitignore.add(&wvignore_path);
-        }
-
-        let gitignore = gitignore.build()?;
+        let gitignore = self.build_ignore()?;
 
         for entry in WalkDir::new(&self.root_path)
             .follow_links(false)
@@ -45,93 +46,248 @@ impl FileScanner {
                 }
 
                 let relative_path = path.strip_prefix(&self.root_path).unwrap_or(path);
-                !gitignore.matched(relative_path, path.is_dir()).is_ignore()
+                !narrative_store::is_internal_file(relative_path)
+                    && !gitignore.matched(relative_path, path.is_dir()).is_ignore()
             })
         {
             let entry = entry?;
             let path = entry.path();
 
             if let Ok(relative_path) = path.strip_prefix(&self.root_path) {
-                let path_str = relative_path.to_string_lossy().to_string();
+                let file_info = self.file_info(path, relative_path)?;
+                self.file_map.insert(file_info.path.clone(), file_info);
+            }
+        }
 
-                let preview = if path.is_file() {
-                    self.get_file_preview(path)?
-                } else {
-                    String::new()
-                };
+        *self.ignore.write().unwrap() = gitignore;
 
-                let file_info = FileInfo {
-                    path: path_str.clone(),
-                    preview,
-                    is_directory: path.is_dir(),
-                };
+        Ok(())
+    }
 
-                self.file_map.insert(path_str, file_info);
-            }
+    fn build_ignore(&self) -> Result<Gitignore> {
+        let wvignore_path = self.root_path.join(".wvignore");
+        let mut gitignore = GitignoreBuilder::new(&self.root_path);
+
+        if wvignore_path.exists() {
+            gitignore.add(&wvignore_path);
         }
 
-        Ok(())
+        Ok(gitignore.build()?)
     }
 
-    fn get_file_preview(&self, path: &Path) -> Result<String> {
-        if let Ok(content) = fs::read_to_string(path) {
-            let first_line = content.lines().next().unwrap_or("");
-            Ok(first_line.chars().take(30).collect())
+    fn file_info(&self, path: &Path, relative_path: &Path) -> Result<FileInfo> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified().ok().map(chrono::DateTime::<chrono::Utc>::from);
+
+        let (preview, chars) = if metadata.is_file() {
+            match fs::read_to_string(path) {
+                Ok(content) => (preview_of(&content), content.chars().count()),
+                Err(_) => (String::from("[Binary file]"), 0),
+            }
         } else {
-            Ok(String::from("[Binary file]"))
+            (String::new(), 0)
+        };
+
+        Ok(FileInfo {
+            path: relative_path.to_string_lossy().to_string(),
+            preview,
+            is_directory: metadata.is_dir(),
+            size: if metadata.is_file() { metadata.len() } else { 0 },
+            chars,
+            modified,
+        })
+    }
+
+    /// ウォッチャーから通知されたパス1件分だけファイルマップを更新する
+    ///
+    /// 変更があった場合は対応するイベントを返す。`.wvignore` 自体が変わった場合は全体を再スキャンする。
+    pub fn refresh_path(&self, path: &Path) -> Result<Option<ChangeEvent>> {
+        let relative_path = match self.relative_to_root(path) {
+            Some(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => return Ok(None),
+        };
+
+        if relative_path == Path::new(".wvignore") {
+            self.scan()?;
+            return Ok(Some(ChangeEvent::FilesRescanned));
         }
+
+        let path_str = relative_path.to_string_lossy().to_string();
+
+        if !path.exists() {
+            let prefix = format!("{path_str}/");
+            let existed = self.file_map.remove(&path_str).is_some();
+            self.file_map.retain(|key, _| !key.starts_with(&prefix));
+            return Ok(existed.then_some(ChangeEvent::FileRemoved { path: path_str }));
+        }
+
+        // `.wvignore` がなくてもストアの内部ファイルは一覧・検索・プレビューに出さない
+        if narrative_store::is_internal_file(&relative_path) {
+            return Ok(None);
+        }
+
+        let ignored = self
+            .ignore
+            .read()
+            .unwrap()
+            .matched_path_or_any_parents(&relative_path, path.is_dir())
+            .is_ignore();
+        if ignored {
+            return Ok(None);
+        }
+
+        // 新規ディレクトリ配下のファイルの場合、親ディレクトリも登録する
+        for ancestor in relative_path.ancestors().skip(1) {
+            if ancestor.as_os_str().is_empty() {
+                break;
+            }
+            let key = ancestor.to_string_lossy().to_string();
+            if !self.file_map.contains_key(&key) {
+                let info = self.file_info(&self.root_path.join(ancestor), ancestor)?;
+                self.file_map.insert(key, info);
+            }
+        }
+
+        let file_info = self.file_info(path, &relative_path)?;
+        self.file_map.insert(path_str.clone(), file_info);
+
+        Ok(Some(ChangeEvent::FileChanged { path: path_str }))
+    }
+
+    /// ウォッチャーは絶対パスで通知してくるため、正規化したルートからの相対パスも試す
+    fn relative_to_root(&self, path: &Path) -> Option<PathBuf> {
+        if let Ok(relative) = path.strip_prefix(&self.root_path) {
+            return Some(relative.to_path_buf());
+        }
+
+        let canonical_root = self.root_path.canonicalize().ok()?;
+        path.strip_prefix(canonical_root).ok().map(Path::to_path_buf)
+    }
+
+    /// ファイルマップに登録されているファイルの絶対パス（除外・未登録のものは `None`）
+    pub fn file_path(&self, relative_path: &str) -> Option<PathBuf> {
+        let info = self.file_map.get(relative_path)?;
+        (!info.is_directory).then(|| self.root_path.join(relative_path))
     }
 
     pub fn get_file_map(&self) -> &DashMap<String, FileInfo> {
         &self.file_map
     }
 
-    pub fn resolve_includes(
-        &self,
-        narrative_data: &crate::models::NarrativeData,
-    ) -> Result<String> {
-        let mut result = String::new();
+    pub fn resolve_items(&self, narrative_data: &NarrativeData) -> Result<Vec<ResolvedItem>> {
+        let mut items = narrative_data.contexts.iter().collect::<Vec<_>>();
+        items.sort_by_key(|item| item.order);
 
-        for context_item in &narrative_data.contexts {
-            let full_path = self.root_path.join(&context_item.path);
+        let mut resolved = Vec::with_capacity(items.len());
+        for context_item in items {
+            let content = self.extract_content(context_item)?;
+            resolved.push(ResolvedItem {
+                path: context_item.path.clone(),
+                include_type: context_item.include_type.clone(),
+                header: context_item.header.clone(),
+                footer: context_item.footer.clone(),
+                bytes: content.len(),
+                chars: content.chars().count(),
+                content,
+            });
+        }
 
-            if !full_path.exists() {
-                anyhow::bail!("File not found: {}", context_item.path);
-            }
+        Ok(resolved)
+    }
+
+    fn extract_content(&self, context_item: &ContextItem) -> Result<String> {
+        if let IncludeType::Text { body } = &context_item.include_type {
+            return Ok(body.clone());
+        }
+
+        let path = context_item.path.clone();
+        if context_item.escapes_root() {
+            return Err(ResolveError::PathOutsideRoot { path }.into());
+        }
+
+        let full_path = self.root_path.join(&path);
+        if !full_path.exists() {
+            return Err(ResolveError::FileNotFound { path }.into());
+        }
 
-            let content = fs::read_to_string(&full_path)?;
+        let content = match fs::read_to_string(&full_path) {
+            Ok(content) => content,
+            Err(source) => return Err(ResolveError::Unreadable { path, source }.into()),
+        };
+        let mut result = String::new();
 
-            match &context_item.include_type {
-                crate::models::IncludeType::Full => {
-                    result.push_str(&content);
+        match &context_item.include_type {
+            IncludeType::Full => {
+                result.push_str(&content);
+            }
+            IncludeType::Section { section } => match markdown::find_section(&content, section) {
+                Some(section_content) => result.push_str(section_content),
+                None => {
+                    let section = section.clone();
+                    return Err(ResolveError::SectionNotFound { path, section }.into());
                 }
-                crate::models::IncludeType::Section { section } => {
-                    // 簡易的な実装: #section_name から次の # までを抽出
-                    let section_marker = format!("# {section}");
-                    if let Some(start_idx) = content.find(&section_marker) {
-                        let section_content = &content[start_idx..];
-                        if let Some(end_idx) = section_content[1..].find("\n#") {
-                            result.push_str(&section_content[..end_idx + 1]);
-                        } else {
-                            result.push_str(section_content);
-                        }
+            },
+            IncludeType::Lines { start, end } => {
+                let lines: Vec<&str> = content.lines().collect();
+                let start_idx = start.saturating_sub(1);
+                let end_idx = (*end).min(lines.len());
+
+                if start_idx >= lines.len() {
+                    return Err(ResolveError::LinesOutOfRange {
+                        path,
+                        start: *start,
+                        end: *end,
+                        line_count: lines.len(),
                     }
+                    .into());
                 }
-                crate::models::IncludeType::Lines { start, end } => {
-                    let lines: Vec<&str> = content.lines().collect();
-                    let start_idx = start.saturating_sub(1);
-                    let end_idx = (*end).min(lines.len());
-
-                    for line in &lines[start_idx..end_idx] {
-                        result.push_str(line);
-                        result.push('\n');
-                    }
+
+                for line in &lines[start_idx..end_idx.max(start_idx)] {
+                    result.push_str(line);
+                    result.push('\n');
                 }
             }
-
-            result.push_str("\n\n");
+            IncludeType::Anchor { anchor } => match anchor::locate(&content, anchor) {
+                Ok(located) => result.push_str(&content[located.range]),
+                Err(err) => {
+                    let reason = err.to_string();
+                    return Err(ResolveError::AnchorNotFound { path, reason }.into());
+                }
+            },
+            IncludeType::Text { .. } => unreachable!(),
         }
 
         Ok(result)
     }
 }
+
+/// ナラティブのアイテムを解決できなかった理由（`anyhow::Error` から取り出して API のエラーに使う）
+#[derive(Debug, thiserror::Error)]
+pub enum ResolveError {
+    #[error("Path escapes the project root: {path}")]
+    PathOutsideRoot { path: String },
+    #[error("File not found: {path} (run `weaver check` to find stale references)")]
+    FileNotFound { path: String },
+    #[error("Failed to read {path}")]
+    Unreadable {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Section '{section}' not found in {path} (run `weaver check` to find stale references)")]
+    SectionNotFound { path: String, section: String },
+    #[error("Lines {start}-{end} are out of range: {path} has {line_count} lines")]
+    LinesOutOfRange {
+        path: String,
+        start: usize,
+        end: usize,
+        line_count: usize,
+    },
+    #[error("{reason} in {path} (run `weaver check` to find stale references)")]
+    AnchorNotFound { path: String, reason: String },
+}
+
+fn preview_of(content: &str) -> String {
+    let first_line = content.lines().next().unwrap_or("");
+    first_line.chars().take(30).collect()
+}
@@ -0,0 +1,127 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::models::path_escapes_root;
+use crate::services::{lookup, FileScanner};
+
+const PROFILE_DIR: &str = "character";
+const MAX_SUGGESTIONS: usize = 5;
+
+/// `dump-episode-info` が書き出す `episode_index.json` の1件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpisodeInfo {
+    pub episode_path: String,
+    pub characters: Vec<String>,
+    pub logline: String,
+    pub themes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    pub path: String,
+    pub content: String,
+    /// 指定した名前では見つからず、ただ1つの候補で代用した
+    pub substituted: bool,
+}
+
+/// `novel find-context profile` と同じ順序でプロファイルを探す
+///
+/// 別名 → `character/<name>.md`（サブディレクトリ指定なら基本名でも）→ 候補が1つだけならそれ。
+pub fn profile(
+    scanner: &FileScanner,
+    aliases: &HashMap<String, String>,
+    name: &str,
+) -> Result<Profile> {
+    if let Some(path) = aliases.get(name) {
+        return read_profile(scanner.root_path(), path, false);
+    }
+
+    let mut paths = vec![format!("{PROFILE_DIR}/{name}.md")];
+    if let Some((_, base_name)) = name.rsplit_once('/') {
+        paths.push(format!("{PROFILE_DIR}/{base_name}.md"));
+    }
+    if let Some(path) = paths.iter().find(|path| scanner.file_path(path).is_some()) {
+        return read_profile(scanner.root_path(), path, false);
+    }
+
+    let prefix = format!("{PROFILE_DIR}/");
+    let profiles: Vec<String> = scanner
+        .get_file_map()
+        .iter()
+        .filter(|entry| !entry.value().is_directory && entry.key().starts_with(&prefix))
+        .map(|entry| entry.key().clone())
+        .collect();
+    let matches = lookup::similar_paths(
+        &paths[0],
+        profiles.iter().map(String::as_str),
+        MAX_SUGGESTIONS,
+    );
+
+    match matches.as_slice() {
+        [only] => read_profile(scanner.root_path(), &only.value, true),
+        [] => anyhow::bail!("Profile '{}' not found (tried {})", name, paths.join(", ")),
+        _ => {
+            let suggestions: Vec<String> = matches.into_iter().map(|m| m.value).collect();
+            anyhow::bail!(
+                "Profile '{}' not found. Did you mean one of these?\n  {}",
+                name,
+                suggestions.join("\n  ")
+            )
+        }
+    }
+}
+
+fn read_profile(root: &Path, path: &str, substituted: bool) -> Result<Profile> {
+    if path_escapes_root(path) {
+        anyhow::bail!("Profile path escapes the project root: {path}");
+    }
+
+    let content = fs::read_to_string(root.join(path))
+        .with_context(|| format!("Failed to read profile {path}"))?;
+    Ok(Profile {
+        path: path.to_string(),
+        content,
+        substituted,
+    })
+}
+
+/// `novel find-context episode` と同じく、登場人物名を含むエピソードを返す
+///
+/// 別名はプロファイルのファイル名（拡張子なし）に読み替える。戻り値の1つ目は読み替えた名前。
+pub fn episodes(
+    root: &Path,
+    aliases: &HashMap<String, String>,
+    character: &str,
+) -> Result<(String, Vec<EpisodeInfo>)> {
+    let index_path = [
+        root.join(".novelenv").join("episode_index.json"),
+        root.join("episode_index.json"),
+    ]
+    .into_iter()
+    .find(|path| path.exists())
+    .context("episode_index.json not found in .novelenv/ or the project root (run `dump-episode-info` first)")?;
+
+    let resolved = aliases
+        .get(character)
+        .map(|path| {
+            let path = path.trim_end_matches(".md");
+            path.rsplit('/').next().unwrap_or(path)
+        })
+        .unwrap_or(character)
+        .to_string();
+
+    let content = fs::read_to_string(&index_path)
+        .with_context(|| format!("Failed to read {}", index_path.display()))?;
+    let all_episodes: Vec<EpisodeInfo> = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", index_path.display()))?;
+
+    let episodes = all_episodes
+        .into_iter()
+        .filter(|info| info.characters.iter().any(|c| c.contains(resolved.as_str())))
+        .collect();
+
+    Ok((resolved, episodes))
+}
@@ -0,0 +1,147 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::models::{ContextItem, ContextsDiff, NarrativeData, Revision};
+
+pub const HISTORY_DIR: &str = "history";
+
+/// ナラティブごとのリビジョン履歴（`.novelenv/history/<id>.json`）
+///
+/// 書き込みは `NarrativeStore` のロック内からのみ行う。
+#[derive(Clone)]
+pub struct HistoryStore {
+    history_dir: PathBuf,
+    limit: usize,
+}
+
+impl HistoryStore {
+    pub fn new(storage_path: &Path, limit: usize) -> Self {
+        Self {
+            history_dir: storage_path.join(".novelenv").join(HISTORY_DIR),
+            limit: limit.max(1),
+        }
+    }
+
+    fn history_file(&self, id: &Uuid) -> PathBuf {
+        self.history_dir.join(format!("{id}.json"))
+    }
+
+    pub fn list(&self, id: &Uuid) -> Result<Vec<Revision>> {
+        let path = self.history_file(id);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let json = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn get(&self, id: &Uuid, rev: u32) -> Result<Option<Revision>> {
+        Ok(self.list(id)?.into_iter().find(|revision| revision.rev == rev))
+    }
+
+    pub fn record(
+        &self,
+        before: Option<&NarrativeData>,
+        after: &NarrativeData,
+        message: Option<String>,
+    ) -> Result<Revision> {
+        let mut revisions = self.list(&after.id)?;
+
+        // 履歴機能より前から存在するナラティブは、変更前の状態を起点として残しておく
+        if let (true, Some(before)) = (revisions.is_empty(), before) {
+            revisions.push(Revision {
+                rev: 1,
+                timestamp: before.updated_at,
+                message: Some(String::from("Initial state")),
+                diff: diff_contexts(&[], &before.contexts),
+                snapshot: before.clone(),
+            });
+        }
+
+        let rev = revisions.last().map(|r| r.rev + 1).unwrap_or(1);
+
+        let previous_contexts = before.map(|n| n.contexts.as_slice()).unwrap_or(&[]);
+        let revision = Revision {
+            rev,
+            timestamp: chrono::Utc::now(),
+            message,
+            diff: diff_contexts(previous_contexts, &after.contexts),
+            snapshot: after.clone(),
+        };
+
+        revisions.push(revision.clone());
+        if revisions.len() > self.limit {
+            let excess = revisions.len() - self.limit;
+            revisions.drain(..excess);
+        }
+
+        self.write(&after.id, &revisions)?;
+        Ok(revision)
+    }
+
+    fn write(&self, id: &Uuid, revisions: &[Revision]) -> Result<()> {
+        fs::create_dir_all(&self.history_dir)
+            .with_context(|| format!("Failed to create {}", self.history_dir.display()))?;
+
+        let path = self.history_file(id);
+        let tmp_path = self.history_dir.join(format!(".{id}.json.tmp"));
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(serde_json::to_string_pretty(revisions)?.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// `order` 以外の内容が同じアイテムを同一とみなして差分を取る
+pub fn diff_contexts(before: &[ContextItem], after: &[ContextItem]) -> ContextsDiff {
+    let before_sorted = sorted_by_order(before);
+    let after_sorted = sorted_by_order(after);
+
+    let mut unmatched_after: Vec<Option<&ContextItem>> =
+        after_sorted.iter().copied().map(Some).collect();
+    let mut removed = Vec::new();
+    // 両方に存在するアイテムの、after 側での位置（before の順に並ぶ）
+    let mut common_positions = Vec::new();
+
+    for item in &before_sorted {
+        let matched = unmatched_after
+            .iter()
+            .position(|candidate| candidate.is_some_and(|c| same_item(c, item)));
+        match matched {
+            Some(position) => {
+                unmatched_after[position] = None;
+                common_positions.push(position);
+            }
+            None => removed.push((*item).clone()),
+        }
+    }
+
+    let added = unmatched_after.into_iter().flatten().cloned().collect();
+    let reordered = common_positions.windows(2).any(|pair| pair[0] > pair[1]);
+
+    ContextsDiff {
+        added,
+        removed,
+        reordered,
+    }
+}
+
+fn sorted_by_order(items: &[ContextItem]) -> Vec<&ContextItem> {
+    let mut sorted: Vec<&ContextItem> = items.iter().collect();
+    sorted.sort_by_key(|item| item.order);
+    sorted
+}
+
+fn same_item(a: &ContextItem, b: &ContextItem) -> bool {
+    a.path == b.path && a.include_type == b.include_type && a.header == b.header && a.footer == b.footer
+}
@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+/// 類似候補とその一致度（大きいほど近い）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub value: String,
+    pub score: u8,
+}
+
+/// `target` に近いファイルパスを一致度の高い順に返す
+///
+/// 採点は find-context の類似プロファイル検索と同じ。拡張子が違うファイルは候補にしない。
+pub fn similar_paths<'a>(
+    target: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<Match> {
+    let target_ext = extension(target);
+    let target_lower = strip_extension(target).to_lowercase();
+    let base_name_lower = base_name(&target_lower).to_string();
+
+    let matches = candidates
+        .into_iter()
+        .filter(|candidate| extension(candidate) == target_ext)
+        .filter_map(|candidate| {
+            let full_name_lower = strip_extension(candidate).to_lowercase();
+            let stem_lower = base_name(&full_name_lower);
+            let score = match_score(stem_lower, &full_name_lower, &base_name_lower, &target_lower);
+            (score > 0).then(|| Match {
+                value: candidate.to_string(),
+                score,
+            })
+        });
+
+    top(matches, limit)
+}
+
+/// 見出しの書き換えを想定し、`target` に近い見出しを一致度の高い順に返す
+pub fn similar_headings<'a>(
+    target: &str,
+    headings: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<Match> {
+    let target_lower = target.to_lowercase();
+
+    let matches = headings.into_iter().filter_map(|heading| {
+        let heading_lower = heading.to_lowercase();
+        let score = match_score(&heading_lower, &heading_lower, &target_lower, &target_lower);
+        (score > 0).then(|| Match {
+            value: heading.to_string(),
+            score,
+        })
+    });
+
+    top(matches, limit)
+}
+
+/// 候補が1つだけ最高点なら、それを自動修正に使ってよい候補とみなす
+pub fn unique_best(matches: &[Match]) -> Option<&Match> {
+    match matches {
+        [best] => Some(best),
+        [best, second, ..] if best.score > second.score => Some(best),
+        _ => None,
+    }
+}
+
+fn top(matches: impl Iterator<Item = Match>, limit: usize) -> Vec<Match> {
+    let mut matches: Vec<Match> = matches.collect();
+    // Sort by match score (descending) and then alphabetically
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then(a.value.cmp(&b.value)));
+    matches.dedup_by(|a, b| a.value == b.value);
+    matches.truncate(limit);
+    matches
+}
+
+fn match_score(stem_lower: &str, full_name_lower: &str, base_name_lower: &str, target_lower: &str) -> u8 {
+    // Exact base name match (highest priority)
+    if stem_lower == base_name_lower {
+        100
+    }
+    // Exact full path match
+    else if full_name_lower == target_lower {
+        95
+    }
+    // Base name starts with target
+    else if stem_lower.starts_with(base_name_lower) {
+        80
+    }
+    // Base name ends with target
+    else if stem_lower.ends_with(base_name_lower) {
+        75
+    }
+    // Target starts with base name
+    else if base_name_lower.starts_with(stem_lower) {
+        70
+    }
+    // Base name contains target
+    else if stem_lower.contains(base_name_lower) {
+        60
+    }
+    // Target contains base name
+    else if base_name_lower.contains(stem_lower) {
+        55
+    }
+    // Full name contains target
+    else if full_name_lower.contains(target_lower) {
+        40
+    }
+    // Target contains full name
+    else if target_lower.contains(full_name_lower) {
+        35
+    }
+    // Character distance match (for typos like 太朗 → 太郎)
+    else if character_distance(stem_lower, base_name_lower) <= 1 {
+        25
+    }
+    // Fuzzy character match (at least 50% common characters)
+    else if has_significant_overlap(stem_lower, base_name_lower, 0.5) {
+        20
+    } else {
+        0
+    }
+}
+
+fn extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+}
+
+fn strip_extension(path: &str) -> &str {
+    match Path::new(path).extension() {
+        Some(ext) => &path[..path.len() - ext.len() - 1],
+        None => path,
+    }
+}
+
+fn base_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Levenshtein distance between two strings, counted in characters
+fn character_distance(s1: &str, s2: &str) -> usize {
+    let chars1: Vec<char> = s1.chars().collect();
+    let chars2: Vec<char> = s2.chars().collect();
+
+    if chars1.is_empty() {
+        return chars2.len();
+    }
+    if chars2.is_empty() {
+        return chars1.len();
+    }
+
+    let mut previous: Vec<usize> = (0..=chars2.len()).collect();
+    for (i, c1) in chars1.iter().enumerate() {
+        let mut current = vec![i + 1; chars2.len() + 1];
+        for (j, c2) in chars2.iter().enumerate() {
+            current[j + 1] = if c1 == c2 {
+                previous[j]
+            } else {
+                1 + previous[j].min(previous[j + 1]).min(current[j])
+            };
+        }
+        previous = current;
+    }
+
+    previous[chars2.len()]
+}
+
+fn has_significant_overlap(s1: &str, s2: &str, threshold: f32) -> bool {
+    if s1.is_empty() || s2.is_empty() {
+        return false;
+    }
+
+    let chars1: HashSet<char> = s1.chars().collect();
+    let chars2: HashSet<char> = s2.chars().collect();
+
+    let intersection = chars1.intersection(&chars2).count();
+    let union = chars1.union(&chars2).count();
+
+    (intersection as f32 / union as f32) >= threshold
+}
@@ -0,0 +1,132 @@
+use crate::models::OutlineHeading;
+
+/// 見出しの文字列が `section` と一致する節を、見出し行から次の見出しの手前まで取り出す
+///
+/// 見出しレベルは問わず、最初に現れたものを使う。
+/// 範囲は `outline` の `line`〜`end_line` と同じになる。
+pub fn find_section<'a>(content: &'a str, section: &str) -> Option<&'a str> {
+    let headings = heading_lines(content);
+    let index = headings.iter().position(|heading| heading.text == section)?;
+    let start = headings[index].offset;
+
+    match headings.get(index + 1) {
+        Some(next) => Some(&content[start..next.offset]),
+        None => Some(&content[start..]),
+    }
+}
+
+struct HeadingLine<'a> {
+    level: usize,
+    text: &'a str,
+    /// 1始まりの行番号
+    line: usize,
+    /// 行頭のバイト位置
+    offset: usize,
+}
+
+/// 見出しの行（コードブロック内の `#` は見出しとみなさない）
+fn heading_lines(content: &str) -> Vec<HeadingLine<'_>> {
+    let mut headings = Vec::new();
+    let mut in_fence = false;
+    let mut offset = 0;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_offset = offset;
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        let level = line.chars().take_while(|&ch| ch == '#').count();
+        let text = &line[level..];
+        if (1..=6).contains(&level) && text.starts_with(' ') && !text.trim().is_empty() {
+            headings.push(HeadingLine {
+                level,
+                text: text.trim(),
+                line: index + 1,
+                offset: line_offset,
+            });
+        }
+    }
+
+    headings
+}
+
+/// 見出しの文字列（`#` と前後の空白を除いたもの）を出現順に返す
+///
+/// コードブロック内の `#` は見出しとみなさない。
+pub fn headings(content: &str) -> Vec<&str> {
+    heading_lines(content).into_iter().map(|heading| heading.text).collect()
+}
+
+/// 見出しの階層と行番号（コードブロック内の `#` は見出しとみなさない）
+///
+/// 各見出しの範囲は `find_section` と同じく次の見出し（レベルを問わない）の手前まで。
+pub fn outline(content: &str) -> Vec<OutlineHeading> {
+    let flat = heading_lines(content);
+    let total_lines = content.lines().count();
+
+    let mut headings: Vec<OutlineHeading> = flat
+        .iter()
+        .enumerate()
+        .map(|(i, heading)| OutlineHeading {
+            level: heading.level,
+            text: heading.text.to_string(),
+            line: heading.line,
+            end_line: flat.get(i + 1).map_or(total_lines, |next| next.line - 1),
+            children: Vec::new(),
+        })
+        .collect();
+
+    // 後ろから順に、直前にあるより浅い見出しの子として付け替える
+    let mut roots = Vec::new();
+    while let Some(heading) = headings.pop() {
+        match headings.iter_mut().rev().find(|parent| parent.level < heading.level) {
+            Some(parent) => parent.children.insert(0, heading),
+            None => roots.insert(0, heading),
+        }
+    }
+
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str =
+        "# 設定\n\n## Introduction\n本文\n\n```sh\n#!/bin/bash\n# コメント\n```\n\n## 人物\n太郎\n";
+
+    #[test]
+    fn section_requires_an_exact_heading() {
+        assert_eq!(find_section(DOC, "Intro"), None);
+        assert_eq!(find_section(DOC, "人物"), Some("## 人物\n太郎\n"));
+    }
+
+    #[test]
+    fn fenced_hashes_are_not_headings() {
+        assert_eq!(headings(DOC), vec!["設定", "Introduction", "人物"]);
+        assert_eq!(find_section(DOC, "コメント"), None);
+        assert!(find_section(DOC, "Introduction").unwrap().contains("# コメント"));
+    }
+
+    #[test]
+    fn outline_ranges_match_section_includes() {
+        let doc = "# 序章 前書き\n前\n## 序章\n本文\n### 場面\n細部\n";
+        let lines: Vec<&str> = doc.lines().collect();
+
+        let mut pending = outline(doc);
+        while let Some(heading) = pending.pop() {
+            let expected = lines[heading.line - 1..heading.end_line].join("\n") + "\n";
+            assert_eq!(find_section(doc, &heading.text), Some(expected.as_str()));
+            pending.extend(heading.children);
+        }
+    }
+}
@@ -1,5 +1,18 @@
+pub mod anchor;
+pub mod browse;
+pub mod bundle;
+pub mod events;
 pub mod file_scanner;
+pub mod find_context;
+pub mod history;
+pub mod lookup;
+pub mod markdown;
 pub mod narrative_store;
+pub mod render;
+pub mod resolve_cache;
+pub mod validation;
+pub mod watcher;
 
-pub use file_scanner::FileScanner;
+pub use events::EventBus;
+pub use file_scanner::{FileScanner, ResolveError};
 pub use narrative_store::NarrativeStore;
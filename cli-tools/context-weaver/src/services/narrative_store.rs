@@ -1,70 +1,396 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use dashmap::DashMap;
-use std::fs;
-use std::path::PathBuf;
+use fs2::FileExt;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::models::NarrativeData;
+use crate::config::{StorageLayout, WeaverConfig};
+use crate::models::{
+    NarrativeData, NarrativeFilter, NarrativeSort, ResolveDiff, ResolvedItem, Revision,
+};
+use crate::services::bundle;
+use crate::services::events::{ChangeEvent, EventBus};
+use crate::services::history::{HistoryStore, HISTORY_DIR};
+use crate::services::resolve_cache::{self, ResolveCache};
+use crate::services::FileScanner;
+
+const STORAGE_FILE: &str = "narratives.json";
+const STORAGE_DIR: &str = "narratives";
+const LOCK_FILE: &str = "narratives.lock";
 
 #[derive(Clone)]
 pub struct NarrativeStore {
     narratives: Arc<DashMap<Uuid, NarrativeData>>,
     storage_path: PathBuf,
+    layout: StorageLayout,
+    history: HistoryStore,
+    resolve_cache: ResolveCache,
+    events: Option<EventBus>,
+}
+
+/// `narratives.lock` に対する advisory lock（ドロップ時にファイルを閉じて解放される）
+struct StorageLock {
+    _file: File,
 }
 
 impl NarrativeStore {
-    pub fn new(storage_path: PathBuf) -> Self {
+    /// ディスク上のナラティブを読み込む
+    ///
+    /// `narratives.json` が壊れている場合はバックアップを残したうえでエラーを返す。
+    /// 空のストアで起動して上書き保存してしまうのを防ぐため。
+    /// 保存形式と履歴数は `novelenv.toml` の設定に従う。
+    pub fn open(storage_path: PathBuf, config: &WeaverConfig) -> Result<Self> {
         let store = Self {
             narratives: Arc::new(DashMap::new()),
+            history: HistoryStore::new(&storage_path, config.history_limit),
+            resolve_cache: ResolveCache::new(&storage_path),
+            layout: config.storage,
             storage_path,
+            events: None,
         };
 
-        // Load existing narratives
-        let _ = store.load_from_disk();
+        store.migrate_layout()?;
+        store.load_from_disk()?;
+
+        Ok(store)
+    }
+
+    /// 変更をイベントバスに通知するようにする（serve 時のみ使用）
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    fn publish(&self, event: ChangeEvent) {
+        if let Some(events) = &self.events {
+            events.publish(event);
+        }
+    }
 
-        store
+    pub fn is_storage_file(&self, path: &Path) -> bool {
+        match self.layout {
+            StorageLayout::File => {
+                path.file_name() == Some(OsStr::new(STORAGE_FILE))
+                    && parent_name(path) == Some(OsStr::new(".novelenv"))
+            }
+            StorageLayout::Directory => {
+                path.extension() == Some(OsStr::new("toml"))
+                    && parent_name(path) == Some(OsStr::new(STORAGE_DIR))
+                    && path.parent().and_then(parent_name) == Some(OsStr::new(".novelenv"))
+            }
+        }
+    }
+
+    /// 他プロセスによる保存を拾うために監視するディレクトリ
+    ///
+    /// `.wvignore` で `.novelenv/` を除外していても監視できるよう、ファイルスキャナーとは別に登録する。
+    pub fn watch_directories(&self) -> Result<Vec<PathBuf>> {
+        let mut directories = vec![self.novelenv_dir()?];
+        if self.layout == StorageLayout::Directory {
+            let storage_dir = self.storage_dir()?;
+            fs::create_dir_all(&storage_dir)
+                .with_context(|| format!("Failed to create {}", storage_dir.display()))?;
+            directories.push(storage_dir);
+        }
+        Ok(directories)
+    }
+
+    /// 他プロセス（CLI など）による変更をディスクから読み直す
+    pub fn reload(&self) -> Result<()> {
+        let narratives = {
+            let _lock = self.lock(false)?;
+            self.read_from_disk()?
+        };
+
+        self.replace_all(narratives);
+        self.publish(ChangeEvent::NarrativesReloaded);
+        Ok(())
     }
 
-    fn storage_file(&self) -> PathBuf {
+    fn novelenv_dir(&self) -> Result<PathBuf> {
         let novelenv_dir = self.storage_path.join(".novelenv");
         // Create .novelenv directory if it doesn't exist
         if !novelenv_dir.exists() {
-            let _ = fs::create_dir_all(&novelenv_dir);
+            fs::create_dir_all(&novelenv_dir).with_context(|| {
+                format!("Failed to create {}", novelenv_dir.display())
+            })?;
+        }
+        Ok(novelenv_dir)
+    }
+
+    fn storage_file(&self) -> Result<PathBuf> {
+        Ok(self.novelenv_dir()?.join(STORAGE_FILE))
+    }
+
+    fn storage_dir(&self) -> Result<PathBuf> {
+        Ok(self.novelenv_dir()?.join(STORAGE_DIR))
+    }
+
+    /// ディレクトリ形式に切り替えた直後は `narratives.json` の内容を1ファイルずつに分けて移す
+    ///
+    /// 元のファイルは `narratives.json.migrated` として残す。
+    fn migrate_layout(&self) -> Result<()> {
+        if self.layout != StorageLayout::Directory {
+            return Ok(());
+        }
+
+        let _lock = self.lock(true)?;
+        let file = self.storage_file()?;
+        if self.storage_dir()?.exists() || !file.exists() {
+            return Ok(());
+        }
+
+        let narratives = self.read_file(&file)?;
+        self.replace_all(narratives);
+        self.write_directory()?;
+
+        let migrated = file.with_file_name(format!("{STORAGE_FILE}.migrated"));
+        fs::rename(&file, &migrated)
+            .with_context(|| format!("Failed to rename {}", file.display()))?;
+        tracing::info!(
+            "Moved narratives to {} (the old file was kept as {})",
+            self.storage_dir()?.display(),
+            migrated.display()
+        );
+
+        Ok(())
+    }
+
+    fn lock(&self, exclusive: bool) -> Result<StorageLock> {
+        let path = self.novelenv_dir()?.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+        if exclusive {
+            file.lock_exclusive()
+        } else {
+            file.lock_shared()
         }
-        novelenv_dir.join("narratives.json")
+        .with_context(|| format!("Failed to lock {}", path.display()))?;
+
+        Ok(StorageLock { _file: file })
     }
 
-    fn save_to_disk(&self) -> Result<()> {
-        let narratives: Vec<NarrativeData> = self.list();
+    fn write_to_disk(&self) -> Result<()> {
+        match self.layout {
+            StorageLayout::File => self.write_file(),
+            StorageLayout::Directory => self.write_directory(),
+        }
+    }
+
+    fn write_file(&self) -> Result<()> {
+        let mut narratives: Vec<NarrativeData> = self.list();
+        // 差分が見やすいよう作成順に並べて保存する
+        narratives.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
         let json = serde_json::to_string_pretty(&narratives)?;
-        fs::write(self.storage_file(), json)?;
+
+        write_atomic(&self.storage_file()?, &json)
+    }
+
+    /// 内容が変わったファイルだけを書き換え、消えたナラティブのファイルを削除する
+    fn write_directory(&self) -> Result<()> {
+        let dir = self.storage_dir()?;
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+        for narrative in self.list() {
+            let path = dir.join(format!("{}.toml", narrative.id));
+            let content = bundle::to_toml(&narrative)?;
+            if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+                write_atomic(&path, &content)?;
+            }
+        }
+
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let stale = path.extension() == Some(OsStr::new("toml"))
+                && path
+                    .file_stem()
+                    .and_then(OsStr::to_str)
+                    .and_then(|stem| Uuid::parse_str(stem).ok())
+                    .is_some_and(|id| !self.narratives.contains_key(&id));
+            if stale {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+
         Ok(())
     }
 
     fn load_from_disk(&self) -> Result<()> {
-        let path = self.storage_file();
+        let narratives = {
+            let _lock = self.lock(false)?;
+            self.read_from_disk()?
+        };
+
+        self.replace_all(narratives);
+        Ok(())
+    }
+
+    fn read_from_disk(&self) -> Result<Vec<NarrativeData>> {
+        match self.layout {
+            StorageLayout::File => self.read_file(&self.storage_file()?),
+            StorageLayout::Directory => self.read_directory(),
+        }
+    }
+
+    fn read_file(&self, path: &Path) -> Result<Vec<NarrativeData>> {
         if !path.exists() {
-            return Ok(());
+            return Ok(Vec::new());
+        }
+
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        match serde_json::from_str(&json) {
+            Ok(narratives) => Ok(narratives),
+            Err(err) => Err(self.corrupt(path, err.to_string())),
+        }
+    }
+
+    fn read_directory(&self) -> Result<Vec<NarrativeData>> {
+        let dir = self.storage_dir()?;
+        if !dir.exists() {
+            return Ok(Vec::new());
         }
 
-        let json = fs::read_to_string(path)?;
-        let narratives: Vec<NarrativeData> = serde_json::from_str(&json)?;
+        let mut narratives = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension() != Some(OsStr::new("toml")) {
+                continue;
+            }
+
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            match bundle::from_toml(&content) {
+                Ok(narrative) => narratives.push(narrative),
+                Err(err) => return Err(self.corrupt(&path, format!("{err:#}"))),
+            }
+        }
 
+        Ok(narratives)
+    }
+
+    /// 壊れたファイルのバックアップを残し、その旨のエラーを作る
+    fn corrupt(&self, path: &Path, reason: String) -> anyhow::Error {
+        match self.backup_corrupt_file(path) {
+            Ok(backup) => anyhow::anyhow!(
+                "{} is corrupt ({}). A backup was saved to {}",
+                path.display(),
+                reason,
+                backup.display()
+            ),
+            Err(err) => err,
+        }
+    }
+
+    fn backup_corrupt_file(&self, path: &Path) -> Result<PathBuf> {
+        let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+        let name = path.file_name().and_then(OsStr::to_str).unwrap_or(STORAGE_FILE);
+        let backup = path.with_file_name(format!("{name}.corrupt-{timestamp}"));
+
+        if !backup.exists() {
+            fs::copy(path, &backup)
+                .with_context(|| format!("Failed to back up {}", path.display()))?;
+        }
+
+        Ok(backup)
+    }
+
+    fn replace_all(&self, narratives: Vec<NarrativeData>) {
+        // clear() すると並行する読み出しが一瞬空のストアを見るため、差分で入れ替える
+        let ids: HashSet<Uuid> = narratives.iter().map(|n| n.id).collect();
+        self.narratives.retain(|id, _| ids.contains(id));
         for narrative in narratives {
             self.narratives.insert(narrative.id, narrative);
         }
+    }
 
-        Ok(())
+    /// 排他ロックを取ってディスクの最新状態に合わせてから変更し、書き戻す
+    ///
+    /// 別プロセスが先に保存した変更を上書きで失わないようにするため。
+    /// `apply` が返したリビジョンは、書き込みに成功してから記録する（保存されなかった状態を履歴に残さない）。
+    /// リビジョンを記録したかどうかを返す。
+    fn transaction(
+        &self,
+        apply: impl FnOnce(&DashMap<Uuid, NarrativeData>) -> Result<Option<PendingRevision>>,
+    ) -> Result<bool> {
+        let _lock = self.lock(true)?;
+
+        let narratives = self.read_from_disk()?;
+        self.replace_all(narratives.clone());
+
+        // 保存できなかった変更を配信し続けないよう、失敗したらディスクの状態に戻す
+        let pending = match apply(&self.narratives).and_then(|pending| {
+            self.write_to_disk()?;
+            Ok(pending)
+        }) {
+            Ok(pending) => pending,
+            Err(err) => {
+                self.replace_all(narratives);
+                return Err(err);
+            }
+        };
+
+        let Some(pending) = pending else {
+            return Ok(false);
+        };
+        self.history
+            .record(pending.before.as_ref(), &pending.after, pending.message)
+            .with_context(|| {
+                format!("Saved narrative '{}' but failed to record its revision", pending.after.name)
+            })?;
+        Ok(true)
     }
 
     pub fn create(&self, mut narrative: NarrativeData) -> Result<NarrativeData> {
+        normalize_labels(&mut narrative);
         narrative.id = Uuid::new_v4();
         narrative.created_at = chrono::Utc::now();
         narrative.updated_at = chrono::Utc::now();
 
-        self.narratives.insert(narrative.id, narrative.clone());
-        self.save_to_disk()?;
+        self.transaction(|narratives| {
+            narratives.insert(narrative.id, narrative.clone());
+            Ok(Some(PendingRevision {
+                before: None,
+                after: narrative.clone(),
+                message: None,
+            }))
+        })?;
+        self.publish(ChangeEvent::NarrativeChanged { id: narrative.id });
+        Ok(narrative)
+    }
+
+    /// 他のプロジェクトから持ち込んだナラティブを ID と作成日時を保ったまま追加する
+    pub fn import(&self, mut narrative: NarrativeData, message: String) -> Result<NarrativeData> {
+        normalize_labels(&mut narrative);
+        narrative.updated_at = chrono::Utc::now();
+
+        self.transaction(|narratives| {
+            if narratives.contains_key(&narrative.id) {
+                anyhow::bail!("Narrative {} already exists", narrative.id);
+            }
+            narratives.insert(narrative.id, narrative.clone());
+            Ok(Some(PendingRevision {
+                before: None,
+                after: narrative.clone(),
+                message: Some(message),
+            }))
+        })?;
+        self.publish(ChangeEvent::NarrativeChanged { id: narrative.id });
         Ok(narrative)
     }
 
@@ -72,18 +398,94 @@ impl NarrativeStore {
         self.narratives.get(id).map(|entry| entry.clone())
     }
 
-    pub fn update(&self, id: &Uuid, mut narrative: NarrativeData) -> Result<NarrativeData> {
+    /// ID・名前・ID または名前の一意な前方一致でナラティブを探す
+    ///
+    /// 完全一致を前方一致より優先する。複数に当てはまる場合は候補を並べてエラーにする。
+    pub fn find(&self, query: &str) -> Result<NarrativeData> {
+        if query.is_empty() {
+            anyhow::bail!("Narrative name or ID must not be empty");
+        }
+
+        if let Ok(id) = Uuid::parse_str(query) {
+            if let Some(narrative) = self.get(&id) {
+                return Ok(narrative);
+            }
+        }
+
+        let narratives = self.list();
+
+        let exact: Vec<&NarrativeData> = narratives.iter().filter(|n| n.name == query).collect();
+        let candidates = if exact.is_empty() {
+            let prefix = query.to_lowercase();
+            narratives
+                .iter()
+                .filter(|n| {
+                    n.id.to_string().starts_with(&prefix) || n.name.starts_with(query)
+                })
+                .collect()
+        } else {
+            exact
+        };
+
+        match candidates.as_slice() {
+            [] => anyhow::bail!("Narrative '{}' not found", query),
+            [narrative] => Ok((*narrative).clone()),
+            _ => {
+                let list = candidates
+                    .iter()
+                    .map(|n| format!("  {}  {}", n.id, n.name))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                anyhow::bail!("'{}' matches more than one narrative:\n{}", query, list)
+            }
+        }
+    }
+
+    /// 内容が変わっていればリビジョンを記録して更新する
+    pub fn update(
+        &self,
+        id: &Uuid,
+        mut narrative: NarrativeData,
+        message: Option<String>,
+    ) -> Result<NarrativeData> {
+        normalize_labels(&mut narrative);
         narrative.id = *id;
-        narrative.updated_at = chrono::Utc::now();
 
-        self.narratives.insert(*id, narrative.clone());
-        self.save_to_disk()?;
+        let changed = self.transaction(|narratives| {
+            let before = narratives.get(id).map(|entry| entry.clone());
+            if let Some(before) = &before {
+                narrative.created_at = before.created_at;
+            }
+
+            // 何も変わっていなければ更新日時も据え置く（一覧の並び順と履歴を食い違わせない）
+            let changed = match &before {
+                Some(before) => has_changes(before, &narrative),
+                None => true,
+            };
+            narrative.updated_at = match &before {
+                Some(before) if !changed => before.updated_at,
+                _ => chrono::Utc::now(),
+            };
+            narratives.insert(*id, narrative.clone());
+            Ok(changed.then(|| PendingRevision {
+                before,
+                after: narrative.clone(),
+                message,
+            }))
+        })?;
+        if changed {
+            self.publish(ChangeEvent::NarrativeChanged { id: *id });
+        }
         Ok(narrative)
     }
 
     pub fn delete(&self, id: &Uuid) -> Result<()> {
-        self.narratives.remove(id);
-        self.save_to_disk()?;
+        self.transaction(|narratives| {
+            narratives.remove(id);
+            Ok(None)
+        })?;
+        self.resolve_cache.remove(id)?;
+        self.publish(ChangeEvent::NarrativeDeleted { id: *id });
         Ok(())
     }
 
@@ -93,4 +495,205 @@ impl NarrativeStore {
             .map(|entry| entry.value().clone())
             .collect()
     }
+
+    /// 条件に合うナラティブを `filter.sort` の順に返す
+    pub fn search(&self, filter: &NarrativeFilter) -> Vec<NarrativeData> {
+        let query = filter
+            .query
+            .as_deref()
+            .map(|query| query.trim().to_lowercase())
+            .filter(|query| !query.is_empty());
+        let folder = filter.folder.as_deref().and_then(normalize_folder);
+
+        let mut narratives: Vec<NarrativeData> = self
+            .narratives
+            .iter()
+            .filter(|entry| {
+                let narrative = entry.value();
+                let template_ok = match filter.template {
+                    Some(template) => narrative.is_template == template,
+                    None => true,
+                };
+                let folder_ok = match folder.as_deref() {
+                    Some(folder) => in_folder(narrative, folder),
+                    None => true,
+                };
+                let query_ok = match query.as_deref() {
+                    Some(query) => matches_query(narrative, query),
+                    None => true,
+                };
+                template_ok
+                    && folder_ok
+                    && query_ok
+                    && filter.tags.iter().all(|tag| narrative.tags.contains(tag))
+            })
+            .map(|entry| entry.value().clone())
+            .collect();
+
+        match filter.sort {
+            NarrativeSort::Updated => narratives.sort_by_key(|n| Reverse(n.updated_at)),
+            NarrativeSort::Name => narratives.sort_by(|a, b| a.name.cmp(&b.name)),
+        }
+        narratives
+    }
+
+    pub fn history(&self, id: &Uuid) -> Result<Vec<Revision>> {
+        let _lock = self.lock(false)?;
+        self.history.list(id)
+    }
+
+    pub fn revision(&self, id: &Uuid, rev: u32) -> Result<Option<Revision>> {
+        let _lock = self.lock(false)?;
+        self.history.get(id, rev)
+    }
+
+    /// 最新のリビジョン番号（履歴がなければ 0）
+    pub fn current_rev(&self, id: &Uuid) -> Result<u32> {
+        Ok(self.history(id)?.last().map(|revision| revision.rev).unwrap_or(0))
+    }
+
+    /// 前回の解決結果が使えればファイルを読み直さずに返す
+    ///
+    /// `record` なら結果を記録し、`resolve_diff` の比較元にする（プレビューなどでは記録しない）。
+    pub fn resolve(
+        &self,
+        scanner: &FileScanner,
+        narrative: &NarrativeData,
+        record: bool,
+    ) -> Result<Vec<ResolvedItem>> {
+        let rev = self.current_rev(&narrative.id)?;
+        self.resolve_cache
+            .resolve(scanner.root_path(), narrative, rev, record, |narrative| {
+                scanner.resolve_items(narrative)
+            })
+    }
+
+    /// 最後に記録した解決結果から、現在の解決結果までの変化（記録がなければ `None`）
+    pub fn resolve_diff(
+        &self,
+        scanner: &FileScanner,
+        narrative: &NarrativeData,
+    ) -> Result<Option<ResolveDiff>> {
+        let recorded = match self.resolve_cache.load(&narrative.id)? {
+            Some(recorded) => recorded,
+            None => return Ok(None),
+        };
+
+        let current = self.resolve(scanner, narrative, false)?;
+        let rev = self.current_rev(&narrative.id)?;
+        Ok(Some(resolve_cache::diff(narrative, &recorded, rev, &current)))
+    }
+
+    /// 指定リビジョンの状態に戻す（復元自体も新しいリビジョンとして記録される）
+    ///
+    /// 削除済みのナラティブも履歴が残っていれば復元できる。
+    pub fn restore(&self, id: &Uuid, rev: u32, message: Option<String>) -> Result<Option<NarrativeData>> {
+        let revision = match self.revision(id, rev)? {
+            Some(revision) => revision,
+            None => return Ok(None),
+        };
+
+        let message = message.or_else(|| Some(format!("Restore revision {rev}")));
+        self.update(id, revision.snapshot, message).map(Some)
+    }
+}
+
+/// 保存処理の一時ファイルやロックファイル、履歴、解決結果のキャッシュなど、ファイル一覧に出すべきでないもの
+///
+/// 絶対パスでもプロジェクトルートからの相対パスでもよい。
+pub fn is_internal_file(path: &Path) -> bool {
+    let components: Vec<&OsStr> = path.iter().collect();
+    let Some(index) = components.iter().rposition(|&name| name == ".novelenv") else {
+        return false;
+    };
+    let inner = &components[index + 1..];
+
+    let is_temp = inner
+        .last()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(".tmp"));
+
+    match inner.first() {
+        Some(&first) => {
+            first == LOCK_FILE || first == HISTORY_DIR || first == resolve_cache::CACHE_DIR || is_temp
+        }
+        None => false,
+    }
+}
+
+fn parent_name(path: &Path) -> Option<&OsStr> {
+    path.parent().and_then(Path::file_name)
+}
+
+/// 一時ファイルに書き出してから rename し、読み手が途中状態を見ないようにする
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let name = path.file_name().and_then(OsStr::to_str).unwrap_or(STORAGE_FILE);
+    let tmp_path = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    {
+        let mut file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+
+    Ok(())
+}
+
+/// タグの前後の空白・重複と、フォルダーの前後の `/` を取り除く
+fn normalize_labels(narrative: &mut NarrativeData) {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in narrative.tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    narrative.tags = tags;
+    narrative.folder = narrative.folder.as_deref().and_then(normalize_folder);
+}
+
+fn normalize_folder(folder: &str) -> Option<String> {
+    let parts: Vec<&str> = folder
+        .split('/')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// サブフォルダーにあるものも含める
+fn in_folder(narrative: &NarrativeData, folder: &str) -> bool {
+    narrative.folder.as_deref().is_some_and(|own| {
+        own == folder || own.strip_prefix(folder).is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+fn matches_query(narrative: &NarrativeData, query: &str) -> bool {
+    narrative.name.to_lowercase().contains(query)
+        || narrative
+            .description
+            .as_deref()
+            .is_some_and(|description| description.to_lowercase().contains(query))
+        || narrative.tags.iter().any(|tag| tag.to_lowercase().contains(query))
+        || narrative
+            .folder
+            .as_deref()
+            .is_some_and(|folder| folder.to_lowercase().contains(query))
+}
+
+/// 書き込みに成功したら記録するリビジョン
+struct PendingRevision {
+    before: Option<NarrativeData>,
+    after: NarrativeData,
+    message: Option<String>,
+}
+
+fn has_changes(before: &NarrativeData, after: &NarrativeData) -> bool {
+    before.name != after.name
+        || before.description != after.description
+        || before.is_template != after.is_template
+        || before.tags != after.tags
+        || before.folder != after.folder
+        || before.contexts != after.contexts
+        || before.output != after.output
 }
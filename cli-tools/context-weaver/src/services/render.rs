@@ -0,0 +1,204 @@
+use anyhow::Result;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::{
+    AnchorRange, IncludeType, NarrativeData, OutputOptions, ResolveFormat, ResolvedItem, SourceLabel,
+};
+
+#[derive(Serialize)]
+struct ResolvedBundle<'a> {
+    id: Uuid,
+    name: &'a str,
+    total_bytes: usize,
+    total_chars: usize,
+    items: &'a [ResolvedItem],
+}
+
+pub fn render(
+    narrative: &NarrativeData,
+    items: &[ResolvedItem],
+    format: ResolveFormat,
+    options: &OutputOptions,
+) -> Result<String> {
+    match format {
+        ResolveFormat::Markdown => Ok(render_markdown(items, options)),
+        ResolveFormat::Xml => Ok(render_xml(items)),
+        ResolveFormat::Json => {
+            let bundle = ResolvedBundle {
+                id: narrative.id,
+                name: &narrative.name,
+                total_bytes: items.iter().map(|item| item.bytes).sum(),
+                total_chars: items.iter().map(|item| item.chars).sum(),
+                items,
+            };
+            Ok(serde_json::to_string_pretty(&bundle)? + "\n")
+        }
+        ResolveFormat::Jsonl => {
+            let mut output = String::new();
+            for item in items {
+                output.push_str(&serde_json::to_string(item)?);
+                output.push('\n');
+            }
+            Ok(output)
+        }
+    }
+}
+
+pub fn content_type(format: ResolveFormat) -> &'static str {
+    match format {
+        ResolveFormat::Markdown => "text/markdown; charset=utf-8",
+        ResolveFormat::Xml => "application/xml; charset=utf-8",
+        ResolveFormat::Json => "application/json",
+        ResolveFormat::Jsonl => "application/x-ndjson",
+    }
+}
+
+/// `Accept` ヘッダーから出力形式を推定する
+///
+/// q 値の最も高い対応形式を選ぶ（同じ q 値なら先に書かれたもの）。
+/// ブラウザが送る `text/html` や `*/*` は既定の形式（Markdown）として扱う。
+pub fn format_from_accept(accept: &str) -> Option<ResolveFormat> {
+    let mut best: Option<(f32, ResolveFormat)> = None;
+
+    for range in accept.split(',') {
+        let mut parts = range.split(';');
+        let media_type = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            .map_or(Some(1.0), |(_, value)| value.trim().parse::<f32>().ok());
+
+        let (Some(quality), Some(format)) = (quality, format_for_media_type(&media_type)) else {
+            continue;
+        };
+        if quality <= 0.0 {
+            continue;
+        }
+        match best {
+            Some((best_quality, _)) if best_quality >= quality => {}
+            _ => best = Some((quality, format)),
+        }
+    }
+
+    best.map(|(_, format)| format)
+}
+
+fn format_for_media_type(media_type: &str) -> Option<ResolveFormat> {
+    match media_type {
+        "application/x-ndjson" | "application/jsonl" => Some(ResolveFormat::Jsonl),
+        "application/json" => Some(ResolveFormat::Json),
+        "application/xml" | "text/xml" => Some(ResolveFormat::Xml),
+        "text/markdown" | "text/plain" | "text/html" | "text/*" | "*/*" => Some(ResolveFormat::default()),
+        _ => None,
+    }
+}
+
+pub fn render_markdown(items: &[ResolvedItem], options: &OutputOptions) -> String {
+    items
+        .iter()
+        .map(|item| wrap_item(item, options.source_label))
+        .collect::<Vec<_>>()
+        .join(&options.separator)
+}
+
+fn render_xml(items: &[ResolvedItem]) -> String {
+    let mut output = String::from("<documents>\n");
+
+    for (index, item) in items.iter().enumerate() {
+        output.push_str(&format!("<document index=\"{}\"", index + 1));
+        if !item.path.is_empty() {
+            output.push_str(&format!(" path=\"{}\"", escape_attr(&item.path)));
+        }
+        match &item.include_type {
+            IncludeType::Full => output.push_str(" type=\"full\""),
+            IncludeType::Section { section } => {
+                output.push_str(&format!(" type=\"section\" section=\"{}\"", escape_attr(section)))
+            }
+            IncludeType::Lines { start, end } => {
+                output.push_str(&format!(" type=\"lines\" start=\"{start}\" end=\"{end}\""))
+            }
+            IncludeType::Text { .. } => output.push_str(" type=\"text\""),
+            IncludeType::Anchor { anchor } => {
+                output.push_str(" type=\"anchor\"");
+                if let AnchorRange::Marker { name } = anchor {
+                    output.push_str(&format!(" marker=\"{}\"", escape_attr(name)));
+                }
+            }
+        }
+        output.push_str(">\n");
+
+        output.push_str(&wrap_item(item, SourceLabel::None));
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push_str("</document>\n");
+    }
+
+    output.push_str("</documents>\n");
+    output
+}
+
+/// ヘッダー・フッター・出典ラベルで本文を包む
+fn wrap_item(item: &ResolvedItem, label: SourceLabel) -> String {
+    let mut block = String::new();
+
+    let has_source = !item.path.is_empty() && !matches!(item.include_type, IncludeType::Text { .. });
+    if has_source {
+        match label {
+            SourceLabel::None => {}
+            SourceLabel::Heading => block.push_str(&format!("## {}\n\n", item.path)),
+            SourceLabel::Tag => {
+                block.push_str(&format!("<source path=\"{}\">\n", escape_attr(&item.path)))
+            }
+            SourceLabel::Comment => {
+                block.push_str(&format!("<!-- source: {} -->\n", escape_comment(&item.path)))
+            }
+        }
+    }
+
+    if let Some(header) = &item.header {
+        block.push_str(header);
+        if !header.ends_with('\n') {
+            block.push('\n');
+        }
+    }
+
+    block.push_str(&item.content);
+
+    if let Some(footer) = &item.footer {
+        if !block.ends_with('\n') {
+            block.push('\n');
+        }
+        block.push_str(footer);
+    }
+
+    if has_source && label == SourceLabel::Tag {
+        if !block.ends_with('\n') {
+            block.push('\n');
+        }
+        block.push_str("</source>");
+    }
+
+    block
+}
+
+/// コメント中の `--` で `-->` が早く閉じないよう、連続するハイフンの間に空白を挟む
+fn escape_comment(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if ch == '-' && escaped.ends_with('-') {
+            escaped.push(' ');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
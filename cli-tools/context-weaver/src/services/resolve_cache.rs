@@ -0,0 +1,271 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::models::{
+    ChangeStatus, IncludeType, ItemChange, NarrativeData, ResolveDiff, ResolvedItem,
+};
+
+pub const CACHE_DIR: &str = "resolve_cache";
+
+/// 前回の解決結果（`.novelenv/resolve_cache/<id>.json`）
+///
+/// ナラティブのリビジョンとアイテム定義、参照しているファイルのハッシュが変わっていなければ
+/// ファイルを読み直さずにこの結果を使う。`weaver diff` の比較元にもなる。
+#[derive(Clone)]
+pub struct ResolveCache {
+    cache_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub rev: u32,
+    /// `contexts` の SHA-256（手編集でリビジョンを経ずに変わった場合に備える）
+    contexts_hash: String,
+    pub resolved_at: chrono::DateTime<chrono::Utc>,
+    sources: BTreeMap<String, SourceStamp>,
+    pub items: Vec<ResolvedItem>,
+}
+
+/// サイズと更新日時が同じならハッシュを計算し直さない
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SourceStamp {
+    size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<chrono::DateTime<chrono::Utc>>,
+    sha256: String,
+}
+
+impl ResolveCache {
+    pub fn new(storage_path: &Path) -> Self {
+        Self {
+            cache_dir: storage_path.join(".novelenv").join(CACHE_DIR),
+        }
+    }
+
+    fn cache_file(&self, id: &Uuid) -> PathBuf {
+        self.cache_dir.join(format!("{id}.json"))
+    }
+
+    pub fn load(&self, id: &Uuid) -> Result<Option<CacheEntry>> {
+        let path = self.cache_file(id);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let json = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        // 形式が変わった古いキャッシュは作り直せばよいので、読めなければ無いものとして扱う
+        Ok(serde_json::from_str(&json).ok())
+    }
+
+    /// キャッシュが使えればその結果を、使えなければ `resolve` で解決し直した結果を返す
+    ///
+    /// `record` なら解決し直した結果を次回のキャッシュ（と差分の比較元）として保存する。
+    pub fn resolve(
+        &self,
+        root: &Path,
+        narrative: &NarrativeData,
+        rev: u32,
+        record: bool,
+        resolve: impl FnOnce(&NarrativeData) -> Result<Vec<ResolvedItem>>,
+    ) -> Result<Vec<ResolvedItem>> {
+        let contexts_hash = sha256_hex(serde_json::to_string(&narrative.contexts)?.as_bytes());
+        let cached = self
+            .load(&narrative.id)?
+            .filter(|entry| entry.rev == rev && entry.contexts_hash == contexts_hash);
+
+        let sources = stamp_sources(root, narrative, cached.as_ref().map(|entry| &entry.sources));
+        if let Some(mut entry) = cached {
+            if same_content(&entry.sources, &sources) {
+                // 更新日時だけ変わったファイルを次回ハッシュし直さないよう記録を更新する
+                if record && entry.sources != sources {
+                    entry.sources = sources;
+                    self.save(&narrative.id, &entry)?;
+                }
+                return Ok(entry.items);
+            }
+        }
+
+        let items = resolve(narrative)?;
+        if record {
+            self.save(
+                &narrative.id,
+                &CacheEntry {
+                    rev,
+                    contexts_hash,
+                    resolved_at: chrono::Utc::now(),
+                    sources,
+                    items: items.clone(),
+                },
+            )?;
+        }
+        Ok(items)
+    }
+
+    fn save(&self, id: &Uuid, entry: &CacheEntry) -> Result<()> {
+        fs::create_dir_all(&self.cache_dir)
+            .with_context(|| format!("Failed to create {}", self.cache_dir.display()))?;
+
+        let path = self.cache_file(id);
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&tmp_path, serde_json::to_string(entry)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    }
+
+    pub fn remove(&self, id: &Uuid) -> Result<()> {
+        let path = self.cache_file(id);
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// 参照しているファイルのハッシュ（読めないファイルは含めない。解決時にエラーになる）
+fn stamp_sources(
+    root: &Path,
+    narrative: &NarrativeData,
+    previous: Option<&BTreeMap<String, SourceStamp>>,
+) -> BTreeMap<String, SourceStamp> {
+    let mut sources = BTreeMap::new();
+
+    for item in &narrative.contexts {
+        let is_text = matches!(item.include_type, IncludeType::Text { .. });
+        if is_text || item.escapes_root() || sources.contains_key(&item.path) {
+            continue;
+        }
+
+        let full_path = root.join(&item.path);
+        let Ok(metadata) = fs::metadata(&full_path) else {
+            continue;
+        };
+        let size = metadata.len();
+        let modified = metadata.modified().ok().map(chrono::DateTime::<chrono::Utc>::from);
+
+        let unchanged = previous
+            .and_then(|previous| previous.get(&item.path))
+            .filter(|stamp| stamp.size == size && stamp.modified == modified && modified.is_some());
+        let stamp = match unchanged {
+            Some(stamp) => stamp.clone(),
+            None => match fs::read(&full_path) {
+                Ok(bytes) => SourceStamp {
+                    size,
+                    modified,
+                    sha256: sha256_hex(&bytes),
+                },
+                Err(_) => continue,
+            },
+        };
+        sources.insert(item.path.clone(), stamp);
+    }
+
+    sources
+}
+
+/// 更新日時だけが変わったファイル（touch や保存し直し）は変更とみなさない
+fn same_content(
+    before: &BTreeMap<String, SourceStamp>,
+    after: &BTreeMap<String, SourceStamp>,
+) -> bool {
+    before.len() == after.len()
+        && before
+            .iter()
+            .all(|(path, stamp)| after.get(path).is_some_and(|s| s.sha256 == stamp.sha256))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// 記録済みの解決結果と現在の結果をアイテムごとに突き合わせる
+///
+/// 同じパス・同じ取り込み方のアイテムを先頭から順に対応付け、残りを追加・削除とする。
+pub fn diff(
+    narrative: &NarrativeData,
+    recorded: &CacheEntry,
+    current_rev: u32,
+    current: &[ResolvedItem],
+) -> ResolveDiff {
+    let mut unmatched: Vec<Option<&ResolvedItem>> = recorded.items.iter().map(Some).collect();
+    let mut items = Vec::new();
+    let mut patch = String::new();
+
+    for item in current {
+        let label = item.include_type.describe(&item.path);
+        let before = unmatched
+            .iter_mut()
+            .find(|old| {
+                old.is_some_and(|old| old.path == item.path && old.include_type == item.include_type)
+            })
+            .and_then(Option::take);
+
+        let status = match before {
+            Some(before) if wrapped(before) == wrapped(item) => ChangeStatus::Unchanged,
+            Some(before) => {
+                patch.push_str(&unified(&label, &wrapped(before), &wrapped(item)));
+                ChangeStatus::Modified
+            }
+            None => {
+                patch.push_str(&unified(&label, "", &wrapped(item)));
+                ChangeStatus::Added
+            }
+        };
+        items.push(ItemChange {
+            label,
+            status,
+            before_chars: before.map(|before| before.chars),
+            after_chars: Some(item.chars),
+        });
+    }
+
+    for before in unmatched.into_iter().flatten() {
+        let label = before.include_type.describe(&before.path);
+        patch.push_str(&unified(&label, &wrapped(before), ""));
+        items.push(ItemChange {
+            label,
+            status: ChangeStatus::Removed,
+            before_chars: Some(before.chars),
+            after_chars: None,
+        });
+    }
+
+    ResolveDiff {
+        id: narrative.id,
+        name: narrative.name.clone(),
+        recorded_rev: recorded.rev,
+        recorded_at: recorded.resolved_at,
+        current_rev,
+        changed: items.iter().any(|item| item.status != ChangeStatus::Unchanged),
+        items,
+        diff: patch,
+    }
+}
+
+/// ヘッダー・フッターも出力に含まれるので比較対象にする
+fn wrapped(item: &ResolvedItem) -> String {
+    [item.header.as_deref(), Some(item.content.as_str()), item.footer.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn unified(label: &str, before: &str, after: &str) -> String {
+    TextDiff::from_lines(before, after)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{label}"), &format!("b/{label}"))
+        .to_string()
+}
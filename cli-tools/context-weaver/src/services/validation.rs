@@ -0,0 +1,158 @@
+use std::fs;
+
+use crate::models::{ContextItem, IncludeType, ItemFix, NarrativeData, Problem, ValidationIssue};
+use crate::services::{anchor, lookup, markdown, FileScanner};
+
+const MAX_SUGGESTIONS: usize = 5;
+
+/// ナラティブのアイテムが存在しないファイル・見出し・行を指していないか調べる
+pub fn check(scanner: &FileScanner, narrative: &NarrativeData) -> Vec<ValidationIssue> {
+    let files: Vec<String> = scanner
+        .get_file_map()
+        .iter()
+        .filter(|entry| !entry.value().is_directory)
+        .map(|entry| entry.key().clone())
+        .collect();
+
+    let mut items: Vec<&ContextItem> = narrative.contexts.iter().collect();
+    items.sort_by_key(|item| item.order);
+
+    let mut issues = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        if matches!(item.include_type, IncludeType::Text { .. }) {
+            continue;
+        }
+
+        let issue = |problem, suggestions, fix| ValidationIssue {
+            narrative_id: narrative.id,
+            narrative_name: narrative.name.clone(),
+            position: index + 1,
+            path: item.path.clone(),
+            problem,
+            suggestions,
+            fix,
+        };
+
+        // プロジェクト外のファイルは存在も中身も調べない（候補や修正案から漏れないように）
+        if item.escapes_root() {
+            issues.push(issue(Problem::PathOutsideRoot, Vec::new(), None));
+            continue;
+        }
+
+        let full_path = scanner.root_path().join(&item.path);
+        if item.path.is_empty() || !full_path.is_file() {
+            let matches = lookup::similar_paths(
+                &item.path,
+                files.iter().map(String::as_str),
+                MAX_SUGGESTIONS,
+            );
+            let fix = lookup::unique_best(&matches).map(|best| {
+                // 移動先のファイルでも見出しや行範囲が合っているかを確かめ、直せるなら一緒に直す
+                let include_type = read(scanner, &best.value)
+                    .and_then(|content| check_content(&item.include_type, &content))
+                    .and_then(|(_, _, include_type)| include_type);
+                ItemFix {
+                    path: Some(best.value.clone()),
+                    include_type,
+                }
+            });
+            let suggestions = matches.into_iter().map(|m| m.value).collect();
+            issues.push(issue(Problem::MissingFile, suggestions, fix));
+            continue;
+        }
+
+        // UTF-8 として読めないファイルは中身を検査しない
+        let Some(content) = read(scanner, &item.path) else {
+            continue;
+        };
+        if let Some((problem, suggestions, include_type)) =
+            check_content(&item.include_type, &content)
+        {
+            let fix = include_type.map(|include_type| ItemFix {
+                path: None,
+                include_type: Some(include_type),
+            });
+            issues.push(issue(problem, suggestions, fix));
+        }
+    }
+
+    issues
+}
+
+/// `check` で見つかった修正案のうち自動修正できるものを適用し、適用した件数を返す
+pub fn apply_fixes(narrative: &mut NarrativeData, issues: &[ValidationIssue]) -> usize {
+    let mut indices: Vec<usize> = (0..narrative.contexts.len()).collect();
+    indices.sort_by_key(|&index| narrative.contexts[index].order);
+
+    let mut applied = 0;
+    for issue in issues.iter().filter(|issue| issue.narrative_id == narrative.id) {
+        let (Some(fix), Some(&index)) = (&issue.fix, indices.get(issue.position - 1)) else {
+            continue;
+        };
+
+        let item = &mut narrative.contexts[index];
+        if let Some(path) = &fix.path {
+            item.path = path.clone();
+        }
+        if let Some(include_type) = &fix.include_type {
+            item.include_type = include_type.clone();
+        }
+        applied += 1;
+    }
+
+    applied
+}
+
+fn read(scanner: &FileScanner, path: &str) -> Option<String> {
+    fs::read_to_string(scanner.root_path().join(path)).ok()
+}
+
+/// 問題・候補・自動修正後の取り込み方を返す（問題がなければ `None`）
+fn check_content(
+    include_type: &IncludeType,
+    content: &str,
+) -> Option<(Problem, Vec<String>, Option<IncludeType>)> {
+    match include_type {
+        IncludeType::Section { section } => {
+            if markdown::find_section(content, section).is_some() {
+                return None;
+            }
+
+            let matches =
+                lookup::similar_headings(section, markdown::headings(content), MAX_SUGGESTIONS);
+            let fix = lookup::unique_best(&matches).map(|best| IncludeType::Section {
+                section: best.value.clone(),
+            });
+            let suggestions = matches.into_iter().map(|m| m.value).collect();
+            let problem = Problem::MissingSection {
+                section: section.clone(),
+            };
+            Some((problem, suggestions, fix))
+        }
+        IncludeType::Lines { start, end } => {
+            // 終わりはファイル末尾に切り詰めて解決されるので、開始行がファイル外のときだけ問題にする
+            let line_count = content.lines().count();
+            if start.saturating_sub(1) < line_count {
+                return None;
+            }
+
+            let problem = Problem::LinesOutOfRange {
+                start: *start,
+                end: *end,
+                line_count,
+            };
+            Some((problem, Vec::new(), None))
+        }
+        IncludeType::Anchor { anchor } => match anchor::locate(content, anchor) {
+            Ok(located) if located.ambiguous => Some((Problem::AmbiguousAnchor, Vec::new(), None)),
+            Ok(_) => None,
+            Err(err) => {
+                let problem = Problem::AnchorNotFound {
+                    reason: err.to_string(),
+                };
+                Some((problem, Vec::new(), None))
+            }
+        },
+        IncludeType::Full | IncludeType::Text { .. } => None,
+    }
+}
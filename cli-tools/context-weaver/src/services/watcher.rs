@@ -0,0 +1,105 @@
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use walkdir::WalkDir;
+
+use crate::services::events::{ChangeEvent, EventBus};
+use crate::services::{narrative_store, FileScanner, NarrativeStore};
+
+/// ファイルシステムの変更を監視し、ファイルマップとナラティブを差分更新する
+///
+/// `.wvignore` で除外されたディレクトリは監視しないため、再帰監視ではなく
+/// スキャン済みのディレクトリごとに監視を登録する。
+/// ナラティブの保存先は除外の設定にかかわらず常に監視する。
+pub fn spawn(scanner: FileScanner, store: NarrativeStore, events: EventBus) -> Result<()> {
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx)?;
+
+    watch_directories(&mut watcher, &scanner);
+    for directory in store.watch_directories()? {
+        watch_new_directory(&mut watcher, &directory);
+    }
+
+    thread::spawn(move || {
+        // watcher はこのスレッドが所有し、サーバーが動いている間は生き続ける
+        for result in rx {
+            let event = match result {
+                Ok(event) => event,
+                Err(err) => {
+                    tracing::warn!("File watcher error: {}", err);
+                    continue;
+                }
+            };
+
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+
+            for path in &event.paths {
+                if narrative_store::is_internal_file(path) {
+                    continue;
+                }
+
+                if store.is_storage_file(path) {
+                    if let Err(err) = store.reload() {
+                        tracing::warn!("Failed to reload narratives: {}", err);
+                    }
+                    continue;
+                }
+
+                match scanner.refresh_path(path) {
+                    Ok(Some(change)) => {
+                        match &change {
+                            ChangeEvent::FileChanged { .. } if path.is_dir() => {
+                                watch_new_directory(&mut watcher, path);
+                                // mkdir -p などで中身ごと作られた場合に備えて配下も取り込む
+                                for entry in WalkDir::new(path).min_depth(1).into_iter().flatten() {
+                                    if let Ok(Some(nested)) = scanner.refresh_path(entry.path()) {
+                                        if entry.file_type().is_dir() {
+                                            watch_new_directory(&mut watcher, entry.path());
+                                        }
+                                        events.publish(nested);
+                                    }
+                                }
+                            }
+                            ChangeEvent::FilesRescanned => {
+                                watch_directories(&mut watcher, &scanner);
+                            }
+                            _ => {}
+                        }
+                        events.publish(change);
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        tracing::warn!("Failed to refresh {}: {}", path.display(), err);
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn watch_directories(watcher: &mut RecommendedWatcher, scanner: &FileScanner) {
+    let root = scanner.root_path().to_path_buf();
+    let directories: Vec<_> = scanner
+        .get_file_map()
+        .iter()
+        .filter(|entry| entry.value().is_directory)
+        .map(|entry| root.join(entry.key()))
+        .collect();
+
+    watch_new_directory(watcher, &root);
+    for directory in directories {
+        watch_new_directory(watcher, &directory);
+    }
+}
+
+fn watch_new_directory(watcher: &mut RecommendedWatcher, path: &Path) {
+    if let Err(err) = watcher.watch(path, RecursiveMode::NonRecursive) {
+        tracing::warn!("Failed to watch {}: {}", path.display(), err);
+    }
+}
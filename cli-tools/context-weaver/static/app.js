@@ -2,10 +2,56 @@ let files = [];
 let currentContexts = [];
 let narratives = [];
 let currentTab = 'normal';
+const refreshTimers = {};
+
+// `weaver serve` が表示する URL の ?token= を覚えておき、API 呼び出しに付ける
+const accessToken = (() => {
+    const params = new URLSearchParams(location.search);
+    const token = params.get('token');
+    if (token) {
+        sessionStorage.setItem('weaverToken', token);
+        params.delete('token');
+        const query = params.toString();
+        history.replaceState(null, '', location.pathname + (query ? `?${query}` : '') + location.hash);
+    }
+    return token || sessionStorage.getItem('weaverToken');
+})();
+
+function apiFetch(url, options = {}) {
+    if (!accessToken) {
+        return fetch(url, options);
+    }
+    const headers = {...options.headers, 'Authorization': `Bearer ${accessToken}`};
+    return fetch(url, {...options, headers});
+}
+
+// API のエラーは {code, message, path, section} の JSON で返る
+async function errorMessage(response) {
+    try {
+        const error = await response.json();
+        return error.message || `${error.code} (${response.status})`;
+    } catch {
+        return `Request failed (${response.status})`;
+    }
+}
+
+async function loadServerInfo() {
+    try {
+        const response = await apiFetch('/api/server');
+        if (response.status === 401) {
+            alert('アクセストークンが必要です。weaver serve が表示した URL（?token=…）を開いてください。');
+            return;
+        }
+        const info = await response.json();
+        document.body.classList.toggle('read-only', info.read_only);
+    } catch (error) {
+        console.error('Failed to load server info:', error);
+    }
+}
 
 async function loadFiles() {
     try {
-        const response = await fetch('/api/files');
+        const response = await apiFetch('/api/files');
         files = await response.json();
         renderFileTree();
     } catch (error) {
@@ -21,7 +67,7 @@ async function refreshFiles() {
         refreshButton.classList.add('refreshing');
         refreshButton.disabled = true;
         
-        const response = await fetch('/api/files/refresh', {
+        const response = await apiFetch('/api/files/refresh', {
             method: 'POST'
         });
         
@@ -44,8 +90,14 @@ async function refreshFiles() {
 }
 
 async function loadNarratives() {
+    const params = new URLSearchParams({sort: document.getElementById('narrative-sort').value});
+    const query = document.getElementById('narrative-search').value.trim();
+    const tags = document.getElementById('narrative-tag-filter').value.trim();
+    if (query) params.set('q', query);
+    if (tags) params.set('tags', tags);
+
     try {
-        const response = await fetch('/api/narratives');
+        const response = await apiFetch(`/api/narratives?${params}`);
         narratives = await response.json();
         renderNarratives();
     } catch (error) {
@@ -88,7 +140,7 @@ function buildTree(files) {
                 }
                 current = current.children[part];
             });
-            current.files.push({name: fileName, path: file.path, preview: file.preview});
+            current.files.push({name: fileName, path: file.path, preview: file.preview, size: file.size, chars: file.chars});
         }
     });
 
@@ -111,25 +163,116 @@ function renderTreeNode(node, container, level = 0) {
     // Sort files by name
     const sortedFiles = [...node.files].sort((a, b) => a.name.localeCompare(b.name));
     sortedFiles.forEach(file => {
-        const fileDiv = document.createElement('div');
-        fileDiv.className = 'tree-item file';
+        const fileDiv = createFileItem(file.path, file.name, escapeHtml(file.preview), fileWeight(file));
         fileDiv.style.paddingLeft = `${(level + 1) * 20}px`;
-        fileDiv.innerHTML = `<div class="file-name">${file.name}</div><div class="file-preview">${file.preview}</div>`;
-        fileDiv.draggable = true;
-        fileDiv.dataset.path = file.path;
-
-        fileDiv.addEventListener('dragstart', (e) => {
-            e.dataTransfer.effectAllowed = 'copy';
-            e.dataTransfer.setData('text/plain', file.path);
-            fileDiv.classList.add('dragging');
-        });
+        container.appendChild(fileDiv);
+    });
+}
 
-        fileDiv.addEventListener('dragend', () => {
-            fileDiv.classList.remove('dragging');
-        });
+// ドラッグでナラティブに追加でき、見出しを展開してセクション単位でも追加できるファイル項目
+function createFileItem(path, name, detailHtml, weight) {
+    const fileDiv = document.createElement('div');
+    fileDiv.className = 'tree-item file';
+    fileDiv.innerHTML = `
+        <div class="file-name">
+            <span class="outline-toggle" title="見出しを表示">▸</span>${escapeHtml(name)}
+            <span class="file-weight">${escapeHtml(weight)}</span>
+        </div>
+        <div class="file-preview">${detailHtml}</div>
+        <div class="file-outline" hidden></div>`;
+    fileDiv.draggable = true;
+    fileDiv.dataset.path = path;
+
+    fileDiv.addEventListener('dragstart', (e) => {
+        e.dataTransfer.effectAllowed = 'copy';
+        e.dataTransfer.setData('text/plain', path);
+        fileDiv.classList.add('dragging');
+    });
 
-        container.appendChild(fileDiv);
+    fileDiv.addEventListener('dragend', () => {
+        fileDiv.classList.remove('dragging');
+    });
+
+    const toggle = fileDiv.querySelector('.outline-toggle');
+    toggle.addEventListener('click', (e) => {
+        e.stopPropagation();
+        toggleOutline(path, fileDiv.querySelector('.file-outline'), toggle);
     });
+
+    return fileDiv;
+}
+
+function fileWeight(file) {
+    if (file.chars) {
+        return `${file.chars.toLocaleString()}字`;
+    }
+    if (file.size >= 1024) {
+        return `${(file.size / 1024).toFixed(1)}KB`;
+    }
+    return file.size ? `${file.size}B` : '';
+}
+
+async function toggleOutline(path, container, toggle) {
+    if (!container.hidden) {
+        container.hidden = true;
+        toggle.textContent = '▸';
+        return;
+    }
+
+    try {
+        const response = await apiFetch(`/api/files/${encodeURIComponent(path)}/outline`);
+        if (!response.ok) {
+            throw new Error(`Failed to load outline (${response.status})`);
+        }
+        const outline = await response.json();
+
+        container.innerHTML = outline.length
+            ? renderOutline(outline)
+            : '<div class="outline-empty">見出しがありません</div>';
+        container.querySelectorAll('.outline-heading').forEach(heading => {
+            heading.addEventListener('click', (e) => {
+                e.stopPropagation();
+                addContext(path, {type: 'Section', section: heading.dataset.section});
+            });
+        });
+        container.hidden = false;
+        toggle.textContent = '▾';
+    } catch (error) {
+        console.error('Failed to load outline:', error);
+    }
+}
+
+function renderOutline(headings) {
+    return headings.map(heading => `
+        <div class="outline-heading" data-section="${escapeHtml(heading.text)}" title="L${heading.line}-${heading.end_line} をセクションとして追加" style="padding-left: ${(heading.level - 1) * 12}px">
+            ${escapeHtml(heading.text)} <span class="outline-lines">L${heading.line}</span>
+        </div>
+        ${renderOutline(heading.children)}`).join('');
+}
+
+async function searchFiles(query) {
+    if (!query.trim()) {
+        renderFileTree();
+        return;
+    }
+
+    try {
+        const response = await apiFetch(`/api/files/search?q=${encodeURIComponent(query)}`);
+        const results = await response.json();
+
+        const treeView = document.getElementById('file-tree');
+        treeView.innerHTML = results.length ? '' : '<p class="placeholder">一致するファイルはありません</p>';
+
+        results.forEach(result => {
+            const file = files.find(f => f.path === result.path) || {};
+            const detail = result.matches
+                .map(match => `<div class="search-match"><span class="outline-lines">L${match.line}</span> ${escapeHtml(match.text)}</div>`)
+                .join('');
+            treeView.appendChild(createFileItem(result.path, result.path, detail, fileWeight(file)));
+        });
+    } catch (error) {
+        console.error('Failed to search files:', error);
+    }
 }
 
 function setupContextList() {
@@ -196,10 +339,22 @@ function renderContextList() {
 
         sortedContexts.forEach((context, displayIndex) => {
             const actualIndex = currentContexts.indexOf(context);
+            const isText = context.include_type.type === 'Text';
+            const hasWrapper = Boolean(context.header || context.footer);
+            const body = isText
+                ? `<textarea class="text-body" placeholder="Text block" oninput="updateTextBody(${actualIndex}, this.value)">${escapeHtml(context.include_type.body)}</textarea>`
+                : `<span class="path">${escapeHtml(context.path)}</span>${includeLabel(context.include_type)}`;
             html += `
                 <div class="context-item" draggable="true" data-index="${actualIndex}">
                     <span class="drag-handle">⋮⋮</span>
-                    <span class="path">${context.path}</span>
+                    <div class="context-body">
+                        ${body}
+                        <details class="wrappers"${hasWrapper ? ' open' : ''}>
+                            <summary>Header / Footer</summary>
+                            <textarea placeholder="Header" oninput="updateWrapper(${actualIndex}, 'header', this.value)">${escapeHtml(context.header || '')}</textarea>
+                            <textarea placeholder="Footer" oninput="updateWrapper(${actualIndex}, 'footer', this.value)">${escapeHtml(context.footer || '')}</textarea>
+                        </details>
+                    </div>
                     <span class="remove" onclick="removeContext(${actualIndex})">✕</span>
                 </div>
             `;
@@ -215,6 +370,27 @@ function renderContextList() {
     }
 }
 
+// Full 以外の取り込み方を短く表示する
+function includeLabel(includeType) {
+    let label = '';
+    switch (includeType.type) {
+        case 'Section':
+            label = `§ ${includeType.section}`;
+            break;
+        case 'Lines':
+            label = `L${includeType.start}-${includeType.end}`;
+            break;
+        case 'Anchor':
+            label = includeType.anchor.kind === 'marker'
+                ? `⚓ ${includeType.anchor.name}`
+                : `⚓ ${includeType.anchor.start.split('\n')[0]} … ${includeType.anchor.end.split('\n').pop()}`;
+            break;
+        default:
+            return '';
+    }
+    return ` <span class="include-type">${escapeHtml(label)}</span>`;
+}
+
 function setupContextDragAndDrop() {
     const contextItems = document.querySelectorAll('.context-item[draggable="true"]');
     const dropZones = document.querySelectorAll('.drop-zone');
@@ -316,6 +492,72 @@ function moveContextToPosition(fromIndex, targetPosition) {
     renderContextList();
 }
 
+// 同じファイルでもセクション違いなら別アイテムとして追加できる
+function addContext(path, includeType) {
+    const exists = currentContexts.some(c =>
+        c.path === path && JSON.stringify(c.include_type) === JSON.stringify(includeType));
+    if (exists) {
+        return;
+    }
+
+    currentContexts.push({
+        path: path,
+        include_type: includeType,
+        order: currentContexts.length
+    });
+    renderContextList();
+}
+
+function addTextBlock() {
+    currentContexts.push({
+        path: '',
+        include_type: {type: 'Text', body: ''},
+        order: currentContexts.length
+    });
+    renderContextList();
+}
+
+function updateTextBody(index, value) {
+    currentContexts[index].include_type.body = value;
+}
+
+function updateWrapper(index, field, value) {
+    currentContexts[index][field] = value || null;
+}
+
+function escapeHtml(value) {
+    return String(value)
+        .replace(/&/g, '&amp;')
+        .replace(/</g, '&lt;')
+        .replace(/>/g, '&gt;')
+        .replace(/"/g, '&quot;');
+}
+
+// 区切り文字は入力欄ではエスケープ表記（\n）で扱う
+function escapeSeparator(value) {
+    return value.replace(/\\/g, '\\\\').replace(/\n/g, '\\n').replace(/\t/g, '\\t');
+}
+
+function unescapeSeparator(value) {
+    return value.replace(/\\(n|t|\\)/g, (_, ch) => ch === 'n' ? '\n' : ch === 't' ? '\t' : '\\');
+}
+
+function readOutputOptions() {
+    return {
+        separator: unescapeSeparator(document.getElementById('narrative-separator').value),
+        source_label: document.getElementById('narrative-source-label').value
+    };
+}
+
+function readLabels() {
+    const tags = document.getElementById('narrative-tags').value
+        .split(',')
+        .map(tag => tag.trim())
+        .filter(tag => tag);
+    const folder = document.getElementById('narrative-folder').value.trim();
+    return {tags, folder: folder || null};
+}
+
 function removeContext(index) {
     currentContexts.splice(index, 1);
     currentContexts.forEach((c, i) => c.order = i);
@@ -338,7 +580,9 @@ async function saveNarrative() {
         name,
         description: description || null,
         is_template: isTemplate,
+        ...readLabels(),
         contexts: currentContexts,
+        output: readOutputOptions(),
         created_at: new Date().toISOString(),
         updated_at: new Date().toISOString()
     };
@@ -347,14 +591,14 @@ async function saveNarrative() {
         let response;
         if (currentId) {
             // Update existing narrative
-            response = await fetch(`/api/narratives/${currentId}`, {
+            response = await apiFetch(`/api/narratives/${currentId}`, {
                 method: 'PUT',
                 headers: {'Content-Type': 'application/json'},
                 body: JSON.stringify(narrative)
             });
         } else {
             // Create new narrative
-            response = await fetch('/api/narratives', {
+            response = await apiFetch('/api/narratives', {
                 method: 'POST',
                 headers: {'Content-Type': 'application/json'},
                 body: JSON.stringify(narrative)
@@ -364,6 +608,8 @@ async function saveNarrative() {
         if (response.ok) {
             clearNarrativeBuilder();
             loadNarratives();
+        } else {
+            alert(`保存に失敗しました: ${await errorMessage(response)}`);
         }
     } catch (error) {
         console.error('Failed to save narrative:', error);
@@ -385,13 +631,15 @@ async function saveAsNewNarrative() {
         name,
         description: description || null,
         is_template: isTemplate,
+        ...readLabels(),
         contexts: currentContexts,
+        output: readOutputOptions(),
         created_at: new Date().toISOString(),
         updated_at: new Date().toISOString()
     };
 
     try {
-        const response = await fetch('/api/narratives', {
+        const response = await apiFetch('/api/narratives', {
             method: 'POST',
             headers: {'Content-Type': 'application/json'},
             body: JSON.stringify(narrative)
@@ -400,6 +648,8 @@ async function saveAsNewNarrative() {
         if (response.ok) {
             clearNarrativeBuilder();
             loadNarratives();
+        } else {
+            alert(`保存に失敗しました: ${await errorMessage(response)}`);
         }
     } catch (error) {
         console.error('Failed to save narrative:', error);
@@ -409,10 +659,15 @@ async function saveAsNewNarrative() {
 function clearNarrativeBuilder() {
     document.getElementById('narrative-name').value = '';
     document.getElementById('narrative-description').value = '';
+    document.getElementById('narrative-tags').value = '';
+    document.getElementById('narrative-folder').value = '';
     document.getElementById('current-narrative-id').value = '';
     document.getElementById('is-template').checked = false;
+    document.getElementById('narrative-separator').value = '\\n\\n';
+    document.getElementById('narrative-source-label').value = 'none';
     currentContexts = [];
     renderContextList();
+    loadPreview();
 }
 
 function renderNarratives() {
@@ -427,33 +682,71 @@ function renderNarratives() {
     if (filteredNarratives.length === 0) {
         const tabName = currentTab === 'template' ? 'テンプレート' : '通常のナラティブ';
         narrativeList.innerHTML = `<p class="placeholder">No saved ${tabName}</p>`;
-    } else {
-        narrativeList.innerHTML = filteredNarratives.map(narrative => `
-            <div class="narrative-item ${narrative.is_template ? 'template' : ''}">
-                <h3>${narrative.name}${narrative.is_template ? '<span class="template-badge">テンプレート</span>' : ''}</h3>
-                <div class="id">ID: ${narrative.id}</div>
-                ${narrative.description ? `<p>${narrative.description}</p>` : ''}
-                <div class="actions">
-                    <button class="resolve" onclick="resolveNarrative('${narrative.id}')">Resolve</button>
-                    <button class="copy-command" onclick="copySketchCommand('${narrative.id}', event)">Copy</button>
-                    <button class="edit" onclick="editNarrative('${narrative.id}')">Edit</button>
-                    <button class="delete" onclick="deleteNarrative('${narrative.id}')">Delete</button>
-                </div>
+        return;
+    }
+
+    // フォルダーごとにまとめる（フォルダーなしを先頭に、あとは名前順）
+    const groups = new Map();
+    filteredNarratives.forEach(narrative => {
+        const folder = narrative.folder || '';
+        if (!groups.has(folder)) groups.set(folder, []);
+        groups.get(folder).push(narrative);
+    });
+    const folders = [...groups.keys()].sort();
+    const showHeaders = folders.some(folder => folder);
+
+    narrativeList.innerHTML = folders.map(folder => `
+        ${showHeaders ? `<div class="narrative-folder">${escapeHtml(folder || '(フォルダーなし)')}</div>` : ''}
+        ${groups.get(folder).map(renderNarrativeItem).join('')}
+    `).join('');
+}
+
+function renderNarrativeItem(narrative) {
+    const tags = (narrative.tags || []).map(tag =>
+        `<span class="tag-chip" data-tag="${escapeHtml(tag)}">#${escapeHtml(tag)}</span>`
+    ).join('');
+
+    return `
+        <div class="narrative-item ${narrative.is_template ? 'template' : ''}">
+            <h3>${escapeHtml(narrative.name)}${narrative.is_template ? '<span class="template-badge">テンプレート</span>' : ''}</h3>
+            <div class="id">ID: ${narrative.id}</div>
+            ${narrative.description ? `<p>${escapeHtml(narrative.description)}</p>` : ''}
+            ${tags ? `<div class="tags">${tags}</div>` : ''}
+            <div class="actions">
+                <button class="resolve" onclick="resolveNarrative('${narrative.id}')">Resolve</button>
+                <button class="copy-command" onclick="copySketchCommand('${narrative.id}', event)">Copy</button>
+                <button class="edit" onclick="editNarrative('${narrative.id}')">Edit</button>
+                <button class="delete" onclick="deleteNarrative('${narrative.id}')">Delete</button>
             </div>
-        `).join('');
+        </div>
+    `;
+}
+
+// タグをクリックすると、そのタグで絞り込む
+function filterByTag(tag) {
+    const input = document.getElementById('narrative-tag-filter');
+    const tags = input.value.split(',').map(t => t.trim()).filter(t => t);
+    if (!tags.includes(tag)) {
+        tags.push(tag);
+        input.value = tags.join(', ');
+        loadNarratives();
     }
 }
 
+const RESOLVE_EXTENSIONS = {markdown: 'md', xml: 'xml', json: 'json', jsonl: 'jsonl'};
+
 async function resolveNarrative(id) {
+    const format = document.getElementById('resolve-format').value;
+
     try {
-        const response = await fetch(`/api/narratives/${id}/resolve`);
+        const response = await apiFetch(`/api/narratives/${id}/resolve?format=${format}`);
         const content = await response.text();
 
-        const blob = new Blob([content], {type: 'text/plain'});
+        const blob = new Blob([content], {type: response.headers.get('Content-Type') || 'text/plain'});
         const url = URL.createObjectURL(blob);
         const a = document.createElement('a');
         a.href = url;
-        a.download = `narrative-${id}.txt`;
+        a.download = `narrative-${id}.${RESOLVE_EXTENSIONS[format]}`;
         a.click();
         URL.revokeObjectURL(url);
     } catch (error) {
@@ -499,12 +792,19 @@ async function editNarrative(id) {
     // Load narrative data into the builder
     document.getElementById('narrative-name').value = narrative.name;
     document.getElementById('narrative-description').value = narrative.description || '';
+    document.getElementById('narrative-tags').value = (narrative.tags || []).join(', ');
+    document.getElementById('narrative-folder').value = narrative.folder || '';
     document.getElementById('current-narrative-id').value = narrative.id;
     document.getElementById('is-template').checked = narrative.is_template || false;
 
+    const output = narrative.output || {separator: '\n\n', source_label: 'none'};
+    document.getElementById('narrative-separator').value = escapeSeparator(output.separator);
+    document.getElementById('narrative-source-label').value = output.source_label;
+
     // Load contexts
     currentContexts = [...narrative.contexts];
     renderContextList();
+    loadPreview();
 
     // Scroll to the narrative builder
     document.querySelector('.narrative-builder').scrollIntoView({behavior: 'smooth'});
@@ -516,7 +816,7 @@ async function deleteNarrative(id) {
     }
 
     try {
-        const response = await fetch(`/api/narratives/${id}`, {
+        const response = await apiFetch(`/api/narratives/${id}`, {
             method: 'DELETE'
         });
 
@@ -528,6 +828,58 @@ async function deleteNarrative(id) {
     }
 }
 
+async function loadPreview() {
+    const preview = document.getElementById('resolved-preview');
+    const id = document.getElementById('current-narrative-id').value;
+
+    if (!id) {
+        preview.classList.add('placeholder');
+        preview.textContent = '保存済みのナラティブを編集すると解決結果が表示されます';
+        return;
+    }
+
+    try {
+        const response = await apiFetch(`/api/narratives/${id}/resolve?format=markdown&record=false`);
+        preview.classList.toggle('placeholder', !response.ok);
+        preview.textContent = response.ok ? await response.text() : await errorMessage(response);
+    } catch (error) {
+        console.error('Failed to load preview:', error);
+    }
+}
+
+// 短時間に連続する変更通知をまとめて1回の再読み込みにする
+function scheduleRefresh(key, callback) {
+    clearTimeout(refreshTimers[key]);
+    refreshTimers[key] = setTimeout(callback, 300);
+}
+
+function setupLiveUpdates() {
+    // EventSource はヘッダーを付けられないため、トークンはクエリで渡す
+    const url = accessToken ? `/api/events?token=${encodeURIComponent(accessToken)}` : '/api/events';
+    const source = new EventSource(url);
+
+    source.addEventListener('file', (e) => {
+        const change = JSON.parse(e.data);
+        scheduleRefresh('files', loadFiles);
+
+        const affectsPreview = change.kind === 'files_rescanned' ||
+            currentContexts.some(c => c.path === change.path);
+        if (affectsPreview) {
+            scheduleRefresh('preview', loadPreview);
+        }
+    });
+
+    source.addEventListener('narrative', (e) => {
+        const change = JSON.parse(e.data);
+        scheduleRefresh('narratives', loadNarratives);
+
+        const currentId = document.getElementById('current-narrative-id').value;
+        if (!change.id || change.id === currentId) {
+            scheduleRefresh('preview', loadPreview);
+        }
+    });
+}
+
 function switchTab(tab) {
     currentTab = tab;
     
@@ -544,14 +896,37 @@ function switchTab(tab) {
 }
 
 document.addEventListener('DOMContentLoaded', () => {
+    loadServerInfo();
     loadFiles();
     loadNarratives();
     setupContextList();
+    setupLiveUpdates();
 
     document.getElementById('refresh-files').addEventListener('click', refreshFiles);
     document.getElementById('save-narrative').addEventListener('click', saveNarrative);
     document.getElementById('save-as-new-narrative').addEventListener('click', saveAsNewNarrative);
+    document.getElementById('add-text-block').addEventListener('click', addTextBlock);
+
+    const fileSearch = document.getElementById('file-search');
+    let searchTimer = null;
+    fileSearch.addEventListener('input', () => {
+        clearTimeout(searchTimer);
+        searchTimer = setTimeout(() => searchFiles(fileSearch.value), 250);
+    });
     
+    let narrativeSearchTimer = null;
+    const scheduleNarrativeSearch = () => {
+        clearTimeout(narrativeSearchTimer);
+        narrativeSearchTimer = setTimeout(loadNarratives, 250);
+    };
+    document.getElementById('narrative-search').addEventListener('input', scheduleNarrativeSearch);
+    document.getElementById('narrative-tag-filter').addEventListener('input', scheduleNarrativeSearch);
+    document.getElementById('narrative-sort').addEventListener('change', loadNarratives);
+    document.getElementById('narrative-list').addEventListener('click', event => {
+        const chip = event.target.closest('.tag-chip');
+        if (chip) filterByTag(chip.dataset.tag);
+    });
+
     // Setup tab switching
     document.querySelectorAll('.tab-button').forEach(button => {
         button.addEventListener('click', () => {
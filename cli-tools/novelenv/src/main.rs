@@ -52,9 +52,9 @@ struct FindContextArgs {
 
 #[derive(Args)]
 struct WeaveArgs {
-    #[arg(help = "Subcommand (serve, resolve)")]
+    #[arg(help = "Subcommand (serve, mcp, resolve, diff, list, show, create, edit, rm, rename, copy, check, migrate-lines, export, import, history, restore)")]
     subcommand: String,
-    #[arg(help = "Additional arguments")]
+    #[arg(help = "Additional arguments", trailing_var_arg = true, allow_hyphen_values = true)]
     args: Vec<String>,
 }
 
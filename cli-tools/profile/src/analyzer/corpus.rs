@@ -0,0 +1,124 @@
+use anyhow::Result;
+use crate::models::*;
+use super::AnalysisOptions;
+
+/// 外れ値の判定には自分以外に最低これだけのファイルが必要
+pub const MIN_BASELINE_FILES: usize = 3;
+
+/// 前処理済みの1ファイル分のテキスト
+pub struct CorpusDocument {
+    pub path: String,
+    pub group: Option<String>,
+    pub text: String,
+}
+
+pub struct CorpusAnalyzer;
+
+impl CorpusAnalyzer {
+    /// ファイルごと・グループごと・全体のプロファイルを作り、外れ値を探す
+    ///
+    /// 全体とグループの集計は、テキストを連結して解析し直したもの（平均の平均にはしない）。
+    pub fn analyze(
+        documents: &[CorpusDocument],
+        outlier_threshold: f32,
+        options: &AnalysisOptions,
+    ) -> Result<CorpusProfile> {
+        let files = documents
+            .iter()
+            .map(|doc| {
+                Ok(FileProfile {
+                    path: doc.path.clone(),
+                    group: doc.group.clone(),
+                    profile: super::analyze(&doc.text, options)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut group_names: Vec<&str> = Vec::new();
+        for name in documents.iter().filter_map(|doc| doc.group.as_deref()) {
+            if !group_names.contains(&name) {
+                group_names.push(name);
+            }
+        }
+        let groups = group_names
+            .into_iter()
+            .map(|name| {
+                let members: Vec<&CorpusDocument> = documents
+                    .iter()
+                    .filter(|doc| doc.group.as_deref() == Some(name))
+                    .collect();
+                Ok(GroupProfile {
+                    name: name.to_string(),
+                    files: members.len(),
+                    profile: super::analyze(&join(&members), options)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let aggregate = super::analyze(&join(&documents.iter().collect::<Vec<_>>()), options)?;
+        let outliers = find_outliers(&files, outlier_threshold);
+
+        Ok(CorpusProfile {
+            files,
+            groups,
+            aggregate,
+            outliers,
+        })
+    }
+}
+
+fn join(documents: &[&CorpusDocument]) -> String {
+    documents
+        .iter()
+        .map(|doc| doc.text.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 各ファイルを「自分以外のファイル」の平均・標準偏差と比べ、z 値が閾値以上の指標を挙げる
+///
+/// 自分を含めると外れ値自身が基準を引っ張ってしまうので除いて比べる。
+/// 他のファイルがほぼ同じ値のときに z 値が発散しないよう、標準偏差には平均の 5% の下限を設ける。
+fn find_outliers(files: &[FileProfile], threshold: f32) -> Vec<Outlier> {
+    if files.len() < MIN_BASELINE_FILES + 1 {
+        return Vec::new();
+    }
+
+    let mut outliers = Vec::new();
+    for metric in CorpusMetric::ALL {
+        let values: Vec<f32> = files.iter().map(|file| metric.value(&file.profile)).collect();
+
+        for (i, file) in files.iter().enumerate() {
+            let others: Vec<f32> = values
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, value)| *value)
+                .collect();
+            let mean = others.iter().sum::<f32>() / others.len() as f32;
+            let variance =
+                others.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / (others.len() - 1) as f32;
+            let std_dev = variance.sqrt().max(mean.abs() * 0.05).max(f32::EPSILON);
+
+            let z_score = (values[i] - mean) / std_dev;
+            if z_score.abs() >= threshold {
+                outliers.push(Outlier {
+                    path: file.path.clone(),
+                    metric,
+                    value: values[i],
+                    baseline: mean,
+                    z_score,
+                });
+            }
+        }
+    }
+
+    // ファイルの順、同じファイル内では外れ方の大きい順
+    let order = |path: &str| files.iter().position(|file| file.path == path);
+    outliers.sort_by(|a, b| {
+        order(&a.path)
+            .cmp(&order(&b.path))
+            .then(b.z_score.abs().total_cmp(&a.z_score.abs()))
+    });
+    outliers
+}
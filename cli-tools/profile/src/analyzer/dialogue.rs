@@ -1,38 +1,326 @@
 use anyhow::Result;
-use crate::models::{DialogueStats, TextSegment, SegmentType};
+use std::collections::{HashMap, HashSet};
+use crate::models::{DialogueStats, SegmentType, SpeakerStats, TextSegment};
 use crate::parser::brackets::parse_dialogues;
 
+/// 名前のあとの敬称（同じ人物としてまとめるため取り除く）
+const HONORIFICS: [&str; 8] = ["さん", "くん", "君", "様", "さま", "ちゃん", "先輩", "先生"];
+/// 誰を指すか決まらない語は話者にしない
+const NOT_SPEAKERS: [&str; 12] = [
+    "彼", "彼女", "彼ら", "誰", "誰か", "皆", "全員", "二人", "男", "女", "声", "相手",
+];
+/// 名前とみなす長さの上限（漢字・カタカナの連続）
+const MAX_NAME_CHARS: usize = 6;
+/// 台詞の前の「○○は」を話者とみなすのは、同じ文にこれらの発話や動作の語か、目的語の「を」があるときだけ
+const SPEECH_VERBS: [&str; 26] = [
+    "言っ", "言う", "言い", "いっ", "いう", "答え", "尋ね", "訊", "聞い", "叫", "呟", "つぶや", "囁", "ささや",
+    "笑", "微笑", "頷", "うなず", "振り返", "振り向", "口を開", "続け", "首を", "顔を上げ", "息をつ", "声を",
+];
+
 pub struct DialogueAnalyzer;
 
 impl DialogueAnalyzer {
     pub fn analyze(text: &str) -> Result<DialogueStats> {
-        let segments = parse_dialogues(text)?;
-        
+        let segments = parse_dialogues(text)?.segments;
+
         let dialogue_chars: usize = segments
             .iter()
             .filter(|s| s.segment_type == SegmentType::Dialogue)
             .map(|s| s.content.chars().count())
             .sum();
-        
-        let dialogue_count = segments
+        let thoughts: Vec<&TextSegment> = segments
+            .iter()
+            .filter(|s| s.segment_type == SegmentType::Thought)
+            .collect();
+        let thought_chars: usize = thoughts.iter().map(|s| s.content.chars().count()).sum();
+
+        let dialogue_spans: Vec<(usize, usize)> = segments
             .iter()
             .filter(|s| s.segment_type == SegmentType::Dialogue)
-            .count();
-        
+            .map(|s| (s.start, s.end))
+            .collect();
+        let dialogue_count = dialogue_spans.len();
+
         let total_chars = text.chars().count();
-        let narrative_chars = total_chars.saturating_sub(dialogue_chars);
-        
+        let narrative_chars = total_chars.saturating_sub(dialogue_chars + thought_chars);
+
         let dialogue_ratio = if total_chars > 0 {
             dialogue_chars as f32 / total_chars as f32
         } else {
             0.0
         };
-        
+
+        let (speakers, unattributed_lines) = speaker_stats(&segments);
+
         Ok(DialogueStats {
             dialogue_chars,
             narrative_chars,
             dialogue_ratio,
             dialogue_count,
+            dialogue_spans,
+            thought_chars,
+            thought_count: thoughts.len(),
+            speakers,
+            unattributed_lines,
         })
     }
-}
\ No newline at end of file
+}
+
+/// 話者ごとの集計と、話者を推定できなかった台詞の数
+fn speaker_stats(segments: &[TextSegment]) -> (Vec<SpeakerStats>, usize) {
+    let guesses = guess_speakers(segments);
+    let total_chars: usize = guesses.iter().map(|(_, chars)| chars).sum();
+
+    let mut by_name: HashMap<&str, (usize, usize)> = HashMap::new();
+    let mut unattributed = 0;
+    for (speaker, chars) in &guesses {
+        match speaker {
+            Some(name) => {
+                let entry = by_name.entry(name.as_str()).or_insert((0, 0));
+                entry.0 += 1;
+                entry.1 += chars;
+            }
+            None => unattributed += 1,
+        }
+    }
+
+    let mut speakers: Vec<SpeakerStats> = by_name
+        .into_iter()
+        .map(|(name, (lines, chars))| SpeakerStats {
+            name: name.to_string(),
+            lines,
+            chars,
+            avg_utterance_length: chars as f32 / lines as f32,
+            share: if total_chars > 0 { chars as f32 / total_chars as f32 } else { 0.0 },
+        })
+        .collect();
+    speakers.sort_by(|a, b| b.lines.cmp(&a.lines).then(b.chars.cmp(&a.chars)).then(a.name.cmp(&b.name)));
+
+    (speakers, unattributed)
+}
+
+/// 台詞ごとの話者の推定（推定できなければ `None`）と、括弧を除いた文字数
+///
+/// 1. 直後の地の文の「と○○は」「と○○が」
+/// 2. 同じ行で直前にある「と○○は言った。」、または直前の地の文（同じ行か前の行）の最後の文の主語。
+///    主語は「○○は」のあとに発話や動作の述語が続くものか、1 の形で一度でも話者になった名前に限る
+/// 3. 地の文を挟まずに続く台詞は、二つ前の台詞と同じ話者（掛け合い）
+fn guess_speakers(segments: &[TextSegment]) -> Vec<(Option<String>, usize)> {
+    let confirmed = confirmed_speakers(segments);
+    let mut guesses: Vec<(Option<String>, usize)> = Vec::new();
+    // 地の文を挟まずに続いている台詞の、直前までの話者
+    let mut exchange: Vec<Option<String>> = Vec::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        match segment.segment_type {
+            SegmentType::Dialogue => {}
+            // 「と○○は言った」だけの地の文は掛け合いを切らない
+            SegmentType::Narrative if !segment.content.trim().is_empty() && attribution_after(&segment.content).is_none() => {
+                exchange.clear();
+                continue;
+            }
+            _ => continue,
+        }
+
+        let following = segments
+            .get(i + 1)
+            .filter(|s| s.segment_type == SegmentType::Narrative)
+            .and_then(|s| attribution_after(&s.content));
+        let preceding = || {
+            i.checked_sub(1)
+                .map(|prev| &segments[prev])
+                .filter(|s| s.segment_type == SegmentType::Narrative)
+                .and_then(|s| {
+                    // 「…」と○○は言った。「…」の後ろの台詞は同じ話者の続き
+                    if s.content.contains('\n') {
+                        attribution_before(&s.content, &confirmed)
+                    } else {
+                        attribution_after(&s.content).or_else(|| attribution_before(&s.content, &confirmed))
+                    }
+                })
+        };
+        let alternating = || {
+            exchange
+                .len()
+                .checked_sub(2)
+                .and_then(|back| exchange[back].clone())
+        };
+        let speaker = following.or_else(preceding).or_else(alternating);
+
+        let chars = utterance_chars(&segment.content);
+        // 同じ話者の台詞が続くとき（「…」と○○は言った。「…」）は1回の発言として数える
+        if speaker.is_none() || exchange.last() != Some(&speaker) {
+            exchange.push(speaker.clone());
+        }
+        guesses.push((speaker, chars));
+    }
+
+    guesses
+}
+
+/// 台詞の直後の「と○○は言った」で話者と分かった名前
+fn confirmed_speakers(segments: &[TextSegment]) -> HashSet<String> {
+    segments
+        .windows(2)
+        .filter(|pair| pair[0].segment_type == SegmentType::Dialogue && pair[1].segment_type == SegmentType::Narrative)
+        .filter_map(|pair| attribution_after(&pair[1].content))
+        .collect()
+}
+
+/// 括弧やダッシュを除いた台詞の長さ
+fn utterance_chars(content: &str) -> usize {
+    content
+        .trim()
+        .trim_start_matches(['「', '『', '“', '―', '—'])
+        .trim_end_matches(['」', '』', '”'])
+        .chars()
+        .count()
+}
+
+/// 台詞の直後の「と○○は言った」「って○○が」
+fn attribution_after(narrative: &str) -> Option<String> {
+    let line = narrative.lines().next()?.trim_start_matches('、');
+    let rest = line.strip_prefix('と').or_else(|| line.strip_prefix("って"))?;
+    subject_at_start(rest.trim_start_matches('、')).map(|(name, _)| name)
+}
+
+/// 台詞の直前の地の文のうち、台詞と同じ行か一つ前の行の最後の文の主語
+///
+/// 台詞のあとの「と○○は言った。」は前の台詞の話者なので、ここでは拾わない。
+/// 「雨が降っていた。」「部屋の中は暗かった。」のような文の主語を話者にしないよう、
+/// 発話や動作の語が続く「○○は」か、すでに話者と分かっている名前だけを選ぶ。
+fn attribution_before(narrative: &str, confirmed: &HashSet<String>) -> Option<String> {
+    let last_line = narrative
+        .trim_end_matches([' ', '　', '\t'])
+        .trim_end_matches('\n')
+        .rsplit('\n')
+        .next()?;
+    // 「すぐ「先生」といいたくなる」のように文の途中で括弧を使った語句は台詞ではない
+    let trimmed = last_line.trim_end_matches([' ', '　']);
+    if !trimmed.is_empty() && !trimmed.ends_with(['。', '、', '！', '？']) {
+        return None;
+    }
+    let sentence = trimmed
+        .trim_end_matches(['。', '、'])
+        .rsplit('。')
+        .next()?;
+    if attribution_after(sentence).is_some() {
+        return None;
+    }
+    first_subject(sentence, confirmed)
+}
+
+/// 文の中で最初に現れる、話者らしい「○○は」「○○が」
+fn first_subject(sentence: &str, confirmed: &HashSet<String>) -> Option<String> {
+    let mut previous = None;
+    for (i, c) in sentence.char_indices() {
+        let starts_name = is_name_char(c) && !previous.is_some_and(is_name_char);
+        if starts_name {
+            if let Some((name, particle)) = subject_at_start(&sentence[i..]) {
+                let predicate = &sentence[i + name.len()..];
+                // 「窓を開けた」のように目的語をとる述語は動作とみなす（「暗かった」のような状態は除く）
+                let acts = particle == 'は'
+                    && (predicate.contains('を') || SPEECH_VERBS.iter().any(|verb| predicate.contains(verb)));
+                if acts || confirmed.contains(&name) {
+                    return Some(name);
+                }
+            }
+        }
+        previous = Some(c);
+    }
+    None
+}
+
+/// 先頭の「○○は」「○○が」「○○も」の名前（敬称は取り除く）と助詞
+fn subject_at_start(text: &str) -> Option<(String, char)> {
+    let name: String = text
+        .chars()
+        .take_while(|&c| is_name_char(c))
+        .collect();
+    let name_len = name.chars().count();
+    if name_len == 0 || name_len > MAX_NAME_CHARS {
+        return None;
+    }
+
+    let rest = &text[name.len()..];
+    let honorific = HONORIFICS.iter().find(|h| rest.starts_with(*h)).copied().unwrap_or("");
+    let rest = &rest[honorific.len()..];
+    let particle = rest.chars().next().filter(|c| matches!(c, 'は' | 'が' | 'も'))?;
+    if NOT_SPEAKERS.contains(&name.as_str()) {
+        return None;
+    }
+    Some((name, particle))
+}
+
+fn is_name_char(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '々' | '\u{30A1}'..='\u{30FA}' | 'ー')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speakers(text: &str) -> Vec<Option<String>> {
+        let segments = parse_dialogues(text).unwrap().segments;
+        guess_speakers(&segments).into_iter().map(|(speaker, _)| speaker).collect()
+    }
+
+    fn named(names: &[&str]) -> Vec<Option<String>> {
+        names
+            .iter()
+            .map(|name| (!name.is_empty()).then(|| name.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn attribution_after_dialogue() {
+        assert_eq!(speakers("「寒いね」と健太は言った。\n「うん」と美咲さんが答えた。"), named(&["健太", "美咲"]));
+    }
+
+    #[test]
+    fn same_line_continuation() {
+        assert_eq!(speakers("「待って」と健太は言った。「まだ話がある」"), named(&["健太", "健太"]));
+    }
+
+    #[test]
+    fn subject_of_preceding_sentence_needs_a_speech_verb() {
+        assert_eq!(speakers("朝、健太は振り返って言った。\n「おはよう」"), named(&["健太"]));
+        assert_eq!(speakers("朝、健太は窓を開けた。\n「寒いな」"), named(&["健太"]));
+        assert_eq!(speakers("美咲は青い空に見とれていた。\n「きれい」"), named(&[""]));
+        assert_eq!(speakers("雨が降っていた。\n「寒いね」"), named(&[""]));
+        assert_eq!(speakers("部屋の中は暗かった。\n「電気をつけよう」"), named(&[""]));
+        assert_eq!(speakers("どこで生れたかとんと見当がつかぬ。\n「ニャー」"), named(&[""]));
+    }
+
+    #[test]
+    fn quoted_words_inside_a_sentence_have_no_speaker() {
+        assert_eq!(speakers("私はその人の記憶を呼び起すごとに、すぐ「先生」といいたくなる。"), named(&[""]));
+    }
+
+    #[test]
+    fn confirmed_names_count_in_preceding_sentence() {
+        let text = "「行こう」と美咲は言った。\n\n翌日、美咲が部屋に来た。\n「おはよう」";
+        assert_eq!(speakers(text), named(&["美咲", "美咲"]));
+    }
+
+    #[test]
+    fn alternation_in_exchange() {
+        let text = "「どこへ行くの」と美咲が聞いた。\n「学校」と健太は答えた。\n「一人で？」\n「うん」";
+        assert_eq!(speakers(text), named(&["美咲", "健太", "美咲", "健太"]));
+    }
+
+    #[test]
+    fn pronouns_are_not_speakers() {
+        assert_eq!(speakers("「ねえ」と彼女は言った。"), named(&[""]));
+    }
+
+    #[test]
+    fn thoughts_and_narration_are_not_utterances() {
+        let stats = DialogueAnalyzer::analyze("（眠い）\n健太は歩いた。\n「おはよう」と健太は言った。").unwrap();
+        assert_eq!(stats.dialogue_count, 1);
+        assert_eq!(stats.thought_count, 1);
+        assert_eq!(stats.speakers.len(), 1);
+        assert_eq!(stats.speakers[0].name, "健太");
+        assert_eq!(stats.unattributed_lines, 0);
+    }
+}
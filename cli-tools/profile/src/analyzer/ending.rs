@@ -0,0 +1,149 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use crate::models::{EndingRun, EndingStats, EndingType, ProseStyle};
+
+/// 同じ語尾がこの数以上続いたら単調な箇所として挙げる（〜た。〜た。〜た。）
+pub const MIN_REPORTED_RUN: usize = 3;
+
+const POLITE_SUFFIXES: [&str; 10] = [
+    "ます", "ました", "ません", "ませんでした", "ましょう", "です", "でした", "でしょう", "ですね", "ですよ",
+];
+const PAST_SUFFIXES: [&str; 3] = ["た", "だった", "であった"];
+const COPULA_SUFFIXES: [&str; 4] = ["である", "だ", "だろう", "であろう"];
+
+pub struct EndingAnalyzer;
+
+impl EndingAnalyzer {
+    pub fn analyze(sentences: &[String]) -> Result<EndingStats> {
+        let endings: Vec<EndingType> = sentences.iter().map(|s| classify(s)).collect();
+
+        let mut counts = BTreeMap::new();
+        for ending in &endings {
+            *counts.entry(*ending).or_insert(0) += 1;
+        }
+
+        let runs = find_runs(&endings);
+        // 同じ長さなら先に現れた方
+        let longest_run = runs.iter().rev().max_by_key(|run| run.length).cloned();
+        let runs = runs
+            .into_iter()
+            .filter(|run| run.length >= MIN_REPORTED_RUN)
+            .collect();
+
+        let (style, mixed_style_sentences) = style_mixing(&endings);
+
+        Ok(EndingStats {
+            counts,
+            longest_run,
+            runs,
+            style,
+            mixed_style_sentences,
+        })
+    }
+}
+
+/// 1文の語尾を分類する
+fn classify(sentence: &str) -> EndingType {
+    let trimmed = sentence
+        .trim()
+        .trim_end_matches(['。', '．', '.', '　', ' ']);
+
+    let is_bracketed = |open: char, close: char| trimmed.starts_with(open) && trimmed.ends_with(close);
+    if is_bracketed('「', '」') || is_bracketed('『', '』') {
+        return EndingType::Dialogue;
+    }
+
+    let body = trimmed.trim_end_matches(['」', '』', '）', ')']);
+    match body.chars().last() {
+        None => return EndingType::Other,
+        Some('？' | '?') => return EndingType::Question,
+        Some('！' | '!') => return EndingType::Exclamation,
+        _ => {}
+    }
+    let body = body.trim_end_matches(['？', '?', '！', '!']);
+
+    if POLITE_SUFFIXES.iter().any(|suffix| body.ends_with(suffix)) {
+        return EndingType::Polite;
+    }
+    if PAST_SUFFIXES.iter().any(|suffix| body.ends_with(suffix)) {
+        return EndingType::Ta;
+    }
+    // 撥音便の過去形（読んだ・死んだ）。「なんだ」は断定として扱う
+    if body.ends_with("んだ") && !body.ends_with("なんだ") {
+        return EndingType::Ta;
+    }
+    if COPULA_SUFFIXES.iter().any(|suffix| body.ends_with(suffix)) {
+        return EndingType::Da;
+    }
+
+    match body.chars().last() {
+        Some('う' | 'く' | 'ぐ' | 'す' | 'ず' | 'つ' | 'ぬ' | 'ぶ' | 'む' | 'る' | 'い') => EndingType::Present,
+        Some(c) if is_noun_like(c) => EndingType::Taigen,
+        _ => EndingType::Other,
+    }
+}
+
+/// 漢字・カタカナ・英数字で終わっていれば名詞（体言）とみなす
+fn is_noun_like(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '々' | '\u{30A1}'..='\u{30FA}' | 'ー')
+        || c.is_alphanumeric() && c.is_ascii()
+        || matches!(c, '０'..='９' | 'Ａ'..='Ｚ' | 'ａ'..='ｚ')
+}
+
+/// 同じ語尾の連続を探す。会話文だけの文は地の文の流れを切らないよう飛ばす
+fn find_runs(endings: &[EndingType]) -> Vec<EndingRun> {
+    let mut runs: Vec<EndingRun> = Vec::new();
+    let mut current: Option<EndingRun> = None;
+
+    for (i, &ending) in endings.iter().enumerate() {
+        if ending == EndingType::Dialogue {
+            continue;
+        }
+        let sentence = i + 1;
+        match current.as_mut() {
+            Some(run) if run.ending == ending => {
+                run.length += 1;
+                run.last_sentence = sentence;
+            }
+            _ => {
+                runs.extend(current.take());
+                current = Some(EndingRun {
+                    ending,
+                    length: 1,
+                    first_sentence: sentence,
+                    last_sentence: sentence,
+                });
+            }
+        }
+    }
+    runs.extend(current);
+    runs
+}
+
+/// 地の文の文体を多数決で決め、少数派の文の番号を返す
+///
+/// 体言止めや疑問文などはどちらの文体でもありうるので数えない。
+fn style_mixing(endings: &[EndingType]) -> (Option<ProseStyle>, Vec<usize>) {
+    let style_of = |ending: &EndingType| match ending {
+        EndingType::Polite => Some(ProseStyle::Polite),
+        EndingType::Ta | EndingType::Present | EndingType::Da => Some(ProseStyle::Plain),
+        _ => None,
+    };
+
+    let polite = endings.iter().filter(|e| style_of(e) == Some(ProseStyle::Polite)).count();
+    let plain = endings.iter().filter(|e| style_of(e) == Some(ProseStyle::Plain)).count();
+    let style = match (plain, polite) {
+        (0, 0) => return (None, Vec::new()),
+        (plain, polite) if polite > plain => ProseStyle::Polite,
+        _ => ProseStyle::Plain,
+    };
+
+    let mixed = endings
+        .iter()
+        .enumerate()
+        .filter(|(_, ending)| style_of(ending).is_some_and(|s| s != style))
+        .map(|(i, _)| i + 1)
+        .collect();
+
+    (Some(style), mixed)
+}
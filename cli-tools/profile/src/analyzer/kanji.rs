@@ -0,0 +1,65 @@
+// 漢字の難しさ
+// 区分の表は data/kanji_grades.txt（学年別漢字配当表と常用漢字表）
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use crate::models::{KanjiCount, KanjiStats};
+
+const GRADE_TABLE: &str = include_str!("data/kanji_grades.txt");
+/// 表の区分のうち、小学校で習わない常用漢字
+const SECONDARY: u8 = 7;
+
+pub struct KanjiAnalyzer;
+
+impl KanjiAnalyzer {
+    pub fn analyze(text: &str) -> Result<KanjiStats> {
+        let grades = grade_table();
+        let mut stats = KanjiStats::default();
+        let mut hyougai: HashMap<char, usize> = HashMap::new();
+
+        for ch in text.chars().filter(|&ch| is_kanji(ch)) {
+            stats.total += 1;
+            match grades.get(&ch) {
+                Some(&grade) if grade < SECONDARY => stats.by_grade[grade as usize - 1] += 1,
+                Some(_) => stats.secondary += 1,
+                None => {
+                    stats.hyougai += 1;
+                    *hyougai.entry(ch).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut hyougai_kanji: Vec<KanjiCount> = hyougai
+            .into_iter()
+            .map(|(kanji, count)| KanjiCount {
+                kanji: kanji.to_string(),
+                count,
+            })
+            .collect();
+        hyougai_kanji.sort_by(|a, b| b.count.cmp(&a.count).then(a.kanji.cmp(&b.kanji)));
+        stats.hyougai_kanji = hyougai_kanji;
+
+        Ok(stats)
+    }
+}
+
+/// 漢字から区分（1〜7）を引く表
+fn grade_table() -> &'static HashMap<char, u8> {
+    static TABLE: OnceLock<HashMap<char, u8>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        GRADE_TABLE
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(' '))
+            .filter_map(|(grade, kanji)| Some((grade.parse::<u8>().ok()?, kanji)))
+            .flat_map(|(grade, kanji)| kanji.chars().map(move |ch| (ch, grade)))
+            .collect()
+    })
+}
+
+/// 々や〆のような記号は数えない（どの表にも載っていないので表外字になってしまう）
+fn is_kanji(ch: char) -> bool {
+    matches!(ch,
+        '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' | '\u{20000}'..='\u{2FA1F}')
+}
@@ -0,0 +1,106 @@
+// 語彙の豊かさ
+//
+// 既定では文をまたがない文字の2-gram（句読点と記号は区切りとして扱う）を単位に数える。
+// 分かち書きのない日本語でも辞書なしで比べられるが、値は形態素で数えたものより高めに出る。
+
+use anyhow::Result;
+use std::collections::HashMap;
+use crate::models::{LexicalDiversity, LexicalUnit};
+use super::AnalysisOptions;
+
+/// MATTR の窓の大きさ（単位の数）
+pub const MATTR_WINDOW: usize = 100;
+
+pub struct LexicalAnalyzer;
+
+impl LexicalAnalyzer {
+    pub fn analyze(sentences: &[String], options: &AnalysisOptions) -> Result<LexicalDiversity> {
+        let (unit, units) = units(sentences, options)?;
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for unit in &units {
+            *counts.entry(unit.as_str()).or_insert(0) += 1;
+        }
+
+        let tokens = units.len();
+        let types = counts.len();
+        let hapax = counts.values().filter(|&&count| count == 1).count();
+        let ratio = |numerator: usize, denominator: usize| {
+            if denominator == 0 {
+                0.0
+            } else {
+                numerator as f32 / denominator as f32
+            }
+        };
+
+        Ok(LexicalDiversity {
+            unit,
+            tokens,
+            types,
+            type_token_ratio: ratio(types, tokens),
+            mattr: mattr(&units, MATTR_WINDOW),
+            hapax_ratio: ratio(hapax, types),
+        })
+    }
+}
+
+#[cfg(feature = "tokenizer")]
+fn units(sentences: &[String], options: &AnalysisOptions) -> Result<(LexicalUnit, Vec<String>)> {
+    match &options.tokenizer {
+        Some(tokenizer) => Ok((LexicalUnit::Token, tokenizer.words(sentences)?)),
+        None => Ok((LexicalUnit::CharBigram, char_bigrams(sentences))),
+    }
+}
+
+#[cfg(not(feature = "tokenizer"))]
+fn units(sentences: &[String], _options: &AnalysisOptions) -> Result<(LexicalUnit, Vec<String>)> {
+    Ok((LexicalUnit::CharBigram, char_bigrams(sentences)))
+}
+
+fn char_bigrams(sentences: &[String]) -> Vec<String> {
+    let mut bigrams = Vec::new();
+    for sentence in sentences {
+        let chars: Vec<char> = sentence.chars().collect();
+        for pair in chars.windows(2) {
+            if pair.iter().all(|&c| is_word_char(c)) {
+                bigrams.push(pair.iter().collect());
+            }
+        }
+    }
+    bigrams
+}
+
+/// 語の一部になる文字（かな・漢字・英数字）
+pub(crate) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, 'ー' | '々' | '〆' | 'ヶ')
+}
+
+/// 窓を1単位ずつずらした TTR の平均（単位が窓より少なければ全体の TTR）
+fn mattr(units: &[String], window: usize) -> f32 {
+    if units.is_empty() {
+        return 0.0;
+    }
+    if units.len() <= window {
+        let types: std::collections::HashSet<&String> = units.iter().collect();
+        return types.len() as f32 / units.len() as f32;
+    }
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for unit in &units[..window] {
+        *counts.entry(unit.as_str()).or_insert(0) += 1;
+    }
+    let mut total = counts.len();
+    for i in window..units.len() {
+        let outgoing = units[i - window].as_str();
+        if let Some(count) = counts.get_mut(outgoing) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(outgoing);
+            }
+        }
+        *counts.entry(units[i].as_str()).or_insert(0) += 1;
+        total += counts.len();
+    }
+
+    total as f32 / (units.len() - window + 1) as f32 / window as f32
+}
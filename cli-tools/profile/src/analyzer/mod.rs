@@ -1,32 +1,71 @@
 use anyhow::Result;
 use crate::models::*;
+use crate::parser::ruby::strip_ruby;
 
 mod stats;
 mod dialogue;
 mod sentence;
 mod rhythm;
+mod ending;
+mod repetition;
+mod kanji;
+mod lexical;
+#[cfg(feature = "tokenizer")]
+pub mod tokenizer;
+mod corpus;
+mod timeline;
 
 use stats::BasicStatsAnalyzer;
 use dialogue::DialogueAnalyzer;
 use sentence::SentenceAnalyzer;
 use rhythm::RhythmAnalyzer;
+use ending::EndingAnalyzer;
+use repetition::RepetitionAnalyzer;
+use kanji::KanjiAnalyzer;
+use lexical::LexicalAnalyzer;
 
-pub fn analyze(text: &str) -> Result<TextProfile> {
-    // 前処理：改行の正規化
-    let normalized = normalize_text(text);
+pub use corpus::{CorpusAnalyzer, CorpusDocument, MIN_BASELINE_FILES};
+pub use ending::MIN_REPORTED_RUN;
+pub use lexical::MATTR_WINDOW;
+pub use repetition::RepetitionOptions;
+pub use timeline::TimelineAnalyzer;
+
+/// 解析の設定（コマンドラインから決まり、コーパスや区間の解析にもそのまま渡す）
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    pub ruby: crate::RubyMode,
+    pub repetition: RepetitionOptions,
+    /// 指定されていれば語彙の豊かさを形態素で数える
+    #[cfg(feature = "tokenizer")]
+    pub tokenizer: Option<tokenizer::Tokenizer>,
+}
+
+pub fn analyze(text: &str, options: &AnalysisOptions) -> Result<TextProfile> {
+    // 前処理：改行の正規化、ルビと注記の除去
+    let stripped = strip_ruby(&normalize_text(text), options.ruby);
+    let normalized = stripped.text;
     
     // 各分析を実行
     let basic_stats = BasicStatsAnalyzer::analyze(&normalized)?;
     let sentences = SentenceAnalyzer::analyze(&normalized)?;
     let dialogue_stats = DialogueAnalyzer::analyze(&normalized)?;
     let rhythm_metrics = RhythmAnalyzer::analyze(&sentences)?;
-    let readability = calculate_readability(&basic_stats, &rhythm_metrics);
+    let ending_stats = EndingAnalyzer::analyze(&sentences)?;
+    let repetition_stats = RepetitionAnalyzer::analyze(&sentences, &options.repetition)?;
+    let kanji_stats = KanjiAnalyzer::analyze(&normalized)?;
+    let lexical_diversity = LexicalAnalyzer::analyze(&sentences, options)?;
+    let readability = calculate_readability(&basic_stats, &rhythm_metrics, &kanji_stats, &lexical_diversity);
     
     Ok(TextProfile {
         basic_stats,
         dialogue_stats,
         rhythm_metrics,
         readability,
+        ruby_stats: stripped.stats,
+        ending_stats,
+        repetition_stats,
+        lexical_diversity,
+        kanji_stats,
     })
 }
 
@@ -36,36 +75,51 @@ fn normalize_text(text: &str) -> String {
         .replace('\r', "\n")
 }
 
-fn calculate_readability(stats: &BasicStats, rhythm: &RhythmMetrics) -> ReadabilityScore {
+/// 読みやすさのスコア（0〜100、高いほど読みやすい）
+///
+/// 各要因を「読みやすい」端で1、「読みにくい」端で0になるよう直線で割り振り、重みをつけて平均する。
+/// 両端の値は児童書・ライトノベル・一般文芸・古典の文章を見比べて決めた目安。
+/// 100字あたりの文数は平均文長の裏返しなので、要因として出力はするがスコアには入れない。
+fn calculate_readability(
+    stats: &BasicStats,
+    rhythm: &RhythmMetrics,
+    kanji: &KanjiStats,
+    lexical: &LexicalDiversity,
+) -> ReadabilityScore {
     let hiragana_ratio = stats.char_types.hiragana_ratio();
-    
-    // シンプルな読みやすさスコア計算
-    // ひらがな率が高く、文が短めで、適度な句読点があると読みやすい
-    let hiragana_score = (hiragana_ratio * 100.0).min(50.0) * 2.0; // 最大50点
-    let sentence_score = if rhythm.avg_sentence_length < 40.0 { 
-        30.0 
-    } else if rhythm.avg_sentence_length < 60.0 { 
-        20.0 
-    } else { 
-        10.0 
-    };
-    
     let punctuation_density = stats.total_sentences as f32 / stats.total_chars.max(1) as f32 * 100.0;
-    let punctuation_score = if punctuation_density > 2.0 && punctuation_density < 5.0 {
-        20.0
-    } else {
-        10.0
+    let paragraph_structure = stats.char_types.total() as f32 / stats.total_paragraphs.max(1) as f32;
+    let difficult_kanji_ratio = kanji.ratio(kanji.secondary + kanji.hyougai);
+    // 文字の2-gram は形態素より異なりが多く出る
+    let (diverse, repetitive) = match lexical.unit {
+        LexicalUnit::CharBigram => (0.95, 0.75),
+        LexicalUnit::Token => (0.85, 0.60),
     };
-    
-    let score = (hiragana_score + sentence_score + punctuation_score).min(100.0);
-    
+
+    let factors = [
+        (0.25, ease(hiragana_ratio, 0.45, 0.70)),
+        (0.25, ease(rhythm.avg_sentence_length, 60.0, 20.0)),
+        (0.20, ease(difficult_kanji_ratio, 0.25, 0.03)),
+        (0.15, ease(paragraph_structure, 200.0, 40.0)),
+        (0.15, ease(lexical.mattr, diverse, repetitive)),
+    ];
+    let score = factors.iter().map(|(weight, ease)| weight * ease).sum::<f32>() * 100.0;
+
     ReadabilityScore {
         score,
         factors: ReadabilityFactors {
             hiragana_ratio,
             sentence_complexity: rhythm.avg_sentence_length,
             punctuation_density,
-            paragraph_structure: 0.0, // TODO: 実装
+            paragraph_structure,
+            difficult_kanji_ratio,
+            lexical_diversity: lexical.mattr,
         },
+        audience: TargetAudience::from_score(score),
     }
-}
\ No newline at end of file
+}
+
+/// `hard` で0、`easy` で1になる直線（範囲外は0か1）
+fn ease(value: f32, hard: f32, easy: f32) -> f32 {
+    ((value - hard) / (easy - hard)).clamp(0.0, 1.0)
+}
@@ -0,0 +1,243 @@
+// 語句の繰り返し
+//
+// 形態素解析は使わず、漢字かカタカナで始まる2〜4文字の並び（文をまたがない）を語句の候補にする。
+// 「思わず」の「思わ」のように、ほとんどが長い語句の一部として現れる短い候補は除く。
+
+use anyhow::Result;
+use std::collections::HashMap;
+use crate::models::{NearRepeat, PhraseCount, PhraseOccurrences, RepetitionStats};
+
+const MIN_GRAM: usize = 2;
+const MAX_GRAM: usize = 4;
+/// テキスト全体でこの回数以上使われた語句を多用として挙げる
+const OVERUSED_MIN_COUNT: usize = 5;
+const OVERUSED_LIMIT: usize = 10;
+
+/// 既定の口癖リスト（`--crutch-words` で追加できる）
+pub const DEFAULT_CRUTCH_WORDS: [&str; 16] = [
+    "ふと", "思わず", "少し", "すこし", "とても", "すごく", "なんだか", "ちょっと",
+    "そっと", "やはり", "やっぱり", "まるで", "一瞬", "静かに", "ゆっくりと", "不意に",
+];
+
+/// 既定のストップリスト。代名詞や形式名詞など、繰り返しても気にならない語（`--stop-words` で追加できる）
+pub const DEFAULT_STOP_WORDS: [&str; 19] = [
+    "彼", "彼女", "彼ら", "私", "僕", "俺", "自分", "事", "時", "物", "方", "様", "為",
+    "中", "前", "後", "何", "今", "人",
+];
+
+#[derive(Debug, Clone)]
+pub struct RepetitionOptions {
+    /// この文数の範囲に
+    pub window: usize,
+    /// この回数以上現れたら近接した繰り返しとする
+    pub min_count: usize,
+    pub crutch_words: Vec<String>,
+    pub stop_words: Vec<String>,
+}
+
+impl Default for RepetitionOptions {
+    fn default() -> Self {
+        Self {
+            window: 5,
+            min_count: 3,
+            crutch_words: DEFAULT_CRUTCH_WORDS.iter().map(|w| w.to_string()).collect(),
+            stop_words: DEFAULT_STOP_WORDS.iter().map(|w| w.to_string()).collect(),
+        }
+    }
+}
+
+pub struct RepetitionAnalyzer;
+
+impl RepetitionAnalyzer {
+    pub fn analyze(sentences: &[String], options: &RepetitionOptions) -> Result<RepetitionStats> {
+        let total_chars: usize = sentences.iter().map(|s| s.chars().count()).sum();
+        let grams = collect_grams(sentences, &options.stop_words);
+
+        Ok(RepetitionStats {
+            crutch_words: crutch_words(sentences, &options.crutch_words),
+            near_repeats: near_repeats(&grams, options),
+            overused: overused(&grams, total_chars),
+        })
+    }
+}
+
+fn crutch_words(sentences: &[String], words: &[String]) -> Vec<PhraseOccurrences> {
+    let mut found: Vec<PhraseOccurrences> = words
+        .iter()
+        .filter(|word| !word.is_empty())
+        .filter_map(|word| {
+            let mut occurrence_sentences = Vec::new();
+            for (i, sentence) in sentences.iter().enumerate() {
+                for _ in sentence.matches(word.as_str()) {
+                    occurrence_sentences.push(i + 1);
+                }
+            }
+            (!occurrence_sentences.is_empty()).then(|| PhraseOccurrences {
+                phrase: word.clone(),
+                count: occurrence_sentences.len(),
+                sentences: occurrence_sentences,
+            })
+        })
+        .collect();
+
+    found.sort_by_key(|word| std::cmp::Reverse(word.count));
+    found
+}
+
+/// 語句の候補ごとの出現位置（文番号、文中の出現ごとに1つ）
+fn collect_grams(sentences: &[String], stop_words: &[String]) -> HashMap<String, Vec<usize>> {
+    let mut grams: HashMap<String, Vec<usize>> = HashMap::new();
+
+    for (i, sentence) in sentences.iter().enumerate() {
+        let chars: Vec<char> = sentence.chars().collect();
+        for start in 0..chars.len() {
+            if !is_content_start(chars[start]) {
+                continue;
+            }
+            for len in MIN_GRAM..=MAX_GRAM {
+                let Some(window) = chars.get(start..start + len) else {
+                    break;
+                };
+                if !window.iter().all(|&c| is_word_char(c)) {
+                    break;
+                }
+                let gram: String = window.iter().collect();
+                if is_stop_word(&gram, stop_words) {
+                    continue;
+                }
+                grams.entry(gram).or_default().push(i + 1);
+            }
+        }
+    }
+
+    grams
+}
+
+fn is_content_start(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{30A1}'..='\u{30FA}')
+}
+
+fn is_word_char(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{3096}' | '\u{30A1}'..='\u{30FA}' | 'ー' | '々' | '\u{4E00}'..='\u{9FFF}')
+}
+
+/// 送り仮名や助詞を除いた部分がストップリストの語なら対象外（「彼女は」「彼の」）
+fn is_stop_word(gram: &str, stop_words: &[String]) -> bool {
+    let stem = gram.trim_end_matches(|c: char| matches!(c, '\u{3041}'..='\u{3096}'));
+    stop_words.iter().any(|word| word == stem)
+}
+
+/// ほとんど（8割以上）が `longer` の一部として現れる短い語句か
+fn is_part_of(shorter: &str, shorter_count: usize, longer: &str, longer_count: usize) -> bool {
+    longer.len() > shorter.len() && longer.contains(shorter) && longer_count * 10 >= shorter_count * 8
+}
+
+/// `window` 文の範囲に `min_count` 回以上現れた語句。範囲が重なる出現はひとまとまりにする
+fn near_repeats(grams: &HashMap<String, Vec<usize>>, options: &RepetitionOptions) -> Vec<NearRepeat> {
+    let window = options.window.max(1);
+    let min_count = options.min_count.max(2);
+
+    let mut repeats = Vec::new();
+    for (gram, positions) in grams {
+        if positions.len() < min_count {
+            continue;
+        }
+
+        // positions[i] から window 文以内に min_count 回以上あれば、その出現すべてに印を付ける
+        let mut marked = vec![false; positions.len()];
+        for i in 0..positions.len() {
+            let end = positions[i..].partition_point(|&p| p < positions[i] + window) + i;
+            if end - i >= min_count {
+                marked[i..end].iter_mut().for_each(|m| *m = true);
+            }
+        }
+
+        let mut cluster: Vec<usize> = Vec::new();
+        for (i, &position) in positions.iter().enumerate() {
+            if !marked[i] || cluster.last().is_some_and(|&last| position >= last + window) {
+                push_cluster(&mut repeats, gram, &mut cluster);
+            }
+            if marked[i] {
+                cluster.push(position);
+            }
+        }
+        push_cluster(&mut repeats, gram, &mut cluster);
+    }
+
+    let mut kept: Vec<NearRepeat> = repeats
+        .iter()
+        .filter(|short| {
+            !repeats.iter().any(|long| {
+                long.first_sentence <= short.last_sentence
+                    && short.first_sentence <= long.last_sentence
+                    && is_part_of(&short.phrase, short.count, &long.phrase, long.count)
+            })
+        })
+        // 「手紙」があれば「手紙を」は挙げない
+        .filter(|long| {
+            !repeats.iter().any(|short| {
+                short.first_sentence <= long.last_sentence
+                    && long.first_sentence <= short.last_sentence
+                    && short.count > long.count
+                    && long.phrase.strip_prefix(short.phrase.as_str()).is_some_and(|rest| {
+                        !rest.is_empty() && rest.chars().all(|c| matches!(c, '\u{3041}'..='\u{3096}'))
+                    })
+            })
+        })
+        .cloned()
+        .collect();
+
+    kept.sort_by(|a, b| {
+        a.first_sentence
+            .cmp(&b.first_sentence)
+            .then(b.count.cmp(&a.count))
+            .then(a.phrase.cmp(&b.phrase))
+    });
+    kept
+}
+
+fn push_cluster(repeats: &mut Vec<NearRepeat>, gram: &str, cluster: &mut Vec<usize>) {
+    let (Some(&first), Some(&last)) = (cluster.first(), cluster.last()) else {
+        return;
+    };
+    repeats.push(NearRepeat {
+        phrase: gram.to_string(),
+        count: cluster.len(),
+        first_sentence: first,
+        last_sentence: last,
+        sentences: std::mem::take(cluster),
+    });
+}
+
+/// テキスト全体での多用語句
+///
+/// 「出し」（思い出し・降り出し）のような漢字1字と送り仮名は別の語の一部であることが多いので、
+/// 漢字かカタカナが2字以上の語句だけを数える。
+fn overused(grams: &HashMap<String, Vec<usize>>, total_chars: usize) -> Vec<PhraseCount> {
+    let frequent: Vec<(&String, usize)> = grams
+        .iter()
+        .filter(|(gram, _)| gram.chars().filter(|&c| is_content_start(c)).count() >= 2)
+        .map(|(gram, positions)| (gram, positions.len()))
+        .filter(|(_, count)| *count >= OVERUSED_MIN_COUNT)
+        .collect();
+
+    let mut kept: Vec<(&String, usize)> = frequent
+        .iter()
+        .filter(|(short, short_count)| {
+            !frequent
+                .iter()
+                .any(|(long, long_count)| is_part_of(short, *short_count, long, *long_count))
+        })
+        .cloned()
+        .collect();
+
+    kept.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    kept.into_iter()
+        .take(OVERUSED_LIMIT)
+        .map(|(gram, count)| PhraseCount {
+            phrase: gram.clone(),
+            count,
+            per_10k_chars: count as f32 * 10_000.0 / total_chars.max(1) as f32,
+        })
+        .collect()
+}
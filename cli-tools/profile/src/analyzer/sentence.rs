@@ -1,44 +1,51 @@
 use anyhow::Result;
+use std::ops::Range;
 
 pub struct SentenceAnalyzer;
 
 impl SentenceAnalyzer {
     pub fn analyze(text: &str) -> Result<Vec<String>> {
-        let mut sentences = Vec::new();
-        let mut current = String::new();
+        Ok(Self::spans(text)
+            .into_iter()
+            .map(|span| text[span].to_string())
+            .collect())
+    }
+
+    /// 各文の（前後の空白を除いた）バイト範囲
+    pub fn spans(text: &str) -> Vec<Range<usize>> {
+        let mut spans = Vec::new();
+        let mut start = 0;
         let mut in_dialogue = false;
-        
-        for ch in text.chars() {
-            current.push(ch);
-            
+
+        for (i, ch) in text.char_indices() {
             // 会話文の開始/終了を追跡
             match ch {
                 '「' | '『' => in_dialogue = true,
                 '」' | '』' => in_dialogue = false,
                 _ => {}
             }
-            
+
             // 文末記号での分割（会話文内は除外）
             if !in_dialogue && (ch == '。' || ch == '！' || ch == '？') {
-                let sentence = current.trim().to_string();
-                if !sentence.is_empty() {
-                    sentences.push(sentence);
-                }
-                current.clear();
+                let end = i + ch.len_utf8();
+                push_trimmed(&mut spans, text, start..end);
+                start = end;
             }
         }
-        
+
         // 最後の文を追加
-        let sentence = current.trim().to_string();
-        if !sentence.is_empty() {
-            sentences.push(sentence);
-        }
-        
-        // 文が一つもない場合は全体を1文として扱う
-        if sentences.is_empty() && !text.trim().is_empty() {
-            sentences.push(text.trim().to_string());
-        }
-        
-        Ok(sentences)
+        push_trimmed(&mut spans, text, start..text.len());
+
+        spans
+    }
+}
+
+fn push_trimmed(spans: &mut Vec<Range<usize>>, text: &str, span: Range<usize>) {
+    let segment = &text[span.clone()];
+    let trimmed = segment.trim_start();
+    let start = span.start + (segment.len() - trimmed.len());
+    let end = start + trimmed.trim_end().len();
+    if start < end {
+        spans.push(start..end);
     }
-}
\ No newline at end of file
+}
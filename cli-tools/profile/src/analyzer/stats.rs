@@ -1,6 +1,8 @@
 use anyhow::Result;
 use crate::models::{BasicStats, CharTypeDistribution};
 
+use super::sentence::SentenceAnalyzer;
+
 pub struct BasicStatsAnalyzer;
 
 impl BasicStatsAnalyzer {
@@ -9,12 +11,8 @@ impl BasicStatsAnalyzer {
         let total_paragraphs = count_paragraphs(text);
         let char_types = analyze_char_types(text);
         
-        // 簡易的な文数カウント（。！？で分割）
-        let total_sentences = text
-            .chars()
-            .filter(|&c| c == '。' || c == '！' || c == '？')
-            .count()
-            .max(1); // 最低1文
+        // 文の区切りはタイムラインや語尾の文番号と同じ（「」の中の。！？では切らない）
+        let total_sentences = SentenceAnalyzer::spans(text).len().max(1); // 最低1文
         
         Ok(BasicStats {
             total_chars,
@@ -25,9 +23,10 @@ impl BasicStatsAnalyzer {
     }
 }
 
+/// 小説の原稿は1行が1段落（空行は場面の区切りや行間）
 fn count_paragraphs(text: &str) -> usize {
-    text.split("\n\n")
-        .filter(|p| !p.trim().is_empty())
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
         .count()
         .max(1)
 }
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use crate::models::TimelineSegment;
+use super::sentence::SentenceAnalyzer;
+use super::AnalysisOptions;
+
+pub struct TimelineAnalyzer;
+
+impl TimelineAnalyzer {
+    /// `window` 文ずつの区間を `step` 文ずつずらしながら解析する（`step == window` なら重ならない）
+    pub fn windows(
+        text: &str,
+        window: usize,
+        step: usize,
+        options: &AnalysisOptions,
+    ) -> Result<Vec<TimelineSegment>> {
+        let normalized = super::normalize_text(text);
+        let sentences = SentenceAnalyzer::spans(&normalized);
+        let window = window.max(1);
+        let step = step.max(1);
+
+        let mut segments = Vec::new();
+        let mut start = 0;
+        while start < sentences.len() {
+            let end = (start + window).min(sentences.len());
+            let label = format!("{}-{}", start + 1, end);
+            // 改行を残すため、文をつなぎ直さず元のテキストから切り出す（行頭の（）や――の判定に要る）
+            let body = &normalized[sentences[start].start..sentences[end - 1].end];
+            segments.push(segment(label, start + 1, body, options)?);
+            if end == sentences.len() {
+                break;
+            }
+            start += step;
+        }
+
+        Ok(segments)
+    }
+
+    /// 見出しごとの区間を解析する（本文のない見出しは飛ばす）
+    pub fn sections(
+        sections: &[(String, String)],
+        options: &AnalysisOptions,
+    ) -> Result<Vec<TimelineSegment>> {
+        let mut segments = Vec::new();
+        let mut next_sentence = 1;
+
+        for (heading, body) in sections {
+            if body.trim().is_empty() {
+                continue;
+            }
+            let segment = segment(heading.clone(), next_sentence, body, options)?;
+            next_sentence = segment.last_sentence + 1;
+            segments.push(segment);
+        }
+
+        Ok(segments)
+    }
+}
+
+fn segment(
+    label: String,
+    first_sentence: usize,
+    text: &str,
+    options: &AnalysisOptions,
+) -> Result<TimelineSegment> {
+    let profile = super::analyze(text, options)?;
+    let sentences = profile.rhythm_metrics.sentence_lengths.len();
+
+    Ok(TimelineSegment {
+        label,
+        first_sentence,
+        last_sentence: first_sentence + sentences.max(1) - 1,
+        chars: profile.basic_stats.total_chars,
+        dialogue_ratio: profile.dialogue_stats.dialogue_ratio,
+        avg_sentence_length: profile.rhythm_metrics.avg_sentence_length,
+        hiragana_ratio: profile.basic_stats.char_types.hiragana_ratio(),
+        tempo_pattern: profile.rhythm_metrics.tempo_pattern,
+    })
+}
@@ -0,0 +1,54 @@
+// 形態素解析（`tokenizer` 機能）
+//
+// 辞書は `--dictionary` で渡す（lindera の辞書ディレクトリ、
+// または `embedded://ipadic` など lindera の embed-* 機能で組み込んだもの）。
+
+use anyhow::{Context, Result};
+use lindera::dictionary::load_dictionary;
+use lindera::mode::Mode;
+use lindera::segmenter::Segmenter;
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+use super::lexical::is_word_char;
+
+#[derive(Clone)]
+pub struct Tokenizer {
+    segmenter: Arc<Segmenter>,
+}
+
+impl fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Tokenizer")
+    }
+}
+
+impl Tokenizer {
+    pub fn load(dictionary: &str) -> Result<Self> {
+        let dictionary = load_dictionary(dictionary)
+            .map_err(|e| anyhow::anyhow!("{}", e))
+            .with_context(|| format!("Failed to load dictionary: {}", dictionary))?;
+        Ok(Self {
+            segmenter: Arc::new(Segmenter::new(Mode::Normal, dictionary, None)),
+        })
+    }
+
+    /// 文ごとに分かち書きした語（記号だけの語は除く）
+    pub fn words(&self, sentences: &[String]) -> Result<Vec<String>> {
+        let mut words = Vec::new();
+        for sentence in sentences {
+            let tokens = self
+                .segmenter
+                .segment(Cow::Borrowed(sentence.as_str()))
+                .map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))?;
+            words.extend(
+                tokens
+                    .iter()
+                    .map(|token| token.surface.as_ref())
+                    .filter(|surface| surface.chars().any(is_word_char))
+                    .map(String::from),
+            );
+        }
+        Ok(words)
+    }
+}
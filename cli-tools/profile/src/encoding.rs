@@ -0,0 +1,252 @@
+// 文字コードの判定と変換
+// 青空文庫などの参照テキストは Shift_JIS や EUC-JP で配布されていることが多い
+
+use anyhow::Result;
+use encoding_rs::{DecoderResult, Encoding, EUC_JP, ISO_2022_JP, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8};
+
+/// 不正なバイト列の位置は先頭からこの数だけ表示する
+const MAX_REPORTED_OFFSETS: usize = 10;
+
+/// 変換結果。不正なバイト列は U+FFFD に置き換え、その位置（バイト単位）を記録する
+pub struct Decoded {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    pub invalid_offsets: Vec<usize>,
+}
+
+/// `--encoding` の値から文字コードを決める（`auto` は `None`）
+pub fn parse_label(label: &str) -> Result<Option<&'static Encoding>> {
+    let normalized = label.trim().to_lowercase().replace('_', "-");
+    let encoding = match normalized.as_str() {
+        "auto" => return Ok(None),
+        "utf-8" | "utf8" => UTF_8,
+        "shift-jis" | "sjis" | "cp932" | "windows-31j" => SHIFT_JIS,
+        "euc-jp" | "eucjp" => EUC_JP,
+        "iso-2022-jp" | "jis" => ISO_2022_JP,
+        // BOM がなければリトルエンディアンとみなす（Windows のメモ帳などの出力）
+        "utf-16" | "utf16" | "utf-16le" => UTF_16LE,
+        "utf-16be" => UTF_16BE,
+        _ => Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown encoding: {} (expected auto, utf-8, shift_jis, euc-jp, iso-2022-jp, utf-16, utf-16be)",
+                label
+            )
+        })?,
+    };
+    Ok(Some(encoding))
+}
+
+/// `encoding` が `None` なら BOM と内容から判定して変換する
+///
+/// BOM があればそれを優先し、指定された文字コードより BOM を信じる。
+pub fn decode(bytes: &[u8], encoding: Option<&'static Encoding>) -> Decoded {
+    let (encoding, bom_len) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_len)) => (encoding, bom_len),
+        None => (encoding.unwrap_or_else(|| detect(bytes)), 0),
+    };
+    let (text, invalid_offsets) = decode_reporting(&bytes[bom_len..], encoding);
+
+    Decoded {
+        text,
+        encoding,
+        invalid_offsets: invalid_offsets.into_iter().map(|offset| offset + bom_len).collect(),
+    }
+}
+
+impl Decoded {
+    /// 不正なバイト列があれば標準エラーに位置を出す（解析自体は続ける）
+    pub fn warn_invalid(&self, source: &str) {
+        if self.invalid_offsets.is_empty() {
+            return;
+        }
+
+        let shown: Vec<String> = self
+            .invalid_offsets
+            .iter()
+            .take(MAX_REPORTED_OFFSETS)
+            .map(|offset| offset.to_string())
+            .collect();
+        let more = if self.invalid_offsets.len() > MAX_REPORTED_OFFSETS { ", ..." } else { "" };
+        eprintln!(
+            "Warning: {}: {} invalid byte sequence(s) for {} replaced with U+FFFD at byte offset {}{}",
+            source,
+            self.invalid_offsets.len(),
+            self.encoding.name(),
+            shown.join(", "),
+            more
+        );
+    }
+}
+
+/// 候補の点数がこの差に収まるなら UTF-8 を選ぶ（不正なバイト列1つ分の減点）
+const NEAR_TIE: i64 = 2;
+
+/// BOM のないテキストの文字コードを推定する
+///
+/// ESC シーケンスだけで書かれていれば ISO-2022-JP、NUL の偏りがあれば UTF-16、
+/// UTF-8 として正しければ UTF-8。それ以外は UTF-8・Shift_JIS・EUC-JP・UTF-16 で試しに変換し、
+/// かなが多く不正なバイト列が少ないものを選ぶ（別の文字コードで読むとかなはほとんど現れない）。
+/// 不正なバイトが少し混じっただけの UTF-8 を取り違えないよう、僅差なら UTF-8 を選ぶ。
+fn detect(bytes: &[u8]) -> &'static Encoding {
+    // ISO-2022-JP は7ビットなので UTF-8 としても正しく読めてしまう。先に見る
+    if bytes.is_ascii() && bytes.windows(3).any(|w| w == b"\x1b$B" || w == b"\x1b$@") {
+        return ISO_2022_JP;
+    }
+    // ASCII だけの UTF-16 は NUL を含んだ UTF-8 としても読めてしまうので、これも先に見る
+    if let Some(utf16) = detect_utf16(bytes) {
+        return utf16;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+
+    let has_newline = bytes.contains(&b'\n');
+    let score = |encoding: &'static Encoding| {
+        let (text, invalid) = decode_reporting(bytes, encoding);
+        // 改行のあるテキストを UTF-16 で読んで改行が一つも現れないなら、UTF-16 ではない
+        if has_newline && (encoding == UTF_16LE || encoding == UTF_16BE) && !text.contains('\n') {
+            return None;
+        }
+        let kana = text.chars().filter(|c| matches!(c, '\u{3040}'..='\u{30FF}')).count();
+        Some(kana as i64 - invalid.len() as i64 * 2)
+    };
+
+    // 同点なら先に挙げたものを選ぶ
+    let mut best = UTF_8;
+    let mut best_score = score(UTF_8).unwrap_or(i64::MIN);
+    for candidate in [SHIFT_JIS, EUC_JP, UTF_16LE, UTF_16BE] {
+        let Some(candidate_score) = score(candidate) else {
+            continue;
+        };
+        let margin = if best == UTF_8 { NEAR_TIE } else { 0 };
+        if candidate_score > best_score.saturating_add(margin) {
+            best = candidate;
+            best_score = candidate_score;
+        }
+    }
+    best
+}
+
+/// ASCII の多い UTF-16 は上位バイトが 0 になるので、偶数・奇数位置の NUL の数で見分ける
+fn detect_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 2 {
+        return None;
+    }
+    let even = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    let half = bytes.len() / 2;
+
+    if odd * 4 > half && even * 4 < odd {
+        Some(UTF_16LE)
+    } else if even * 4 > half && odd * 4 < even {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// 不正なバイト列を U+FFFD に置き換えながら変換し、その位置を返す
+fn decode_reporting(bytes: &[u8], encoding: &'static Encoding) -> (String, Vec<usize>) {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::with_capacity(
+        decoder
+            .max_utf8_buffer_length_without_replacement(bytes.len())
+            .unwrap_or(bytes.len() * 3),
+    );
+    let mut invalid_offsets = Vec::new();
+    let mut consumed = 0;
+
+    loop {
+        let (result, read) =
+            decoder.decode_to_string_without_replacement(&bytes[consumed..], &mut text, true);
+        consumed += read;
+
+        match result {
+            DecoderResult::InputEmpty => break,
+            DecoderResult::OutputFull => {
+                text.reserve(bytes.len() - consumed + 16);
+            }
+            DecoderResult::Malformed(bad, extra) => {
+                invalid_offsets.push(consumed - bad as usize - extra as usize);
+                text.push('\u{FFFD}');
+            }
+        }
+    }
+
+    (text, invalid_offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "吾輩は猫である。名前はまだ無い。\nどこで生れたかとんと見当がつかぬ。\n何でも薄暗いじめじめした所でニャーニャー泣いていた事だけは記憶している。\n";
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() })
+            .collect()
+    }
+
+    fn assert_decodes(bytes: &[u8], expected: &'static Encoding) {
+        let decoded = decode(bytes, None);
+        assert_eq!(decoded.encoding, expected);
+        assert_eq!(decoded.text, SAMPLE);
+        assert!(decoded.invalid_offsets.is_empty());
+    }
+
+    #[test]
+    fn detects_legacy_japanese_encodings() {
+        for encoding in [SHIFT_JIS, EUC_JP, ISO_2022_JP] {
+            let (bytes, _, unmappable) = encoding.encode(SAMPLE);
+            assert!(!unmappable);
+            assert_decodes(&bytes, encoding);
+        }
+    }
+
+    #[test]
+    fn detects_utf16_with_bom() {
+        let mut le = vec![0xFF, 0xFE];
+        le.extend(utf16(SAMPLE, false));
+        assert_decodes(&le, UTF_16LE);
+
+        let mut be = vec![0xFE, 0xFF];
+        be.extend(utf16(SAMPLE, true));
+        assert_decodes(&be, UTF_16BE);
+    }
+
+    #[test]
+    fn detects_utf16_without_bom() {
+        assert_decodes(&utf16(SAMPLE, false), UTF_16LE);
+        assert_decodes(&utf16(SAMPLE, true), UTF_16BE);
+    }
+
+    #[test]
+    fn keeps_utf8_with_a_stray_byte() {
+        let split = SAMPLE.find('\n').unwrap() + 1;
+        let mut bytes = SAMPLE.as_bytes()[..split].to_vec();
+        bytes.push(0xFF);
+        bytes.extend_from_slice(&SAMPLE.as_bytes()[split..]);
+
+        let decoded = decode(&bytes, None);
+        assert_eq!(decoded.encoding, UTF_8);
+        assert_eq!(decoded.invalid_offsets, vec![split]);
+        assert_eq!(decoded.text, format!("{}\u{FFFD}{}", &SAMPLE[..split], &SAMPLE[split..]));
+    }
+
+    #[test]
+    fn keeps_ascii_with_a_stray_byte() {
+        let bytes = b"Chapter 1\nIt was a dark night.\xFF\nThe end.\n";
+        assert_eq!(decode(bytes, None).encoding, UTF_8);
+    }
+
+    #[test]
+    fn reports_offsets_of_invalid_sequences() {
+        let (mut bytes, _, _) = SHIFT_JIS.encode("あいう");
+        let bytes = bytes.to_mut();
+        bytes.insert(2, 0xFF);
+        let (text, offsets) = decode_reporting(bytes, SHIFT_JIS);
+        assert_eq!(text, "あ\u{FFFD}いう");
+        assert_eq!(offsets, vec![2]);
+    }
+}
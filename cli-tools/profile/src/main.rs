@@ -1,5 +1,5 @@
 use clap::{Parser, ValueEnum};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io::{self, Read};
 use std::fs;
 use anyhow::{Result, Context};
@@ -8,10 +8,14 @@ mod models;
 mod analyzer;
 mod parser;
 mod report;
+mod encoding;
 
 use models::TextProfile;
 use report::Reporter;
 
+/// 括弧の警告はファイルごとにこの数まで表示する
+const MAX_BRACKET_WARNINGS: usize = 20;
+
 #[derive(Parser)]
 #[command(name = "profile")]
 #[command(version = "0.1.0")]
@@ -21,8 +25,12 @@ use report::Reporter;
 ANALYSIS METRICS:
 
 Style Balance:
-  - Dialogue ratio: Percentage of text within quotation marks (「」『』)
-  - Narrative ratio: Remaining text (descriptions, thoughts, actions)
+  - Dialogue ratio: Percentage of text within quotation marks (「」『』“”, and
+    lines starting with ――); nested quotes count as part of the outer line
+  - Thoughts: lines starting with （ are counted separately
+  - Narrative ratio: Remaining text (descriptions, actions)
+  - Speakers: guessed from 「…」と○○は言った, a preceding ○○は sentence or
+    alternating exchanges; unmatched brackets are reported with line numbers
 
 Sentence Rhythm:
   - Length distribution: How sentence lengths vary throughout the text
@@ -48,6 +56,23 @@ Markdown files (.md):
 Plain text files (.txt):
   - Analyzed as-is without modifications
 
+Ruby and annotations (--ruby, default: base):
+  - 漢字《かんじ》, ｜親文字《おやもじ》 and <ruby> tags count the base text only
+  - ［＃…］ annotations are dropped; use --aozora for Aozora Bunko files
+
+Readability score (0-100, higher is easier):
+  - Hiragana ratio, sentence length, kanji beyond elementary school (常用 grade
+    table), paragraph length and vocabulary diversity (MATTR)
+  - 80+ children, 65+ teen, 50+ general, 35+ literary, below 35 advanced
+
+Repetition (--repeat-window, --repeat-min, --stop-words, --crutch-words):
+  - Words used 3+ times within 5 sentences, crutch words (ふと, 思わず, 少し, ...)
+    with their sentence numbers, and the most overused phrases
+
+Character encodings (-e/--encoding, default: auto):
+  - auto detects the BOM, then UTF-8 / Shift_JIS / EUC-JP / ISO-2022-JP / UTF-16
+  - Invalid bytes are replaced and reported with their byte offsets
+
 EXAMPLES:
 
 Basic analysis:
@@ -56,14 +81,25 @@ Basic analysis:
 Compare with reference:
   novel profile my_story.md -- --compare bestseller.txt
 
+Pacing within a chapter:
+  novel profile chapter1.md -- --window 20 --chart
+  novel profile chapter1.md -- --sections
+
+Whole project (per-file table, corpus profile, outlier chapters):
+  novel profile episode/
+  novel profile "episode/**/*.md" -- --group-by dir
+
 NovelEnv sketch analysis:
   novel profile scene_sketch/interaction.md -- --sketch
 
 Detailed output:
   novel profile chapter1.md -- --verbose --format json"#)]
 struct Cli {
-    /// Input file path (reads from stdin if not specified)
-    input: Option<PathBuf>,
+    /// Input files, directories or glob patterns (reads from stdin if not specified)
+    ///
+    /// More than one file, a directory or a glob analyzes them together as a corpus.
+    #[arg(value_name = "INPUT")]
+    inputs: Vec<String>,
     
     /// Compare with another file
     #[arg(short, long, value_name = "FILE")]
@@ -73,14 +109,21 @@ struct Cli {
     #[arg(short, long, value_enum, default_value = "terminal")]
     format: OutputFormat,
     
-    /// Character encoding for file input
-    #[arg(short = 'e', long, default_value = "utf-8")]
+    /// Character encoding for file input: auto, utf-8, shift_jis, euc-jp, iso-2022-jp, utf-16, utf-16be
+    ///
+    /// `auto` detects the encoding from the BOM and the content. Invalid bytes are replaced
+    /// and reported with their byte offsets instead of failing the whole file.
+    #[arg(short = 'e', long, default_value = "auto")]
     encoding: String,
     
     /// Generate chart visualization
     #[arg(long)]
     chart: bool,
     
+    /// Write the charts (rhythm, length distribution, dialogue density) to an SVG file
+    #[arg(long, value_name = "FILE")]
+    svg: Option<PathBuf>,
+    
     /// Verbose output (show detailed statistics)
     #[arg(short, long)]
     verbose: bool,
@@ -89,6 +132,55 @@ struct Cli {
     #[arg(long)]
     sketch: bool,
     
+    /// Treat as Aozora Bunko text format (drop the header notes and the 底本 colophon)
+    #[arg(long)]
+    aozora: bool,
+    
+    /// How to count ruby such as 漢字《かんじ》, ｜親文字《おやもじ》 and <ruby> tags
+    #[arg(long, value_enum, value_name = "MODE", default_value = "base")]
+    ruby: RubyMode,
+    
+    /// Show how metrics evolve over windows of N sentences
+    #[arg(long, value_name = "N", conflicts_with_all = ["sections", "compare"])]
+    window: Option<usize>,
+    
+    /// Advance the window by this many sentences (defaults to the window size, i.e. no overlap)
+    #[arg(long, value_name = "N", requires = "window")]
+    step: Option<usize>,
+    
+    /// Show how metrics evolve per Markdown heading section
+    #[arg(long, conflicts_with = "compare")]
+    sections: bool,
+    
+    /// Aggregate corpus files by group in addition to the whole corpus
+    #[arg(long, value_enum, value_name = "GROUP")]
+    group_by: Option<GroupBy>,
+    
+    /// Flag corpus files whose metrics deviate from the other files by this many standard deviations
+    #[arg(long, value_name = "Z", default_value_t = 2.0)]
+    outlier_threshold: f32,
+    
+    /// Flag a word used this many times within --repeat-window sentences
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    repeat_min: usize,
+    
+    /// Number of consecutive sentences searched for repeated words
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    repeat_window: usize,
+    
+    /// Extra words to ignore in repetition checks (one per line, # starts a comment)
+    #[arg(long, value_name = "FILE")]
+    stop_words: Option<PathBuf>,
+    
+    /// Extra crutch words to count in addition to the built-in list (one per line)
+    #[arg(long, value_name = "FILE")]
+    crutch_words: Option<PathBuf>,
+    
+    /// Count vocabulary in morphemes using this lindera dictionary (a directory or embedded://ipadic)
+    #[cfg(feature = "tokenizer")]
+    #[arg(long, value_name = "DICT")]
+    dictionary: Option<String>,
+    
     /// Download and analyze from URL (auto-detects site type)
     #[arg(long)]
     url: Option<String>,
@@ -108,66 +200,281 @@ enum OutputFormat {
     Csv,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum RubyMode {
+    /// Count the base text and drop readings and ［＃…］ annotations
+    Base,
+    /// Count the readings in place of the base text
+    Reading,
+    /// Leave ruby markup and annotations in the text as-is
+    Keep,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+enum GroupBy {
+    /// Parent directory of each file
+    Dir,
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
     
+    if cli.url.is_none() && is_corpus(&cli.inputs) {
+        return run_corpus(&cli);
+    }
+    let input = cli.inputs.first().map(PathBuf::from);
+    let options = analysis_options(&cli)?;
+    
     // Read input text
     let input_text = if let Some(url) = &cli.url {
         fetch_web_text(url)?
     } else {
-        read_input(&cli.input, &cli.encoding)?
+        read_input(&input, &cli.encoding)?
     };
+    let source = match (&cli.url, &input) {
+        (Some(url), _) => url.clone(),
+        (None, Some(path)) => path.display().to_string(),
+        (None, None) => String::from("stdin"),
+    };
+    warn_brackets(&source, &input_text);
     
     // Preprocess text based on file type and options
-    let processed_text = preprocess_text(&input_text, &cli)?;
+    let processed_text = preprocess_text(&input_text, input.as_deref(), &cli)?;
     
     // Analyze processed text
-    let profile = analyze_text(&processed_text)?;
+    let profile = analyzer::analyze(&processed_text, &options)?;
     
     // Handle comparison if requested
     let comparison = if let Some(compare_path) = &cli.compare {
         let compare_text = read_file(compare_path, &cli.encoding)?;
-        let processed_compare_text = preprocess_text(&compare_text, &cli)?;
-        Some(analyze_text(&processed_compare_text)?)
+        let processed_compare_text = preprocess_text(&compare_text, Some(compare_path), &cli)?;
+        Some(analyzer::analyze(&processed_compare_text, &options)?)
     } else {
         None
     };
     
     // Generate and display report
     let reporter = Reporter::new(cli.format.clone(), cli.verbose, cli.chart);
-    reporter.report(&profile, comparison.as_ref(), cli.input.as_deref())?;
+    if let Some(window) = cli.window {
+        if window == 0 {
+            anyhow::bail!("--window must be at least 1 sentence");
+        }
+        let step = cli.step.unwrap_or(window);
+        let timeline = analyzer::TimelineAnalyzer::windows(&processed_text, window, step, &options)?;
+        reporter.report_timeline(&profile, &timeline, input.as_deref())?;
+    } else if cli.sections {
+        // 区間ごとの値が全体のプロファイルと食い違わないよう、同じ前処理をしてから見出しで区切る
+        let text = if cli.aozora { strip_aozora_frame(&input_text) } else { input_text.clone() };
+        let sections = split_sections(&text, cli.sketch)?;
+        let timeline = analyzer::TimelineAnalyzer::sections(&sections, &options)?;
+        reporter.report_timeline(&profile, &timeline, input.as_deref())?;
+    } else {
+        reporter.report(&profile, comparison.as_ref(), input.as_deref(), cli.compare.as_deref())?;
+    }
+    
+    if let Some(svg_path) = &cli.svg {
+        let mut charts = vec![(chart_label(input.as_deref(), cli.url.as_deref()), &profile)];
+        if let (Some(compare), Some(compare_path)) = (&comparison, &cli.compare) {
+            charts.push((chart_label(Some(compare_path), None), compare));
+        }
+        report::write_svg(svg_path, &charts)?;
+    }
+    
+    Ok(())
+}
+
+/// 複数ファイル・ディレクトリ・glob のいずれかならコーパスとして解析する
+fn is_corpus(inputs: &[String]) -> bool {
+    inputs.len() > 1
+        || inputs
+            .iter()
+            .any(|input| is_glob(input) || Path::new(input).is_dir())
+}
+
+fn is_glob(input: &str) -> bool {
+    input.contains(['*', '?', '['])
+}
+
+/// 引数をファイルの一覧に展開する（ディレクトリは配下の .md / .txt を再帰的に集める）
+fn collect_inputs(inputs: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    
+    for input in inputs {
+        let path = Path::new(input);
+        let mut found: Vec<PathBuf> = if path.is_dir() {
+            let pattern = path.join("**").join("*");
+            glob::glob(&pattern.to_string_lossy())
+                .with_context(|| format!("Invalid directory path: {}", input))?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.is_file() && is_text_file(entry))
+                .collect()
+        } else if is_glob(input) {
+            glob::glob(input)
+                .with_context(|| format!("Invalid glob pattern: {}", input))?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.is_file())
+                .collect()
+        } else {
+            vec![path.to_path_buf()]
+        };
+        
+        if found.is_empty() {
+            anyhow::bail!("No text files found for: {}", input);
+        }
+        found.sort();
+        for file in found {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    
+    Ok(files)
+}
+
+fn is_text_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| matches!(ext.to_lowercase().as_str(), "md" | "txt"))
+        .unwrap_or(false)
+}
+
+fn run_corpus(cli: &Cli) -> Result<()> {
+    if cli.compare.is_some() {
+        anyhow::bail!("--compare cannot be used with multiple inputs; outliers are reported against the corpus itself");
+    }
+    if cli.window.is_some() || cli.sections {
+        anyhow::bail!("--window and --sections analyze a single input");
+    }
+    
+    let mut documents = Vec::new();
+    for path in collect_inputs(&cli.inputs)? {
+        let text = read_file(&path, &cli.encoding)?;
+        warn_brackets(&path.display().to_string(), &text);
+        let group = cli.group_by.as_ref().map(|group_by| match group_by {
+            GroupBy::Dir => path
+                .parent()
+                .map(|dir| dir.display().to_string())
+                .filter(|dir| !dir.is_empty())
+                .unwrap_or_else(|| String::from(".")),
+        });
+        documents.push(analyzer::CorpusDocument {
+            path: path.display().to_string(),
+            group,
+            text: preprocess_text(&text, Some(&path), cli)?,
+        });
+    }
+    
+    let corpus = analyzer::CorpusAnalyzer::analyze(&documents, cli.outlier_threshold, &analysis_options(cli)?)?;
+    
+    let reporter = Reporter::new(cli.format.clone(), cli.verbose, cli.chart);
+    reporter.report_corpus(&corpus)?;
+    
+    if let Some(svg_path) = &cli.svg {
+        let mut charts: Vec<(String, &TextProfile)> = corpus
+            .files
+            .iter()
+            .map(|file| (chart_label(Some(Path::new(&file.path)), None), &file.profile))
+            .collect();
+        charts.push((String::from("Corpus"), &corpus.aggregate));
+        report::write_svg(svg_path, &charts)?;
+    }
     
     Ok(())
 }
 
+/// SVG の見出しに使う名前
+fn chart_label(path: Option<&Path>, url: Option<&str>) -> String {
+    match (path, url) {
+        (_, Some(url)) => url.to_string(),
+        (Some(path), None) => path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string()),
+        (None, None) => String::from("stdin"),
+    }
+}
+
 fn read_input(path: &Option<PathBuf>, encoding: &str) -> Result<String> {
     match path {
         Some(p) => read_file(p, encoding),
         None => {
-            let mut buffer = String::new();
-            io::stdin().read_to_string(&mut buffer)
+            let mut buffer = Vec::new();
+            io::stdin().read_to_end(&mut buffer)
                 .context("Failed to read from stdin")?;
-            Ok(buffer)
+            decode_text(&buffer, encoding, "stdin")
         }
     }
 }
 
-fn read_file(path: &PathBuf, encoding: &str) -> Result<String> {
-    // For now, we only support UTF-8
-    // Future: Add encoding_rs for other encodings
-    if encoding != "utf-8" {
-        eprintln!("Warning: Only UTF-8 encoding is currently supported. Ignoring encoding parameter.");
+fn read_file(path: &Path, encoding: &str) -> Result<String> {
+    let bytes = fs::read(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    decode_text(&bytes, encoding, &path.display().to_string())
+}
+
+/// 対応の取れない括弧を元のファイルの行番号で標準エラーに出す
+fn warn_brackets(source: &str, text: &str) {
+    let warnings = parser::brackets::check_brackets(text);
+    for warning in warnings.iter().take(MAX_BRACKET_WARNINGS) {
+        eprintln!("Warning: {}:{}: {}", source, warning.line, warning.message);
+    }
+    if warnings.len() > MAX_BRACKET_WARNINGS {
+        eprintln!(
+            "Warning: {}: {} more bracket warning(s)",
+            source,
+            warnings.len() - MAX_BRACKET_WARNINGS
+        );
+    }
+}
+
+fn decode_text(bytes: &[u8], encoding: &str, source: &str) -> Result<String> {
+    let decoded = encoding::decode(bytes, encoding::parse_label(encoding)?);
+    decoded.warn_invalid(source);
+    Ok(decoded.text)
+}
+
+fn analysis_options(cli: &Cli) -> Result<analyzer::AnalysisOptions> {
+    let mut repetition = analyzer::RepetitionOptions {
+        window: cli.repeat_window,
+        min_count: cli.repeat_min,
+        ..Default::default()
+    };
+    if let Some(path) = &cli.stop_words {
+        repetition.stop_words.extend(read_word_list(path, &cli.encoding)?);
+    }
+    if let Some(path) = &cli.crutch_words {
+        repetition.crutch_words.extend(read_word_list(path, &cli.encoding)?);
     }
     
-    fs::read_to_string(path)
-        .with_context(|| format!("Failed to read file: {}", path.display()))
+    Ok(analyzer::AnalysisOptions {
+        ruby: cli.ruby,
+        repetition,
+        #[cfg(feature = "tokenizer")]
+        tokenizer: cli
+            .dictionary
+            .as_deref()
+            .map(analyzer::tokenizer::Tokenizer::load)
+            .transpose()?,
+    })
+}
+
+/// 1行に1語の一覧を読む（空行と # で始まる行は飛ばす）
+fn read_word_list(path: &Path, encoding: &str) -> Result<Vec<String>> {
+    let text = read_file(path, encoding)?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
 }
 
-fn preprocess_text(text: &str, cli: &Cli) -> Result<String> {
+fn preprocess_text(text: &str, path: Option<&Path>, cli: &Cli) -> Result<String> {
     let mut processed = text.to_string();
     
     // Detect file type from extension or content
-    let is_markdown = if let Some(path) = &cli.input {
+    let is_markdown = if let Some(path) = path {
         path.extension()
             .and_then(|ext| ext.to_str())
             .map(|ext| ext.to_lowercase() == "md")
@@ -181,9 +488,68 @@ fn preprocess_text(text: &str, cli: &Cli) -> Result<String> {
         processed = preprocess_markdown(&processed, cli.sketch)?;
     }
     
+    if cli.aozora {
+        processed = strip_aozora_frame(&processed);
+    }
+    
     Ok(processed)
 }
 
+/// 青空文庫の書式の前後を取り除く
+///
+/// 冒頭の表題・著者名と「【テキスト中に現れる記号について】」の区切り線で囲まれた部分、
+/// 末尾の「底本：」以降の奥付。本文中の注記とルビは解析時に取り除く。
+fn strip_aozora_frame(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let is_rule = |line: &str| {
+        let trimmed = line.trim();
+        trimmed.chars().count() >= 10 && trimmed.chars().all(|c| c == '-')
+    };
+    
+    let mut rules = lines.iter().enumerate().filter(|(_, line)| is_rule(line)).map(|(i, _)| i);
+    let start = match (rules.next(), rules.next()) {
+        (Some(_), Some(second)) => second + 1,
+        _ => 0,
+    };
+    let end = lines
+        .iter()
+        .position(|line| line.starts_with("底本："))
+        .filter(|&end| end >= start)
+        .unwrap_or(lines.len());
+    
+    lines[start..end].join("\n")
+}
+
+/// Markdown の見出しで本文を区切る（最初の見出しより前の本文は `(preamble)`）
+///
+/// `is_sketch` ならスケッチのメタデータを文書全体から取り除いてから区切る。
+fn split_sections(text: &str, is_sketch: bool) -> Result<Vec<(String, String)>> {
+    let mut sections = vec![(String::from("(preamble)"), Vec::new())];
+    let mut lines: Vec<&str> = text.lines().collect();
+    if is_sketch {
+        lines = remove_sketch_metadata(lines)?;
+    }
+    
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('#') {
+            let heading = trimmed.trim_start_matches('#').trim();
+            sections.push((heading.to_string(), Vec::new()));
+        } else if let Some((_, lines)) = sections.last_mut() {
+            lines.push(line);
+        }
+    }
+    
+    if sections.len() == 1 {
+        anyhow::bail!("No Markdown headings found; use --window N to split by sentences instead");
+    }
+    
+    sections
+        .into_iter()
+        .map(|(heading, lines)| Ok((heading, preprocess_markdown(&lines.join("\n"), false)?)))
+        .collect()
+}
+
 fn preprocess_markdown(text: &str, is_sketch: bool) -> Result<String> {
     let mut lines: Vec<&str> = text.lines().collect();
     
@@ -209,7 +575,6 @@ fn preprocess_markdown(text: &str, is_sketch: bool) -> Result<String> {
 
 fn remove_sketch_metadata(lines: Vec<&str>) -> Result<Vec<&str>> {
     let mut result = Vec::new();
-    let mut in_metadata = false;
     let mut skip_until_content = true;
     
     for line in lines {
@@ -250,7 +615,6 @@ fn remove_sketch_metadata(lines: Vec<&str>) -> Result<Vec<&str>> {
 
 fn fetch_web_text(url: &str) -> Result<String> {
     use reqwest::blocking::Client;
-    use scraper::{Html, Selector};
     
     // Create HTTP client
     let client = Client::builder()
@@ -338,14 +702,6 @@ fn extract_text_by_selector(html_content: &str, selector: &str) -> Result<String
     Ok(novel_text)
 }
 
-fn extract_narou_text(html_content: &str) -> Result<String> {
-    extract_text_by_selector(html_content, "div.js-novel-text.p-novel__text p")
-}
-
-fn extract_kakuyomu_text(html_content: &str) -> Result<String> {
-    extract_text_by_selector(html_content, "div.widget-episodeBody.js-episode-body p")
-}
-
 fn strip_html_tags(text: &str) -> String {
     // Simple HTML tag removal - for more complex cases, consider using html2text crate
     let mut result = String::new();
@@ -362,8 +718,3 @@ fn strip_html_tags(text: &str) -> String {
     
     result
 }
-
-fn analyze_text(text: &str) -> Result<TextProfile> {
-    // This will be implemented in analyzer module
-    analyzer::analyze(text)
-}
@@ -1,4 +1,5 @@
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextProfile {
@@ -6,6 +7,16 @@ pub struct TextProfile {
     pub dialogue_stats: DialogueStats,
     pub rhythm_metrics: RhythmMetrics,
     pub readability: ReadabilityScore,
+    #[serde(default)]
+    pub ruby_stats: RubyStats,
+    #[serde(default)]
+    pub ending_stats: EndingStats,
+    #[serde(default)]
+    pub repetition_stats: RepetitionStats,
+    #[serde(default)]
+    pub lexical_diversity: LexicalDiversity,
+    #[serde(default)]
+    pub kanji_stats: KanjiStats,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +56,31 @@ pub struct DialogueStats {
     pub narrative_chars: usize,
     pub dialogue_ratio: f32,
     pub dialogue_count: usize,
+    /// 会話文の位置（文字単位の start..end）。密度チャート用で出力には含めない
+    #[serde(default, skip_serializing)]
+    pub dialogue_spans: Vec<(usize, usize)>,
+    /// 行頭の（）で書かれた心の声。会話にも地の文にも含めない
+    #[serde(default)]
+    pub thought_chars: usize,
+    #[serde(default)]
+    pub thought_count: usize,
+    /// 話者ごとの台詞。推定できた話者だけ、台詞の多い順
+    #[serde(default)]
+    pub speakers: Vec<SpeakerStats>,
+    /// 話者を推定できなかった台詞の数
+    #[serde(default)]
+    pub unattributed_lines: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeakerStats {
+    pub name: String,
+    pub lines: usize,
+    /// 括弧を除いた台詞の文字数
+    pub chars: usize,
+    pub avg_utterance_length: f32,
+    /// 会話文全体に占める割合（文字数）
+    pub share: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +91,142 @@ pub struct RhythmMetrics {
     pub tempo_pattern: TempoPattern,
 }
 
+/// 文末（語尾）の分布と単調さ
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndingStats {
+    pub counts: BTreeMap<EndingType, usize>,
+    /// 同じ語尾が続いた最長の箇所（会話文だけの文は飛ばして数える）
+    pub longest_run: Option<EndingRun>,
+    /// 同じ語尾が `MIN_REPORTED_RUN` 文以上続いた箇所
+    pub runs: Vec<EndingRun>,
+    /// 地の文の文体（だ・である調／です・ます調）。どちらも無ければ None
+    pub style: Option<ProseStyle>,
+    /// 主な文体と異なる文体で終わる地の文の番号（1始まり）
+    pub mixed_style_sentences: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndingType {
+    /// 〜た・〜だ（過去）
+    Ta,
+    /// 〜る・〜う・〜い（現在形）
+    Present,
+    /// 〜だ・〜である
+    Da,
+    /// 〜です・〜ます
+    Polite,
+    /// 体言止め
+    Taigen,
+    Question,
+    Exclamation,
+    /// 「」だけの文
+    Dialogue,
+    /// 終助詞や「……」で終わる文など
+    Other,
+}
+
+impl EndingType {
+    pub const ALL: [EndingType; 9] = [
+        EndingType::Ta,
+        EndingType::Present,
+        EndingType::Da,
+        EndingType::Polite,
+        EndingType::Taigen,
+        EndingType::Question,
+        EndingType::Exclamation,
+        EndingType::Dialogue,
+        EndingType::Other,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EndingType::Ta => "た",
+            EndingType::Present => "る/う",
+            EndingType::Da => "だ/である",
+            EndingType::Polite => "です/ます",
+            EndingType::Taigen => "体言止め",
+            EndingType::Question => "？",
+            EndingType::Exclamation => "！",
+            EndingType::Dialogue => "「」",
+            EndingType::Other => "その他",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndingRun {
+    pub ending: EndingType,
+    pub length: usize,
+    pub first_sentence: usize,
+    pub last_sentence: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProseStyle {
+    /// だ・である調
+    Plain,
+    /// です・ます調
+    Polite,
+}
+
+impl ProseStyle {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProseStyle::Plain => "だ・である調",
+            ProseStyle::Polite => "です・ます調",
+        }
+    }
+}
+
+/// 語句の繰り返し。位置はすべて文番号（1始まり）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepetitionStats {
+    /// 口癖になりやすい語（ふと・思わず・少し…）の出現。多い順
+    pub crutch_words: Vec<PhraseOccurrences>,
+    /// 数文のうちに何度も使われた語句
+    pub near_repeats: Vec<NearRepeat>,
+    /// テキスト全体で多用されている語句。多い順
+    pub overused: Vec<PhraseCount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhraseOccurrences {
+    pub phrase: String,
+    pub count: usize,
+    pub sentences: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NearRepeat {
+    pub phrase: String,
+    pub count: usize,
+    pub first_sentence: usize,
+    pub last_sentence: usize,
+    pub sentences: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhraseCount {
+    pub phrase: String,
+    pub count: usize,
+    pub per_10k_chars: f32,
+}
+
+/// ルビ（振り仮名）の使用量。文字数などの統計はルビを除いたテキストで数える
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RubyStats {
+    pub ruby_count: usize,
+    /// ルビが振られた親文字の数
+    pub base_chars: usize,
+    pub reading_chars: usize,
+    /// ルビを除いた本文 1,000 字あたりのルビの数
+    pub ruby_per_1000_chars: f32,
+    /// 取り除いた ［＃…］ 注記の数
+    pub annotations: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TempoPattern {
     Steady,      // 一定のリズム
@@ -68,14 +240,227 @@ pub enum TempoPattern {
 pub struct ReadabilityScore {
     pub score: f32,  // 0-100
     pub factors: ReadabilityFactors,
+    #[serde(default)]
+    pub audience: TargetAudience,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReadabilityFactors {
     pub hiragana_ratio: f32,
-    pub sentence_complexity: f32,
-    pub punctuation_density: f32,
-    pub paragraph_structure: f32,
+    pub sentence_complexity: f32, // 平均文長
+    pub punctuation_density: f32, // 100字あたりの文数
+    pub paragraph_structure: f32, // 1段落（1行）あたりの平均文字数
+    #[serde(default)]
+    pub difficult_kanji_ratio: f32, // 漢字のうち小学校で習わないものの割合
+    #[serde(default)]
+    pub lexical_diversity: f32, // MATTR
+}
+
+/// 読みやすさのスコアから見た想定読者
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetAudience {
+    Children,  // 80以上
+    Teen,      // 65以上
+    #[default]
+    General,   // 50以上
+    Literary,  // 35以上
+    Advanced,  // 35未満
+}
+
+impl TargetAudience {
+    pub fn from_score(score: f32) -> Self {
+        match score {
+            s if s >= 80.0 => TargetAudience::Children,
+            s if s >= 65.0 => TargetAudience::Teen,
+            s if s >= 50.0 => TargetAudience::General,
+            s if s >= 35.0 => TargetAudience::Literary,
+            _ => TargetAudience::Advanced,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TargetAudience::Children => "小学生向け",
+            TargetAudience::Teen => "中高生向け",
+            TargetAudience::General => "一般向け",
+            TargetAudience::Literary => "文芸・硬め",
+            TargetAudience::Advanced => "専門的・古典的",
+        }
+    }
+}
+
+/// 語彙の豊かさ。`unit` は数えた単位（既定は文字の2-gram、`tokenizer` 機能では形態素）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LexicalDiversity {
+    pub unit: LexicalUnit,
+    /// 延べ数
+    pub tokens: usize,
+    /// 異なり数
+    pub types: usize,
+    pub type_token_ratio: f32,
+    /// 移動平均 TTR。一定の窓ごとの TTR の平均で、TTR と違ってテキストの長さに左右されにくい
+    pub mattr: f32,
+    /// 1回しか現れない語の、異なり数に占める割合
+    pub hapax_ratio: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LexicalUnit {
+    #[default]
+    CharBigram,
+    Token,
+}
+
+impl LexicalUnit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LexicalUnit::CharBigram => "char bigrams",
+            LexicalUnit::Token => "tokens",
+        }
+    }
+}
+
+/// 漢字の難しさ。学年別漢字配当表と常用漢字表で分ける
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KanjiStats {
+    /// 漢字の延べ数
+    pub total: usize,
+    /// 小学校の学年ごとの延べ数（`by_grade[0]` が1年）
+    pub by_grade: [usize; 6],
+    /// 小学校で習わない常用漢字
+    pub secondary: usize,
+    /// 常用漢字表にない漢字
+    pub hyougai: usize,
+    /// 表外字の内訳。多い順
+    pub hyougai_kanji: Vec<KanjiCount>,
+}
+
+impl KanjiStats {
+    pub fn elementary(&self) -> usize {
+        self.by_grade.iter().sum()
+    }
+
+    /// 漢字の延べ数に対する割合
+    pub fn ratio(&self, count: usize) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            count as f32 / self.total as f32
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KanjiCount {
+    pub kanji: String,
+    pub count: usize,
+}
+
+/// 章の中の一区間（`--window` の N 文ごと、または `--sections` の見出しごと）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineSegment {
+    /// 見出し、または文番号の範囲（`1-20`）
+    pub label: String,
+    /// 区間に含まれる文の番号（1始まり、両端を含む）
+    pub first_sentence: usize,
+    pub last_sentence: usize,
+    pub chars: usize,
+    pub dialogue_ratio: f32,
+    pub avg_sentence_length: f32,
+    pub hiragana_ratio: f32,
+    pub tempo_pattern: TempoPattern,
+}
+
+/// 複数ファイルをまとめて解析した結果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorpusProfile {
+    pub files: Vec<FileProfile>,
+    /// `--group-by` 指定時のグループごとの集計（ファイルの登場順）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupProfile>,
+    /// 全ファイルを連結したテキストのプロファイル
+    pub aggregate: TextProfile,
+    pub outliers: Vec<Outlier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileProfile {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub profile: TextProfile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupProfile {
+    pub name: String,
+    pub files: usize,
+    pub profile: TextProfile,
+}
+
+/// プロジェクト内の他のファイルから大きく外れた指標
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outlier {
+    pub path: String,
+    pub metric: CorpusMetric,
+    pub value: f32,
+    /// 他のファイルの平均
+    pub baseline: f32,
+    pub z_score: f32,
+}
+
+/// 外れ値の判定に使う指標
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorpusMetric {
+    DialogueRatio,
+    AvgSentenceLength,
+    VariationCoefficient,
+    HiraganaRatio,
+    Readability,
+}
+
+impl CorpusMetric {
+    pub const ALL: [CorpusMetric; 5] = [
+        CorpusMetric::DialogueRatio,
+        CorpusMetric::AvgSentenceLength,
+        CorpusMetric::VariationCoefficient,
+        CorpusMetric::HiraganaRatio,
+        CorpusMetric::Readability,
+    ];
+
+    pub fn value(&self, profile: &TextProfile) -> f32 {
+        match self {
+            CorpusMetric::DialogueRatio => profile.dialogue_stats.dialogue_ratio,
+            CorpusMetric::AvgSentenceLength => profile.rhythm_metrics.avg_sentence_length,
+            CorpusMetric::VariationCoefficient => profile.rhythm_metrics.variation_coefficient,
+            CorpusMetric::HiraganaRatio => profile.basic_stats.char_types.hiragana_ratio(),
+            CorpusMetric::Readability => profile.readability.score,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CorpusMetric::DialogueRatio => "Dialogue %",
+            CorpusMetric::AvgSentenceLength => "Avg Sentence",
+            CorpusMetric::VariationCoefficient => "Variation",
+            CorpusMetric::HiraganaRatio => "Hiragana %",
+            CorpusMetric::Readability => "Readability",
+        }
+    }
+
+    /// 端末表示用（割合はパーセントにする）
+    pub fn format(&self, value: f32) -> String {
+        match self {
+            CorpusMetric::DialogueRatio | CorpusMetric::HiraganaRatio => {
+                format!("{:.1}%", value * 100.0)
+            }
+            CorpusMetric::AvgSentenceLength | CorpusMetric::Readability => format!("{:.1}", value),
+            CorpusMetric::VariationCoefficient => format!("{:.2}", value),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -89,6 +474,6 @@ pub struct TextSegment {
 #[derive(Debug, Clone, PartialEq)]
 pub enum SegmentType {
     Dialogue,
+    Thought,   // 行頭の（）
     Narrative,
-    Unknown,
 }
\ No newline at end of file
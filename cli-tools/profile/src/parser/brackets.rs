@@ -1,147 +1,351 @@
+// 会話文の括弧
+//
+// 対応する記法:
+//   「…」『…』“…”   会話（入れ子も可：「…『…』…」）
+//   （…）           行頭にあるものは心の声。行の途中の（）は補足として地の文に含める
+//   ――…            行頭のダッシュは行末までを会話として扱う
+//
+// 閉じ括弧がないときは、開いた行の終わりで閉じたものとみなして警告する。
+
 use anyhow::Result;
-use nom::{
-    IResult,
-    branch::alt,
-    bytes::complete::{tag, take_until, take_while1},
-    character::complete::anychar,
-    combinator::{map, recognize},
-    multi::many0,
-    sequence::delimited,
-};
-use crate::models::{TextSegment, SegmentType};
-
-pub fn parse_dialogues(text: &str) -> Result<Vec<TextSegment>> {
-    let mut segments = Vec::new();
-    let mut current_pos = 0;
+use crate::models::{SegmentType, TextSegment};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum QuoteStyle {
+    Kagi,       // 「」
+    DoubleKagi, // 『』
+    Curly,      // “”
+    Paren,      // （）
+    Dash,       // ――
+}
+
+impl QuoteStyle {
+    fn from_open(ch: char) -> Option<Self> {
+        match ch {
+            '「' => Some(QuoteStyle::Kagi),
+            '『' => Some(QuoteStyle::DoubleKagi),
+            '“' => Some(QuoteStyle::Curly),
+            '（' => Some(QuoteStyle::Paren),
+            _ => None,
+        }
+    }
+
+    fn close(&self) -> Option<char> {
+        match self {
+            QuoteStyle::Kagi => Some('」'),
+            QuoteStyle::DoubleKagi => Some('』'),
+            QuoteStyle::Curly => Some('”'),
+            QuoteStyle::Paren => Some('）'),
+            QuoteStyle::Dash => None,
+        }
+    }
+
+    fn segment_type(&self) -> SegmentType {
+        match self {
+            QuoteStyle::Paren => SegmentType::Thought,
+            _ => SegmentType::Dialogue,
+        }
+    }
+}
+
+/// 開き括弧に対応する閉じ括弧
+fn closing_for(open: char) -> Option<char> {
+    QuoteStyle::from_open(open).and_then(|style| style.close())
+}
+
+fn is_close(ch: char) -> bool {
+    matches!(ch, '」' | '』' | '”' | '）')
+}
+
+/// 対応の取れない括弧（行番号は1始まり）
+#[derive(Debug, Clone)]
+pub struct BracketWarning {
+    pub line: usize,
+    pub message: String,
+}
+
+pub struct ParsedDialogues {
+    pub segments: Vec<TextSegment>,
+    pub warnings: Vec<BracketWarning>,
+}
+
+/// テキストを会話・心の声・地の文に分ける
+pub fn parse_dialogues(text: &str) -> Result<ParsedDialogues> {
     let chars: Vec<char> = text.chars().collect();
-    
-    while current_pos < chars.len() {
-        // 会話文の開始を探す
-        if let Some(dialogue_start) = find_dialogue_start(&chars[current_pos..]) {
-            // 会話文開始前の地の文を追加
-            if dialogue_start > 0 {
-                let narrative: String = chars[current_pos..current_pos + dialogue_start]
-                    .iter()
-                    .collect();
-                segments.push(TextSegment {
-                    start: current_pos,
-                    end: current_pos + dialogue_start,
-                    content: narrative,
-                    segment_type: SegmentType::Narrative,
-                });
-            }
-            
-            current_pos += dialogue_start;
-            
-            // 会話文を抽出
-            if let Some(dialogue_end) = find_dialogue_end(&chars[current_pos..]) {
-                let dialogue: String = chars[current_pos..current_pos + dialogue_end]
-                    .iter()
-                    .collect();
-                segments.push(TextSegment {
-                    start: current_pos,
-                    end: current_pos + dialogue_end,
-                    content: dialogue,
-                    segment_type: SegmentType::Dialogue,
-                });
-                current_pos += dialogue_end;
-            } else {
-                // 閉じ括弧が見つからない場合は1文字進める
-                current_pos += 1;
-            }
-        } else {
-            // 残りは全て地の文
-            if current_pos < chars.len() {
-                let narrative: String = chars[current_pos..].iter().collect();
-                segments.push(TextSegment {
-                    start: current_pos,
-                    end: chars.len(),
-                    content: narrative,
-                    segment_type: SegmentType::Narrative,
+    let lines = LineIndex::new(&chars);
+    let mut segments = Vec::new();
+    let mut warnings = Vec::new();
+    let mut narrative_start = 0;
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let Some(style) = opening_at(&chars, pos) else {
+            // （）以外の閉じ括弧が地の文に現れたら、対応する開き括弧がない
+            if is_close(chars[pos]) && chars[pos] != '）' {
+                warnings.push(BracketWarning {
+                    line: lines.line_of(pos),
+                    message: format!("unmatched closing {}", chars[pos]),
                 });
             }
-            break;
+            pos += 1;
+            continue;
+        };
+
+        let end = match style {
+            QuoteStyle::Dash => lines.line_end(pos),
+            _ => find_close(&chars, pos, style, &lines, &mut warnings),
+        };
+
+        if narrative_start < pos {
+            segments.push(segment(&chars, narrative_start, pos, SegmentType::Narrative));
         }
+        segments.push(segment(&chars, pos, end, style.segment_type()));
+        pos = end;
+        narrative_start = end;
+    }
+
+    if narrative_start < chars.len() {
+        segments.push(segment(&chars, narrative_start, chars.len(), SegmentType::Narrative));
     }
-    
-    Ok(segments)
+
+    Ok(ParsedDialogues { segments, warnings })
 }
 
-fn find_dialogue_start(chars: &[char]) -> Option<usize> {
-    for (i, &ch) in chars.iter().enumerate() {
-        if ch == '「' || ch == '『' {
-            return Some(i);
-        }
+/// 対応の取れない括弧だけを調べる
+pub fn check_brackets(text: &str) -> Vec<BracketWarning> {
+    parse_dialogues(text).map(|parsed| parsed.warnings).unwrap_or_default()
+}
+
+fn segment(chars: &[char], start: usize, end: usize, segment_type: SegmentType) -> TextSegment {
+    TextSegment {
+        start,
+        end,
+        content: chars[start..end].iter().collect(),
+        segment_type,
     }
-    None
 }
 
-fn find_dialogue_end(chars: &[char]) -> Option<usize> {
-    if chars.is_empty() {
-        return None;
+/// `pos` から会話などが始まるか。（）とダッシュは行頭（字下げの後）に限る
+fn opening_at(chars: &[char], pos: usize) -> Option<QuoteStyle> {
+    let at_line_start = chars[..pos]
+        .iter()
+        .rev()
+        .take_while(|&&ch| ch != '\n')
+        .all(|&ch| ch == '　' || ch == ' ' || ch == '\t');
+
+    match chars[pos] {
+        '―' | '—' if at_line_start && chars.get(pos + 1) == Some(&chars[pos]) => Some(QuoteStyle::Dash),
+        '（' if at_line_start => Some(QuoteStyle::Paren),
+        '（' => None,
+        ch => QuoteStyle::from_open(ch),
     }
-    
-    let start_bracket = chars[0];
-    let end_bracket = match start_bracket {
-        '「' => '」',
-        '『' => '』',
-        _ => return None,
+}
+
+/// `start` の開き括弧に対応する閉じ括弧の次の位置
+///
+/// 入れ子の括弧は積んでおき、順番の違う閉じ括弧は間の括弧が閉じられていないものとして扱う。
+/// 閉じる前に同じ種類の括弧で始まる行が来たとき（段落をまたぐ台詞の書き方）、心の声が行末で閉じていないとき、
+/// テキストの終わりまで閉じられないときは、開いた行の終わりまでとする。
+fn find_close(
+    chars: &[char],
+    start: usize,
+    style: QuoteStyle,
+    lines: &LineIndex,
+    warnings: &mut Vec<BracketWarning>,
+) -> usize {
+    let mut stack: Vec<(char, usize)> = vec![(chars[start], start)];
+    let unclosed = |open: char, at: usize| BracketWarning {
+        line: lines.line_of(at),
+        message: format!("unclosed {} (no matching {})", open, closing_for(open).unwrap_or(' ')),
     };
-    
-    let mut depth = 0;
-    for (i, &ch) in chars.iter().enumerate() {
-        if ch == start_bracket {
-            depth += 1;
-        } else if ch == end_bracket {
-            depth -= 1;
-            if depth == 0 {
-                return Some(i + 1);
+
+    let mut i = start + 1;
+    while i < chars.len() {
+        let ch = chars[i];
+
+        // 心の声は行をまたがない
+        let new_paragraph = |style| opening_at(chars, next_non_indent(chars, i + 1)) == Some(style);
+        if ch == '\n' && (style == QuoteStyle::Paren || new_paragraph(style)) {
+            warnings.push(unclosed(chars[start], start));
+            return lines.line_end(start);
+        }
+
+        if QuoteStyle::from_open(ch).is_some() {
+            stack.push((ch, i));
+        } else if is_close(ch) {
+            match stack.iter().rposition(|&(open, _)| closing_for(open) == Some(ch)) {
+                Some(depth) => {
+                    for &(open, at) in &stack[depth + 1..] {
+                        // 行の途中の（）の閉じ忘れは補足なので気にしない
+                        if open != '（' {
+                            warnings.push(unclosed(open, at));
+                        }
+                    }
+                    stack.truncate(depth);
+                    if stack.is_empty() {
+                        return i + 1;
+                    }
+                }
+                None => warnings.push(BracketWarning {
+                    line: lines.line_of(i),
+                    message: format!("unmatched closing {} inside {}", ch, chars[start]),
+                }),
             }
         }
+        i += 1;
+    }
+
+    warnings.push(unclosed(chars[start], start));
+    lines.line_end(start)
+}
+
+fn next_non_indent(chars: &[char], pos: usize) -> usize {
+    let mut pos = pos;
+    while pos < chars.len() && matches!(chars[pos], '　' | ' ' | '\t') {
+        pos += 1;
     }
-    
-    None
+    pos.min(chars.len().saturating_sub(1))
 }
 
-// nom版のパーサー（将来の拡張用）
-pub fn dialogue_nom(input: &str) -> IResult<&str, &str> {
-    alt((
-        delimited(tag("「"), take_until("」"), tag("」")),
-        delimited(tag("『"), take_until("』"), tag("』")),
-    ))(input)
+/// 文字位置から行番号と行末を引く
+struct LineIndex {
+    /// 各行の先頭の文字位置
+    starts: Vec<usize>,
+    len: usize,
 }
 
-// 入れ子対応版（Phase 2で使用予定）
-pub fn dialogue_nested_nom(input: &str) -> IResult<&str, String> {
-    fn parse_content(input: &str) -> IResult<&str, String> {
-        let mut result = String::new();
-        let mut remaining = input;
-        
-        while !remaining.is_empty() && !remaining.starts_with('」') && !remaining.starts_with('』') {
-            if remaining.starts_with('『') {
-                // 内側の括弧を処理
-                let (rest, inner) = delimited(
-                    tag("『"),
-                    take_until("』"),
-                    tag("』")
-                )(remaining)?;
-                result.push_str("『");
-                result.push_str(inner);
-                result.push_str("』");
-                remaining = rest;
-            } else {
-                // 通常の文字
-                let (rest, ch) = anychar(remaining)?;
-                result.push(ch);
-                remaining = rest;
-            }
-        }
-        
-        Ok((remaining, result))
-    }
-    
-    map(
-        delimited(tag("「"), parse_content, tag("」")),
-        |content| format!("「{}」", content)
-    )(input)
-}
\ No newline at end of file
+impl LineIndex {
+    fn new(chars: &[char]) -> Self {
+        let mut starts = vec![0];
+        starts.extend(
+            chars
+                .iter()
+                .enumerate()
+                .filter(|(_, &ch)| ch == '\n')
+                .map(|(i, _)| i + 1),
+        );
+        Self { starts, len: chars.len() }
+    }
+
+    fn line_of(&self, pos: usize) -> usize {
+        self.starts.partition_point(|&start| start <= pos)
+    }
+
+    /// `pos` を含む行の改行の位置（最終行ならテキストの長さ）
+    fn line_end(&self, pos: usize) -> usize {
+        self.starts
+            .get(self.line_of(pos))
+            .map_or(self.len, |&next| next - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str) -> Vec<(SegmentType, String)> {
+        parse_dialogues(text)
+            .unwrap()
+            .segments
+            .into_iter()
+            .map(|s| (s.segment_type, s.content))
+            .collect()
+    }
+
+    fn warnings(text: &str) -> Vec<(usize, String)> {
+        check_brackets(text).into_iter().map(|w| (w.line, w.message)).collect()
+    }
+
+    #[test]
+    fn nested_quotes_stay_in_one_dialogue() {
+        assert_eq!(
+            kinds("彼は「『早起きは三文の徳』って言うだろ」と笑った。"),
+            vec![
+                (SegmentType::Narrative, "彼は".to_string()),
+                (SegmentType::Dialogue, "「『早起きは三文の徳』って言うだろ」".to_string()),
+                (SegmentType::Narrative, "と笑った。".to_string()),
+            ]
+        );
+        assert!(warnings("「『早起きは三文の徳』って言うだろ」").is_empty());
+    }
+
+    #[test]
+    fn segments_keep_char_offsets() {
+        let parsed = parse_dialogues("朝。「おはよう」").unwrap();
+        let dialogue = &parsed.segments[1];
+        assert_eq!((dialogue.start, dialogue.end), (2, 8));
+    }
+
+    #[test]
+    fn mismatched_close_reports_the_unclosed_inner_bracket() {
+        let text = "一行目。\n「待って『ちょっと」\n三行目。";
+        assert_eq!(
+            kinds(text)[1],
+            (SegmentType::Dialogue, "「待って『ちょっと」".to_string())
+        );
+        assert_eq!(warnings(text), vec![(2, "unclosed 『 (no matching 』)".to_string())]);
+    }
+
+    #[test]
+    fn unclosed_quote_ends_at_its_line() {
+        let text = "「ねえ、聞いてる？\n健太は黙っていた。";
+        assert_eq!(
+            kinds(text),
+            vec![
+                (SegmentType::Dialogue, "「ねえ、聞いてる？".to_string()),
+                (SegmentType::Narrative, "\n健太は黙っていた。".to_string()),
+            ]
+        );
+        assert_eq!(warnings(text), vec![(1, "unclosed 「 (no matching 」)".to_string())]);
+    }
+
+    #[test]
+    fn quote_reopened_on_the_next_paragraph() {
+        let text = "「長い話になるが\n「続きはこうだ」";
+        let parsed = kinds(text);
+        assert_eq!(parsed[0], (SegmentType::Dialogue, "「長い話になるが".to_string()));
+        assert_eq!(parsed[2], (SegmentType::Dialogue, "「続きはこうだ」".to_string()));
+        assert_eq!(warnings(text), vec![(1, "unclosed 「 (no matching 」)".to_string())]);
+    }
+
+    #[test]
+    fn stray_closing_brackets_are_reported_with_their_line() {
+        assert_eq!(
+            warnings("一行目。\n二行目」。\n「三行目』」"),
+            vec![
+                (2, "unmatched closing 」".to_string()),
+                (3, "unmatched closing 』 inside 「".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn line_initial_parentheses_are_thoughts() {
+        assert_eq!(
+            kinds("　（眠い）\n駅前の広場（東口）で待ち合わせた。"),
+            vec![
+                (SegmentType::Narrative, "　".to_string()),
+                (SegmentType::Thought, "（眠い）".to_string()),
+                (SegmentType::Narrative, "\n駅前の広場（東口）で待ち合わせた。".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_thought_does_not_span_lines() {
+        let text = "（眠い\n健太は歩いた。";
+        assert_eq!(kinds(text)[0], (SegmentType::Thought, "（眠い".to_string()));
+        assert_eq!(warnings(text), vec![(1, "unclosed （ (no matching ）)".to_string())]);
+    }
+
+    #[test]
+    fn dash_lines_are_dialogue_to_the_end_of_the_line() {
+        assert_eq!(
+            kinds("――おはよう、と美咲が返した。\n二人は歩いた。――遠くで鐘が鳴った。"),
+            vec![
+                (SegmentType::Dialogue, "――おはよう、と美咲が返した。".to_string()),
+                (SegmentType::Narrative, "\n二人は歩いた。――遠くで鐘が鳴った。".to_string()),
+            ]
+        );
+    }
+}
@@ -1 +1,2 @@
-pub mod brackets;
\ No newline at end of file
+pub mod brackets;
+pub mod ruby;
@@ -0,0 +1,171 @@
+// ルビと注記の除去
+//
+// 対応する記法:
+//   漢字《かんじ》          直前の漢字の連続が親文字
+//   ｜親文字《おやもじ》    ｜（| も可）から《までが親文字
+//   <ruby>漢字<rt>かんじ</rt></ruby>  <rp>・<rb> も可
+//   ［＃ここから２字下げ］  青空文庫の注記（丸ごと取り除く）
+
+use crate::models::RubyStats;
+use crate::RubyMode;
+
+/// ルビと注記を取り除いたテキスト
+pub struct RubyStripped {
+    pub text: String,
+    pub stats: RubyStats,
+}
+
+/// `mode` に従ってルビと注記を取り除く（`Keep` ならそのまま返す）
+///
+/// 親文字の直前に漢字がない《》は引用符として使われているものとみなして残す。
+pub fn strip_ruby(text: &str, mode: RubyMode) -> RubyStripped {
+    let mut stats = RubyStats::default();
+    if mode == RubyMode::Keep {
+        return RubyStripped {
+            text: text.to_string(),
+            stats,
+        };
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    // ｜で始まった親文字の開始位置（out のバイト位置）
+    let mut explicit_base: Option<usize> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+
+        if (ch == '｜' || ch == '|') && has_ruby_ahead(&chars[i + 1..]) {
+            explicit_base = Some(out.len());
+            i += 1;
+            continue;
+        }
+
+        if ch == '《' {
+            if let Some(close) = find_char(&chars[i + 1..], '》') {
+                let base_start = explicit_base.take().unwrap_or_else(|| kanji_run_start(&out));
+                if base_start < out.len() {
+                    let reading: String = chars[i + 1..i + 1 + close].iter().collect();
+                    apply_ruby(&mut out, base_start, &reading, mode, &mut stats);
+                    i += close + 2;
+                    continue;
+                }
+            }
+        }
+
+        if ch == '［' && chars.get(i + 1) == Some(&'＃') {
+            if let Some(close) = find_char(&chars[i + 2..], '］') {
+                stats.annotations += 1;
+                i += close + 3;
+                continue;
+            }
+        }
+
+        if ch == '<' && starts_with_at(&chars, i, "<ruby>") {
+            if let Some(len) = find_str(&chars[i..], "</ruby>") {
+                let inner: String = chars[i + "<ruby>".len()..i + len].iter().collect();
+                let (base, reading) = split_html_ruby(&inner);
+                let base_start = out.len();
+                out.push_str(&base);
+                apply_ruby(&mut out, base_start, &reading, mode, &mut stats);
+                i += len + "</ruby>".len();
+                continue;
+            }
+        }
+
+        // 改行をまたいだ｜は親文字の指定にならない
+        if ch == '\n' {
+            explicit_base = None;
+        }
+        out.push(ch);
+        i += 1;
+    }
+
+    let total_chars = out.chars().count();
+    stats.ruby_per_1000_chars = if total_chars > 0 {
+        stats.ruby_count as f32 * 1000.0 / total_chars as f32
+    } else {
+        0.0
+    };
+
+    RubyStripped { text: out, stats }
+}
+
+fn apply_ruby(out: &mut String, base_start: usize, reading: &str, mode: RubyMode, stats: &mut RubyStats) {
+    stats.ruby_count += 1;
+    stats.base_chars += out[base_start..].chars().count();
+    stats.reading_chars += reading.chars().count();
+
+    if mode == RubyMode::Reading {
+        out.truncate(base_start);
+        out.push_str(reading);
+    }
+}
+
+/// `<ruby>` の中身を親文字と読みに分ける（`<rp>` は捨て、`<rb>` はタグだけ外す）
+fn split_html_ruby(inner: &str) -> (String, String) {
+    let mut base = String::new();
+    let mut reading = String::new();
+    let mut rest = inner;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<rt>") {
+            let end = after.find("</rt>").unwrap_or(after.len());
+            reading.push_str(&after[..end]);
+            rest = after.get(end + "</rt>".len()..).unwrap_or("");
+        } else if let Some(after) = rest.strip_prefix("<rp>") {
+            let end = after.find("</rp>").unwrap_or(after.len());
+            rest = after.get(end + "</rp>".len()..).unwrap_or("");
+        } else if let Some(after) = rest.strip_prefix("<rb>").or_else(|| rest.strip_prefix("</rb>")) {
+            rest = after;
+        } else {
+            let ch = rest.chars().next().unwrap_or_default();
+            base.push(ch);
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+
+    (base, reading)
+}
+
+/// 親文字として扱う文字（漢字と、々〆ヶヵ などの踊り字・略字）
+fn is_ruby_base(ch: char) -> bool {
+    matches!(ch, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' | '々' | '〆' | 'ヶ' | 'ヵ' | '〇')
+}
+
+/// `out` の末尾に続く漢字の先頭のバイト位置（なければ `out.len()`）
+fn kanji_run_start(out: &str) -> usize {
+    out.char_indices()
+        .rev()
+        .take_while(|(_, ch)| is_ruby_base(*ch))
+        .last()
+        .map(|(index, _)| index)
+        .unwrap_or(out.len())
+}
+
+/// 同じ行のうちに《…》があるか
+fn has_ruby_ahead(chars: &[char]) -> bool {
+    chars
+        .iter()
+        .take_while(|&&ch| ch != '\n')
+        .position(|&ch| ch == '《')
+        .is_some_and(|open| find_char(&chars[open + 1..], '》').is_some())
+}
+
+/// 同じ行の中で `target` を探す
+fn find_char(chars: &[char], target: char) -> Option<usize> {
+    chars
+        .iter()
+        .take_while(|&&ch| ch != '\n')
+        .position(|&ch| ch == target)
+}
+
+fn starts_with_at(chars: &[char], index: usize, pattern: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    chars[index..].starts_with(&pattern)
+}
+
+fn find_str(chars: &[char], pattern: &str) -> Option<usize> {
+    (0..chars.len()).find(|&i| starts_with_at(chars, i, pattern))
+}
@@ -1,21 +1,157 @@
 // Chart visualization module
-// Future: ASCII charts for sentence length distribution, tempo visualization, etc.
+// 端末向けの文字チャートと、SVG 出力が共有する集計
 
-use crate::models::TextProfile;
+use crate::models::{TextProfile, TimelineSegment};
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const DENSITY_LEVELS: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// 文長ヒストグラムの階級幅（これを超える最後の階級はまとめる）
+pub const HISTOGRAM_BIN_WIDTH: usize = 10;
+pub const HISTOGRAM_BINS: usize = 8;
 
 pub struct ChartGenerator;
 
 impl ChartGenerator {
-    pub fn generate_tempo_chart(_profile: &TextProfile) -> String {
-        // TODO: 実装
-        // 文の長さの変化を視覚化
-        // 例: ▁▃▇▃▁▇▃▁
-        String::from("▁▃▇▃▁▇▃▁")
+    /// 文の長さの推移を1行のスパークラインにする
+    ///
+    /// 文の数が幅を超える場合は、隣り合う文をまとめて平均をとる。
+    pub fn generate_tempo_chart(profile: &TextProfile, width: usize) -> String {
+        sparkline(&bucket_means(&profile.rhythm_metrics.sentence_lengths, width))
+    }
+
+    /// 文長の分布を横棒グラフにする（1行に1階級）
+    pub fn generate_distribution_chart(profile: &TextProfile, width: usize) -> String {
+        let bins = length_histogram(&profile.rhythm_metrics.sentence_lengths);
+        let max_count = bins.iter().map(|(_, count)| *count).max().unwrap_or(0);
+        let bar_width = width.saturating_sub(16).max(10);
+
+        bins.iter()
+            .map(|(label, count)| {
+                let filled = if max_count == 0 {
+                    0
+                } else {
+                    (count * bar_width).div_ceil(max_count)
+                };
+                format!("{:>7} {} {}", label, "█".repeat(filled), count)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 本文の先頭から末尾までの会話の密度（濃いほど会話文が多い）
+    pub fn generate_density_strip(profile: &TextProfile, width: usize) -> String {
+        dialogue_density(profile, width)
+            .iter()
+            .map(|&ratio| {
+                let level = (ratio * (DENSITY_LEVELS.len() - 1) as f32).round() as usize;
+                DENSITY_LEVELS[level.min(DENSITY_LEVELS.len() - 1)]
+            })
+            .collect()
+    }
+
+    /// 区間ごとの会話率・平均文長・ひらがな率を、指標ごとに1行のスパークラインにする
+    pub fn generate_timeline_chart(segments: &[TimelineSegment]) -> String {
+        let percent = |v: f32| format!("{:.0}%", v * 100.0);
+        [
+            timeline_row("Dialogue %", segments.iter().map(|s| s.dialogue_ratio), percent),
+            timeline_row("Avg Sentence", segments.iter().map(|s| s.avg_sentence_length), |v| {
+                format!("{:.1}", v)
+            }),
+            timeline_row("Hiragana %", segments.iter().map(|s| s.hiragana_ratio), percent),
+        ]
+        .join("\n")
+    }
+}
+
+/// `ラベル ▁▃▇▅ 最小–最大` の1行
+fn timeline_row(
+    label: &str,
+    values: impl Iterator<Item = f32>,
+    format: impl Fn(f32) -> String,
+) -> String {
+    let values: Vec<f32> = values.collect();
+    let min = values.iter().cloned().fold(f32::MAX, f32::min);
+    let max = values.iter().cloned().fold(f32::MIN, f32::max);
+    format!("{:12} {}  {}–{}", label, sparkline(&values), format(min), format(max))
+}
+
+/// 最小値から最大値までを8段階で表す（全て同じ値なら中ほどの高さで並べる）
+pub fn sparkline(values: &[f32]) -> String {
+    let max = values.iter().cloned().fold(f32::MIN, f32::max);
+    let min = values.iter().cloned().fold(f32::MAX, f32::min);
+
+    values
+        .iter()
+        .map(|&value| {
+            let level = if max > min {
+                ((value - min) / (max - min) * (SPARK_LEVELS.len() - 1) as f32).round() as usize
+            } else {
+                SPARK_LEVELS.len() / 2
+            };
+            SPARK_LEVELS[level.min(SPARK_LEVELS.len() - 1)]
+        })
+        .collect()
+}
+
+/// `values` を最大 `buckets` 個の連続した区間に分け、それぞれの平均を返す
+pub fn bucket_means(values: &[usize], buckets: usize) -> Vec<f32> {
+    if values.is_empty() || buckets == 0 {
+        return Vec::new();
+    }
+
+    let buckets = buckets.min(values.len());
+    (0..buckets)
+        .map(|i| {
+            let start = i * values.len() / buckets;
+            let end = ((i + 1) * values.len() / buckets).max(start + 1);
+            let slice = &values[start..end];
+            slice.iter().sum::<usize>() as f32 / slice.len() as f32
+        })
+        .collect()
+}
+
+/// 文長の度数分布（`1-10`, `11-20`, ... と最後の `71+`）
+pub fn length_histogram(lengths: &[usize]) -> Vec<(String, usize)> {
+    let mut counts = vec![0; HISTOGRAM_BINS];
+    for &len in lengths {
+        let bin = (len.saturating_sub(1) / HISTOGRAM_BIN_WIDTH).min(HISTOGRAM_BINS - 1);
+        counts[bin] += 1;
     }
-    
-    pub fn generate_distribution_chart(_profile: &TextProfile) -> String {
-        // TODO: 実装
-        // 文長の分布を棒グラフで表示
-        String::new()
+
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let low = i * HISTOGRAM_BIN_WIDTH + 1;
+            let label = if i == HISTOGRAM_BINS - 1 {
+                format!("{}+", low)
+            } else {
+                format!("{}-{}", low, low + HISTOGRAM_BIN_WIDTH - 1)
+            };
+            (label, count)
+        })
+        .collect()
+}
+
+/// 本文を `buckets` 等分し、それぞれに占める会話文の割合（0.0〜1.0）を返す
+pub fn dialogue_density(profile: &TextProfile, buckets: usize) -> Vec<f32> {
+    let total = profile.basic_stats.total_chars;
+    if total == 0 || buckets == 0 {
+        return Vec::new();
     }
-}
\ No newline at end of file
+
+    let buckets = buckets.min(total);
+    let spans = &profile.dialogue_stats.dialogue_spans;
+    (0..buckets)
+        .map(|i| {
+            let start = i * total / buckets;
+            let end = ((i + 1) * total / buckets).max(start + 1);
+            let covered: usize = spans
+                .iter()
+                .map(|&(s, e)| e.min(end).saturating_sub(s.max(start)))
+                .sum();
+            covered as f32 / (end - start) as f32
+        })
+        .collect()
+}
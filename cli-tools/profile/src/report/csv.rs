@@ -0,0 +1,343 @@
+use anyhow::Result;
+use std::io;
+use std::path::Path;
+use crate::models::{
+    CorpusProfile, EndingType, LexicalUnit, ProseStyle, TargetAudience, TempoPattern, TextProfile,
+    TimelineSegment,
+};
+
+/// 列の順序と名前は表計算ソフトで履歴を追えるよう固定する（追加は末尾に）
+const COLUMNS: [&str; 58] = [
+    "file",
+    "row",
+    // BasicStats
+    "total_chars",
+    "total_sentences",
+    "total_paragraphs",
+    // CharTypeDistribution
+    "hiragana",
+    "katakana",
+    "kanji",
+    "alphabet",
+    "number",
+    "punctuation",
+    "other",
+    "hiragana_ratio",
+    // DialogueStats
+    "dialogue_chars",
+    "narrative_chars",
+    "dialogue_ratio",
+    "dialogue_count",
+    // RhythmMetrics
+    "avg_sentence_length",
+    "min_sentence_length",
+    "max_sentence_length",
+    "variation_coefficient",
+    "tempo_pattern",
+    // ReadabilityScore
+    "readability_score",
+    "readability_hiragana_ratio",
+    "sentence_complexity",
+    "punctuation_density",
+    "paragraph_structure",
+    // RubyStats
+    "ruby_count",
+    "ruby_per_1000_chars",
+    // EndingStats
+    "ending_ta",
+    "ending_present",
+    "ending_da",
+    "ending_polite",
+    "ending_taigen",
+    "ending_question",
+    "ending_exclamation",
+    "ending_dialogue",
+    "ending_other",
+    "longest_ending_run",
+    "ending_style",
+    "mixed_style_sentences",
+    // RepetitionStats
+    "near_repeats",
+    "crutch_words_total",
+    "crutch_words",
+    "overused_phrases",
+    // KanjiStats / LexicalDiversity
+    "kanji_elementary",
+    "kanji_secondary",
+    "kanji_hyougai",
+    "difficult_kanji_ratio",
+    "lexical_unit",
+    "type_token_ratio",
+    "mattr",
+    "hapax_ratio",
+    "reading_level",
+    // DialogueStats（話者）
+    "thought_chars",
+    "speakers",
+    "unattributed_lines",
+    "speaker_lines",
+];
+
+/// `--window` / `--sections` の列
+const TIMELINE_COLUMNS: [&str; 9] = [
+    "segment",
+    "label",
+    "first_sentence",
+    "last_sentence",
+    "chars",
+    "dialogue_ratio",
+    "avg_sentence_length",
+    "hiragana_ratio",
+    "tempo_pattern",
+];
+
+/// 1セル分の値。差分行では数値同士を引き算し、文字列は空にする
+enum Value {
+    Int(i64),
+    Float(f32),
+    Text(String),
+}
+
+impl Value {
+    fn minus(&self, other: &Value) -> Value {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => Value::Int(a - b),
+            (Value::Float(a), Value::Float(b)) => Value::Float(a - b),
+            _ => Value::Text(String::new()),
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            Value::Int(value) => value.to_string(),
+            Value::Float(value) => format!("{:.4}", value),
+            Value::Text(value) => value.clone(),
+        }
+    }
+}
+
+pub struct CsvReporter;
+
+impl CsvReporter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 解析したファイルごとに1行。比較時は main / comparison / diff（main − comparison）の3行
+    pub fn report(
+        &self,
+        profile: &TextProfile,
+        comparison: Option<(&TextProfile, Option<&Path>)>,
+        input_path: Option<&Path>,
+    ) -> Result<()> {
+        let mut writer = ::csv::Writer::from_writer(io::stdout());
+        writer.write_record(COLUMNS)?;
+
+        let main = metrics(profile);
+        writer.write_record(record(&file_label(input_path), "main", &main))?;
+
+        if let Some((compare, compare_path)) = comparison {
+            let compared = metrics(compare);
+            let diff: Vec<Value> = main
+                .iter()
+                .zip(&compared)
+                .map(|(a, b)| a.minus(b))
+                .collect();
+
+            writer.write_record(record(&file_label(compare_path), "comparison", &compared))?;
+            writer.write_record(record("", "diff", &diff))?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// 区間ごとに1行、最後に章全体（`segment` が `all`）の行
+    pub fn report_timeline(&self, profile: &TextProfile, timeline: &[TimelineSegment]) -> Result<()> {
+        let mut writer = ::csv::Writer::from_writer(io::stdout());
+        writer.write_record(TIMELINE_COLUMNS)?;
+
+        for (i, segment) in timeline.iter().enumerate() {
+            writer.write_record([
+                (i + 1).to_string(),
+                segment.label.clone(),
+                segment.first_sentence.to_string(),
+                segment.last_sentence.to_string(),
+                segment.chars.to_string(),
+                Value::Float(segment.dialogue_ratio).render(),
+                Value::Float(segment.avg_sentence_length).render(),
+                Value::Float(segment.hiragana_ratio).render(),
+                tempo_name(&segment.tempo_pattern).to_string(),
+            ])?;
+        }
+        writer.write_record([
+            String::from("all"),
+            String::new(),
+            String::from("1"),
+            profile.rhythm_metrics.sentence_lengths.len().to_string(),
+            profile.basic_stats.total_chars.to_string(),
+            Value::Float(profile.dialogue_stats.dialogue_ratio).render(),
+            Value::Float(profile.rhythm_metrics.avg_sentence_length).render(),
+            Value::Float(profile.basic_stats.char_types.hiragana_ratio()).render(),
+            tempo_name(&profile.rhythm_metrics.tempo_pattern).to_string(),
+        ])?;
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// コーパスは各ファイル（`file`）、グループ（`group`、file 列はグループ名）、全体（`corpus`）の順
+    pub fn report_corpus(&self, corpus: &CorpusProfile) -> Result<()> {
+        let mut writer = ::csv::Writer::from_writer(io::stdout());
+        writer.write_record(COLUMNS)?;
+
+        for file in &corpus.files {
+            writer.write_record(record(&file.path, "file", &metrics(&file.profile)))?;
+        }
+        for group in &corpus.groups {
+            writer.write_record(record(&group.name, "group", &metrics(&group.profile)))?;
+        }
+        writer.write_record(record("", "corpus", &metrics(&corpus.aggregate)))?;
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn file_label(path: Option<&Path>) -> String {
+    path.map(|p| p.display().to_string())
+        .unwrap_or_else(|| String::from("stdin"))
+}
+
+fn record(file: &str, row: &str, values: &[Value]) -> Vec<String> {
+    let mut record = vec![file.to_string(), row.to_string()];
+    record.extend(values.iter().map(Value::render));
+    record
+}
+
+/// `COLUMNS` の3列目以降と同じ順序で値を並べる
+fn metrics(profile: &TextProfile) -> Vec<Value> {
+    let stats = &profile.basic_stats;
+    let types = &stats.char_types;
+    let dialogue = &profile.dialogue_stats;
+    let rhythm = &profile.rhythm_metrics;
+    let readability = &profile.readability;
+
+    let count = |n: usize| Value::Int(n as i64);
+
+    let mut values = vec![
+        count(stats.total_chars),
+        count(stats.total_sentences),
+        count(stats.total_paragraphs),
+        count(types.hiragana),
+        count(types.katakana),
+        count(types.kanji),
+        count(types.alphabet),
+        count(types.number),
+        count(types.punctuation),
+        count(types.other),
+        Value::Float(types.hiragana_ratio()),
+        count(dialogue.dialogue_chars),
+        count(dialogue.narrative_chars),
+        Value::Float(dialogue.dialogue_ratio),
+        count(dialogue.dialogue_count),
+        Value::Float(rhythm.avg_sentence_length),
+        count(rhythm.sentence_lengths.iter().copied().min().unwrap_or(0)),
+        count(rhythm.sentence_lengths.iter().copied().max().unwrap_or(0)),
+        Value::Float(rhythm.variation_coefficient),
+        Value::Text(tempo_name(&rhythm.tempo_pattern).to_string()),
+        Value::Float(readability.score),
+        Value::Float(readability.factors.hiragana_ratio),
+        Value::Float(readability.factors.sentence_complexity),
+        Value::Float(readability.factors.punctuation_density),
+        Value::Float(readability.factors.paragraph_structure),
+        count(profile.ruby_stats.ruby_count),
+        Value::Float(profile.ruby_stats.ruby_per_1000_chars),
+    ];
+    let endings = &profile.ending_stats;
+    values.extend(
+        EndingType::ALL
+            .iter()
+            .map(|ending| count(endings.counts.get(ending).copied().unwrap_or(0))),
+    );
+    values.push(count(endings.longest_run.as_ref().map_or(0, |run| run.length)));
+    values.push(Value::Text(match endings.style {
+        Some(ProseStyle::Plain) => String::from("plain"),
+        Some(ProseStyle::Polite) => String::from("polite"),
+        None => String::new(),
+    }));
+    values.push(count(endings.mixed_style_sentences.len()));
+    // 語句は「ふと:3;少し:5」の形で1セルに並べる
+    let repetition = &profile.repetition_stats;
+    values.push(count(repetition.near_repeats.len()));
+    values.push(count(repetition.crutch_words.iter().map(|word| word.count).sum()));
+    values.push(Value::Text(
+        repetition
+            .crutch_words
+            .iter()
+            .map(|word| format!("{}:{}", word.phrase, word.count))
+            .collect::<Vec<_>>()
+            .join(";"),
+    ));
+    values.push(Value::Text(
+        repetition
+            .overused
+            .iter()
+            .map(|phrase| format!("{}:{}", phrase.phrase, phrase.count))
+            .collect::<Vec<_>>()
+            .join(";"),
+    ));
+    let kanji = &profile.kanji_stats;
+    let lexical = &profile.lexical_diversity;
+    values.extend([
+        count(kanji.elementary()),
+        count(kanji.secondary),
+        count(kanji.hyougai),
+        Value::Float(readability.factors.difficult_kanji_ratio),
+        Value::Text(
+            match lexical.unit {
+                LexicalUnit::CharBigram => "char_bigram",
+                LexicalUnit::Token => "token",
+            }
+            .to_string(),
+        ),
+        Value::Float(lexical.type_token_ratio),
+        Value::Float(lexical.mattr),
+        Value::Float(lexical.hapax_ratio),
+        Value::Text(
+            match readability.audience {
+                TargetAudience::Children => "children",
+                TargetAudience::Teen => "teen",
+                TargetAudience::General => "general",
+                TargetAudience::Literary => "literary",
+                TargetAudience::Advanced => "advanced",
+            }
+            .to_string(),
+        ),
+    ]);
+    values.extend([
+        count(dialogue.thought_chars),
+        count(dialogue.speakers.len()),
+        count(dialogue.unattributed_lines),
+        Value::Text(
+            dialogue
+                .speakers
+                .iter()
+                .map(|speaker| format!("{}:{}", speaker.name, speaker.lines))
+                .collect::<Vec<_>>()
+                .join(";"),
+        ),
+    ]);
+    debug_assert_eq!(values.len() + 2, COLUMNS.len());
+    values
+}
+
+fn tempo_name(pattern: &TempoPattern) -> &'static str {
+    match pattern {
+        TempoPattern::Steady => "steady",
+        TempoPattern::Varied => "varied",
+        TempoPattern::Accelerating => "accelerating",
+        TempoPattern::Decelerating => "decelerating",
+        TempoPattern::Alternating => "alternating",
+    }
+}
@@ -1,8 +1,12 @@
 use anyhow::Result;
 use colored::*;
-use comfy_table::{Table, Cell, Attribute, Color as TableColor};
+use comfy_table::{Table, Cell};
 use std::path::Path;
-use crate::models::{TextProfile, TempoPattern};
+use crate::models::{
+    CorpusMetric, CorpusProfile, EndingType, ProseStyle, TextProfile, TempoPattern, TimelineSegment,
+};
+use crate::analyzer::{MATTR_WINDOW, MIN_BASELINE_FILES, MIN_REPORTED_RUN};
+use super::chart::ChartGenerator;
 
 pub struct TerminalReporter {
     verbose: bool,
@@ -32,6 +36,149 @@ impl TerminalReporter {
         Ok(())
     }
     
+    pub fn report_timeline(
+        &self,
+        profile: &TextProfile,
+        timeline: &[TimelineSegment],
+        input_path: Option<&Path>,
+    ) -> Result<()> {
+        self.print_header(input_path);
+        println!("{} Timeline ({} segments)", "🕒".blue(), timeline.len());
+        
+        let mut table = Table::new();
+        table.set_header(vec![
+            Cell::new("Segment"),
+            Cell::new("Sentences"),
+            Cell::new("Chars"),
+            Cell::new("Dialogue %"),
+            Cell::new("Avg Sentence"),
+            Cell::new("Hiragana %"),
+            Cell::new("Tempo"),
+        ]);
+        for segment in timeline {
+            table.add_row(vec![
+                Cell::new(&segment.label),
+                Cell::new(format!("{}-{}", segment.first_sentence, segment.last_sentence)),
+                Cell::new(segment.chars),
+                Cell::new(format!("{:.1}%", segment.dialogue_ratio * 100.0)),
+                Cell::new(format!("{:.1}", segment.avg_sentence_length)),
+                Cell::new(format!("{:.1}%", segment.hiragana_ratio * 100.0)),
+                Cell::new(tempo_label(&segment.tempo_pattern)),
+            ]);
+        }
+        // 章全体を最後の行に並べて、区間ごとの値と見比べられるようにする
+        table.add_row(vec![
+            Cell::new("All".bold().to_string()),
+            Cell::new(format!("1-{}", profile.rhythm_metrics.sentence_lengths.len())),
+            Cell::new(profile.basic_stats.total_chars),
+            Cell::new(format!("{:.1}%", profile.dialogue_stats.dialogue_ratio * 100.0)),
+            Cell::new(format!("{:.1}", profile.rhythm_metrics.avg_sentence_length)),
+            Cell::new(format!("{:.1}%", profile.basic_stats.char_types.hiragana_ratio() * 100.0)),
+            Cell::new(tempo_label(&profile.rhythm_metrics.tempo_pattern)),
+        ]);
+        println!("{}", table);
+        
+        if self.chart && !timeline.is_empty() {
+            println!();
+            for line in ChartGenerator::generate_timeline_chart(timeline).lines() {
+                println!("  {}", line);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    pub fn report_corpus(&self, corpus: &CorpusProfile) -> Result<()> {
+        println!(
+            "\n{} Corpus Profile: {} files",
+            "📚".blue(),
+            corpus.files.len().to_string().cyan()
+        );
+        println!("{}", "━".repeat(50).dimmed());
+        
+        // ファイルごとの表（外れ値のセルは赤く !）
+        let mut table = Table::new();
+        let mut header = vec![Cell::new("File"), Cell::new("Chars"), Cell::new("Sentences")];
+        header.extend(CorpusMetric::ALL.iter().map(|metric| Cell::new(metric.label())));
+        table.set_header(header);
+        
+        for file in &corpus.files {
+            let mut row = vec![
+                Cell::new(&file.path),
+                Cell::new(file.profile.basic_stats.total_chars),
+                Cell::new(file.profile.basic_stats.total_sentences),
+            ];
+            row.extend(CorpusMetric::ALL.iter().map(|metric| {
+                let text = metric.format(metric.value(&file.profile));
+                let is_outlier = corpus
+                    .outliers
+                    .iter()
+                    .any(|outlier| outlier.path == file.path && outlier.metric == *metric);
+                if is_outlier {
+                    Cell::new(format!("{} !", text).red().to_string())
+                } else {
+                    Cell::new(text)
+                }
+            }));
+            table.add_row(row);
+        }
+        println!("{}", table);
+        
+        if !corpus.groups.is_empty() {
+            println!();
+            println!("{} Groups", "🗂".blue());
+            let mut table = Table::new();
+            let mut header = vec![Cell::new("Group"), Cell::new("Files"), Cell::new("Chars")];
+            header.extend(CorpusMetric::ALL.iter().map(|metric| Cell::new(metric.label())));
+            table.set_header(header);
+            
+            for group in &corpus.groups {
+                let mut row = vec![
+                    Cell::new(&group.name),
+                    Cell::new(group.files),
+                    Cell::new(group.profile.basic_stats.total_chars),
+                ];
+                row.extend(
+                    CorpusMetric::ALL
+                        .iter()
+                        .map(|metric| Cell::new(metric.format(metric.value(&group.profile)))),
+                );
+                table.add_row(row);
+            }
+            println!("{}", table);
+        }
+        
+        println!();
+        println!("{} Outliers", "🔍".blue());
+        if corpus.outliers.is_empty() {
+            if corpus.files.len() <= MIN_BASELINE_FILES {
+                let note = format!("(needs at least {} files to compare against)", MIN_BASELINE_FILES + 1);
+                println!("  {}", note.dimmed());
+            } else {
+                println!("  {}", "None — every file stays close to the rest of the corpus".green());
+            }
+        }
+        for outlier in &corpus.outliers {
+            println!(
+                "  {} {}: {} {} (others: {}, z = {:+.1})",
+                "⚠".yellow(),
+                outlier.path,
+                outlier.metric.label(),
+                outlier.metric.format(outlier.value).red(),
+                outlier.metric.format(outlier.baseline),
+                outlier.z_score
+            );
+        }
+        
+        // 全体を1本のテキストとして見たプロファイル
+        println!();
+        println!("{} Corpus (all files)", "📊".blue());
+        println!("{}", "━".repeat(50).dimmed());
+        self.print_single(&corpus.aggregate);
+        
+        Ok(())
+    }
+    
     fn print_header(&self, path: Option<&Path>) {
         let filename = path
             .and_then(|p| p.file_name())
@@ -54,12 +201,19 @@ impl TerminalReporter {
         
         // 文体バランス
         println!("{} Style Balance", "📝".blue());
-        let dialogue_percent = (profile.dialogue_stats.dialogue_ratio * 100.0) as usize;
-        let narrative_percent = 100 - dialogue_percent;
+        let dialogue = &profile.dialogue_stats;
+        let dialogue_percent = (dialogue.dialogue_ratio * 100.0) as usize;
+        let thought_percent = dialogue.thought_chars * 100 / profile.basic_stats.total_chars.max(1);
+        let narrative_percent = 100usize.saturating_sub(dialogue_percent + thought_percent);
         
         self.print_bar("Dialogue", dialogue_percent, "█", "░");
+        if dialogue.thought_count > 0 {
+            self.print_bar("Thought", thought_percent, "█", "░");
+        }
         self.print_bar("Narrative", narrative_percent, "█", "░");
         
+        self.print_speakers(profile);
+        
         println!();
         
         // 文のリズム
@@ -78,14 +232,44 @@ impl TerminalReporter {
         );
         
         // テンポパターン
-        let tempo_desc = match profile.rhythm_metrics.tempo_pattern {
-            TempoPattern::Steady => "Steady",
-            TempoPattern::Varied => "Varied",
-            TempoPattern::Accelerating => "Accelerating",
-            TempoPattern::Decelerating => "Decelerating",
-            TempoPattern::Alternating => "Alternating",
-        };
-        println!("  Tempo: {}", tempo_desc.green());
+        println!("  Tempo: {}", tempo_label(&profile.rhythm_metrics.tempo_pattern).green());
+        
+        let readability = &profile.readability;
+        println!(
+            "  Score: {} ({})",
+            format!("{:.1}", readability.score).cyan(),
+            readability.audience.label().green()
+        );
+        let kanji = &profile.kanji_stats;
+        if kanji.total > 0 {
+            println!(
+                "  Kanji: 小学 {:.1}% | 中学 {:.1}% | 表外 {:.1}%",
+                kanji.ratio(kanji.elementary()) * 100.0,
+                kanji.ratio(kanji.secondary) * 100.0,
+                kanji.ratio(kanji.hyougai) * 100.0
+            );
+        }
+        let lexical = &profile.lexical_diversity;
+        println!(
+            "  Vocabulary: MATTR {} ({}, window {})",
+            format!("{:.3}", lexical.mattr).cyan(),
+            lexical.unit.label(),
+            MATTR_WINDOW
+        );
+        
+        // ルビ（振り仮名）の量
+        let ruby = &profile.ruby_stats;
+        if ruby.ruby_count > 0 {
+            println!(
+                "  Ruby: {} ({} per 1,000 chars, {} base chars)",
+                ruby.ruby_count.to_string().cyan(),
+                format!("{:.1}", ruby.ruby_per_1000_chars).cyan(),
+                ruby.base_chars
+            );
+        }
+        
+        self.print_endings(profile);
+        self.print_repetition(profile);
         
         // 詳細モード
         if self.verbose {
@@ -142,7 +326,210 @@ impl TerminalReporter {
             format_diff_percent(hiragana_diff),
         ]);
         
+        // 読みやすさ
+        let readability_diff = profile.readability.score - compare.readability.score;
+        table.add_row(vec![
+            Cell::new("Readability"),
+            Cell::new(format!("{:.1} ({})", profile.readability.score, profile.readability.audience.label())),
+            Cell::new(format!("{:.1} ({})", compare.readability.score, compare.readability.audience.label())),
+            format_diff_float(readability_diff),
+        ]);
+        
+        // 小学校で習わない漢字の割合
+        let kanji_diff = (profile.readability.factors.difficult_kanji_ratio
+            - compare.readability.factors.difficult_kanji_ratio) * 100.0;
+        table.add_row(vec![
+            Cell::new("Difficult Kanji %"),
+            Cell::new(format!("{:.1}%", profile.readability.factors.difficult_kanji_ratio * 100.0)),
+            Cell::new(format!("{:.1}%", compare.readability.factors.difficult_kanji_ratio * 100.0)),
+            format_diff_percent(kanji_diff),
+        ]);
+        
+        // 語彙の豊かさ（単位が違うと比べられないので、そのときは差を出さない）
+        let (lexical, compare_lexical) = (&profile.lexical_diversity, &compare.lexical_diversity);
+        table.add_row(vec![
+            Cell::new("MATTR"),
+            Cell::new(format!("{:.3}", lexical.mattr)),
+            Cell::new(format!("{:.3}", compare_lexical.mattr)),
+            if lexical.unit == compare_lexical.unit {
+                format_diff_float(lexical.mattr - compare_lexical.mattr)
+            } else {
+                Cell::new("-")
+            },
+        ]);
+        
         println!("{}", table);
+
+        if self.chart {
+            println!();
+            println!("{}", "Your Text".bold());
+            self.print_rhythm_chart(profile);
+            println!("{}", "Reference".bold());
+            self.print_rhythm_chart(compare);
+        }
+    }
+    
+    fn print_endings(&self, profile: &TextProfile) {
+        let endings = &profile.ending_stats;
+        let total: usize = endings.counts.values().sum();
+        if total == 0 {
+            return;
+        }
+        
+        println!();
+        println!("{} Sentence Endings", "🔚".blue());
+        let mut counts: Vec<(&EndingType, &usize)> = endings.counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1));
+        for (ending, count) in counts {
+            let percent = count * 100 / total;
+            let filled = (percent * 20 / 100).min(20);
+            // 全角のラベルでも棒の位置がそろうよう表示幅で詰める
+            let padding = 10usize.saturating_sub(display_width(ending.label()));
+            println!(
+                "  {}{} {:3}% {}{} {}",
+                ending.label(),
+                " ".repeat(padding),
+                percent,
+                "█".repeat(filled).green(),
+                "░".repeat(20 - filled).dimmed(),
+                count
+            );
+        }
+        
+        if let Some(run) = &endings.longest_run {
+            println!(
+                "  Longest run: {} × {} (sentences {}-{})",
+                run.ending.label().yellow(),
+                run.length,
+                run.first_sentence,
+                run.last_sentence
+            );
+        }
+        if !endings.runs.is_empty() {
+            let runs: Vec<String> = endings
+                .runs
+                .iter()
+                .map(|run| format!("{}×{} @{}", run.ending.label(), run.length, run.first_sentence))
+                .collect();
+            println!("  Runs of {}+: {}", MIN_REPORTED_RUN, runs.join(", ").yellow());
+        }
+        
+        if let Some(style) = endings.style {
+            println!("  Style: {}", style.label().green());
+            if !endings.mixed_style_sentences.is_empty() {
+                let other = match style {
+                    ProseStyle::Plain => ProseStyle::Polite,
+                    ProseStyle::Polite => ProseStyle::Plain,
+                };
+                let sentences: Vec<String> = endings
+                    .mixed_style_sentences
+                    .iter()
+                    .take(10)
+                    .map(|n| n.to_string())
+                    .collect();
+                let more = if endings.mixed_style_sentences.len() > 10 { ", ..." } else { "" };
+                println!(
+                    "  {} {} endings mixed in: sentences {}{}",
+                    "⚠".yellow(),
+                    other.label(),
+                    sentences.join(", "),
+                    more
+                );
+            }
+        }
+    }
+    
+    /// 話者ごとの台詞の数・平均の長さ・会話に占める割合（詳細モードでなければ上位だけ）
+    fn print_speakers(&self, profile: &TextProfile) {
+        let dialogue = &profile.dialogue_stats;
+        if dialogue.speakers.is_empty() {
+            return;
+        }
+        let limit = if self.verbose { usize::MAX } else { 8 };
+        
+        println!();
+        println!("{} Speakers", "🗣".blue());
+        let width = dialogue
+            .speakers
+            .iter()
+            .take(limit)
+            .map(|speaker| display_width(&speaker.name))
+            .max()
+            .unwrap_or(0);
+        for speaker in dialogue.speakers.iter().take(limit) {
+            let percent = (speaker.share * 100.0).round() as usize;
+            let filled = (percent * 20 / 100).min(20);
+            println!(
+                "  {}{} {:3}% {}{} {} lines, avg {:.1} chars",
+                speaker.name,
+                " ".repeat(width - display_width(&speaker.name)),
+                percent,
+                "█".repeat(filled).green(),
+                "░".repeat(20 - filled).dimmed(),
+                speaker.lines,
+                speaker.avg_utterance_length
+            );
+        }
+        if dialogue.speakers.len() > limit {
+            println!("  {}", format!("... {} more (--verbose for all)", dialogue.speakers.len() - limit).dimmed());
+        }
+        if dialogue.unattributed_lines > 0 {
+            println!("  {}", format!("Unattributed: {} lines", dialogue.unattributed_lines).dimmed());
+        }
+    }
+    
+    /// 近接した繰り返し・口癖・多用語句（詳細モードでなければ上位だけ）
+    fn print_repetition(&self, profile: &TextProfile) {
+        let repetition = &profile.repetition_stats;
+        if repetition.crutch_words.is_empty()
+            && repetition.near_repeats.is_empty()
+            && repetition.overused.is_empty()
+        {
+            return;
+        }
+        let limit = if self.verbose { usize::MAX } else { 5 };
+        
+        println!();
+        println!("{} Repetition", "🔁".blue());
+        
+        if !repetition.near_repeats.is_empty() {
+            println!("  Near repeats: {}", repetition.near_repeats.len().to_string().yellow());
+            for repeat in repetition.near_repeats.iter().take(limit) {
+                println!(
+                    "    {} ×{} (sentences {})",
+                    repeat.phrase.yellow(),
+                    repeat.count,
+                    sentence_list(&repeat.sentences)
+                );
+            }
+            if repetition.near_repeats.len() > limit {
+                println!("    {}", "... (--verbose for all)".dimmed());
+            }
+        }
+        
+        if !repetition.crutch_words.is_empty() {
+            let words: Vec<String> = repetition
+                .crutch_words
+                .iter()
+                .map(|word| format!("{} {}", word.phrase, word.count))
+                .collect();
+            println!("  Crutch words: {}", words.join(", ").yellow());
+            if self.verbose {
+                for word in &repetition.crutch_words {
+                    println!("    {}: sentences {}", word.phrase, sentence_list(&word.sentences));
+                }
+            }
+        }
+        
+        if !repetition.overused.is_empty() {
+            let phrases: Vec<String> = repetition
+                .overused
+                .iter()
+                .take(limit)
+                .map(|phrase| format!("{} {} ({:.1}/10k)", phrase.phrase, phrase.count, phrase.per_10k_chars))
+                .collect();
+            println!("  Overused: {}", phrases.join(", "));
+        }
     }
     
     fn print_bar(&self, label: &str, percent: usize, filled: &str, empty: &str) {
@@ -160,34 +547,27 @@ impl TerminalReporter {
     }
     
     fn print_rhythm_chart(&self, profile: &TextProfile) {
+        let width = chart_width();
+
         println!("{} Sentence Rhythm", "📈".blue());
-        
-        // 文長を範囲ごとに集計
-        let mut short = 0;  // 1-20
-        let mut medium = 0; // 21-40
-        let mut long = 0;   // 41-60
-        let mut very_long = 0; // 61+
-        
-        for &len in &profile.rhythm_metrics.sentence_lengths {
-            match len {
-                1..=20 => short += 1,
-                21..=40 => medium += 1,
-                41..=60 => long += 1,
-                _ => very_long += 1,
-            }
+        let tempo = ChartGenerator::generate_tempo_chart(profile, width);
+        if tempo.is_empty() {
+            println!("  {}", "(no sentences)".dimmed());
+        } else {
+            println!("  {}", tempo.cyan());
         }
-        
-        self.print_histogram("Short (1-20)", short);
-        self.print_histogram("Medium (21-40)", medium);
-        self.print_histogram("Long (41-60)", long);
-        self.print_histogram("Very Long (61+)", very_long);
-        
         println!();
-    }
-    
-    fn print_histogram(&self, label: &str, count: usize) {
-        let bar = "█".repeat(count.min(20)).cyan();
-        println!("  {:15} {} {}", label, bar, count);
+
+        println!("{} Sentence Length", "📊".blue());
+        for line in ChartGenerator::generate_distribution_chart(profile, width).lines() {
+            println!("  {}", line);
+        }
+        println!();
+
+        println!("{} Dialogue Density", "💬".blue());
+        println!("  {}", ChartGenerator::generate_density_strip(profile, width).green());
+        println!("  {}", "start → end (· narrative … █ dialogue)".dimmed());
+        println!();
     }
     
     fn print_detailed_stats(&self, profile: &TextProfile) {
@@ -196,7 +576,9 @@ impl TerminalReporter {
         println!("{}", "─".repeat(30).dimmed());
         
         println!("Paragraphs: {}", profile.basic_stats.total_paragraphs);
+        println!("Avg paragraph: {:.1} chars", profile.readability.factors.paragraph_structure);
         println!("Dialogue segments: {}", profile.dialogue_stats.dialogue_count);
+        println!("Thought segments: {}", profile.dialogue_stats.thought_count);
         println!("Variation coefficient: {:.2}", profile.rhythm_metrics.variation_coefficient);
         
         // 文字種詳細
@@ -209,6 +591,43 @@ impl TerminalReporter {
         println!("  Alphabet: {}", types.alphabet);
         println!("  Number: {}", types.number);
         println!("  Punctuation: {}", types.punctuation);
+        
+        let kanji = &profile.kanji_stats;
+        if kanji.total > 0 {
+            println!();
+            println!("Kanji Grades:");
+            for (grade, count) in kanji.by_grade.iter().enumerate() {
+                println!("  小学{}年: {} ({:.1}%)", grade + 1, count, kanji.ratio(*count) * 100.0);
+            }
+            println!("  中学以降: {} ({:.1}%)", kanji.secondary, kanji.ratio(kanji.secondary) * 100.0);
+            println!("  表外: {} ({:.1}%)", kanji.hyougai, kanji.ratio(kanji.hyougai) * 100.0);
+            if !kanji.hyougai_kanji.is_empty() {
+                let shown: Vec<String> = kanji
+                    .hyougai_kanji
+                    .iter()
+                    .take(20)
+                    .map(|k| format!("{}{}", k.kanji, k.count))
+                    .collect();
+                println!("  表外字: {}", shown.join(" "));
+            }
+        }
+        
+        let lexical = &profile.lexical_diversity;
+        println!();
+        println!("Vocabulary ({}):", lexical.unit.label());
+        println!("  Tokens: {} / Types: {}", lexical.tokens, lexical.types);
+        println!("  TTR: {:.3}", lexical.type_token_ratio);
+        println!("  Hapax: {:.1}%", lexical.hapax_ratio * 100.0);
+        
+        let ruby = &profile.ruby_stats;
+        if ruby.ruby_count > 0 || ruby.annotations > 0 {
+            println!();
+            println!("Ruby:");
+            println!("  Count: {}", ruby.ruby_count);
+            println!("  Base chars: {}", ruby.base_chars);
+            println!("  Reading chars: {}", ruby.reading_chars);
+            println!("  Annotations removed: {}", ruby.annotations);
+        }
     }
 }
 
@@ -243,4 +662,34 @@ fn format_diff_float(diff: f32) -> Cell {
         "≈".dimmed().to_string()
     };
     Cell::new(text)
-}
\ No newline at end of file
+}
+
+/// 端末での表示幅（全角文字は2桁）
+/// 文番号を10個まで並べる
+fn sentence_list(sentences: &[usize]) -> String {
+    let shown: Vec<String> = sentences.iter().take(10).map(|n| n.to_string()).collect();
+    let more = if sentences.len() > 10 { ", ..." } else { "" };
+    format!("{}{}", shown.join(", "), more)
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+fn tempo_label(pattern: &TempoPattern) -> &'static str {
+    match pattern {
+        TempoPattern::Steady => "Steady",
+        TempoPattern::Varied => "Varied",
+        TempoPattern::Accelerating => "Accelerating",
+        TempoPattern::Decelerating => "Decelerating",
+        TempoPattern::Alternating => "Alternating",
+    }
+}
+
+/// 端末の幅に合わせたチャートの幅（取得できなければ 80 桁とみなす）
+fn chart_width() -> usize {
+    let columns = terminal_size::terminal_size()
+        .map(|(terminal_size::Width(w), _)| w as usize)
+        .unwrap_or(80);
+    columns.saturating_sub(4).clamp(20, 100)
+}
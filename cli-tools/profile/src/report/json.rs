@@ -1,6 +1,5 @@
 use anyhow::Result;
-use serde_json;
-use crate::models::TextProfile;
+use crate::models::{CorpusProfile, TextProfile, TimelineSegment};
 
 pub struct JsonReporter;
 
@@ -28,4 +27,18 @@ impl JsonReporter {
         
         Ok(())
     }
-}
\ No newline at end of file
+    
+    pub fn report_timeline(&self, profile: &TextProfile, timeline: &[TimelineSegment]) -> Result<()> {
+        let output = serde_json::json!({
+            "profile": profile,
+            "timeline": timeline,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        Ok(())
+    }
+    
+    pub fn report_corpus(&self, corpus: &CorpusProfile) -> Result<()> {
+        println!("{}", serde_json::to_string_pretty(corpus)?);
+        Ok(())
+    }
+}
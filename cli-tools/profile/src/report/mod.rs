@@ -1,13 +1,18 @@
 use anyhow::Result;
 use std::path::Path;
-use crate::models::TextProfile;
+use crate::models::{CorpusProfile, TextProfile, TimelineSegment};
 
 mod display;
 mod json;
+mod csv;
 mod chart;
+mod svg;
 
 use self::display::TerminalReporter;
 use self::json::JsonReporter;
+use self::csv::CsvReporter;
+
+pub use self::svg::write_svg;
 
 #[derive(Clone)]
 pub struct Reporter {
@@ -30,6 +35,7 @@ impl Reporter {
         profile: &TextProfile,
         comparison: Option<&TextProfile>,
         input_path: Option<&Path>,
+        compare_path: Option<&Path>,
     ) -> Result<()> {
         match self.format {
             crate::OutputFormat::Terminal => {
@@ -41,11 +47,33 @@ impl Reporter {
                 reporter.report(profile, comparison)
             }
             crate::OutputFormat::Csv => {
-                // TODO: CSV出力実装
-                eprintln!("CSV format not yet implemented, falling back to JSON");
-                let reporter = JsonReporter::new();
-                reporter.report(profile, comparison)
+                let reporter = CsvReporter::new();
+                reporter.report(profile, comparison.map(|c| (c, compare_path)), input_path)
+            }
+        }
+    }
+    
+    pub fn report_timeline(
+        &self,
+        profile: &TextProfile,
+        timeline: &[TimelineSegment],
+        input_path: Option<&Path>,
+    ) -> Result<()> {
+        match self.format {
+            crate::OutputFormat::Terminal => TerminalReporter::new(self.verbose, self.chart)
+                .report_timeline(profile, timeline, input_path),
+            crate::OutputFormat::Json => JsonReporter::new().report_timeline(profile, timeline),
+            crate::OutputFormat::Csv => CsvReporter::new().report_timeline(profile, timeline),
+        }
+    }
+    
+    pub fn report_corpus(&self, corpus: &CorpusProfile) -> Result<()> {
+        match self.format {
+            crate::OutputFormat::Terminal => {
+                TerminalReporter::new(self.verbose, self.chart).report_corpus(corpus)
             }
+            crate::OutputFormat::Json => JsonReporter::new().report_corpus(corpus),
+            crate::OutputFormat::Csv => CsvReporter::new().report_corpus(corpus),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use crate::models::TextProfile;
+use super::chart::{bucket_means, dialogue_density, length_histogram};
+
+const WIDTH: f32 = 640.0;
+const MARGIN: f32 = 20.0;
+const TEMPO_HEIGHT: f32 = 80.0;
+const HISTOGRAM_HEIGHT: f32 = 120.0;
+const DENSITY_HEIGHT: f32 = 16.0;
+/// 1ファイル分（見出し・3つのチャート・余白）の高さ
+const SECTION_HEIGHT: f32 = 24.0 + 18.0 + TEMPO_HEIGHT + 18.0 + HISTOGRAM_HEIGHT + 36.0 + DENSITY_HEIGHT + 24.0;
+/// スパークラインと密度帯の分割数
+const BUCKETS: usize = 120;
+
+/// `--chart` と同じ3つのチャートを SVG に書き出す（ファイルごとに縦に並べる）
+pub fn write_svg(path: &Path, profiles: &[(String, &TextProfile)]) -> Result<()> {
+    let height = MARGIN * 2.0 + SECTION_HEIGHT * profiles.len() as f32;
+    let mut svg = String::new();
+
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{height}" viewBox="0 0 {WIDTH} {height}" font-family="sans-serif" font-size="12">"#
+    )?;
+    writeln!(svg, r##"<rect width="100%" height="100%" fill="#ffffff"/>"##)?;
+
+    for (i, (label, profile)) in profiles.iter().enumerate() {
+        let top = MARGIN + SECTION_HEIGHT * i as f32;
+        section(&mut svg, label, profile, top)?;
+    }
+
+    writeln!(svg, "</svg>")?;
+    fs::write(path, svg).with_context(|| format!("Failed to write SVG: {}", path.display()))
+}
+
+fn section(svg: &mut String, label: &str, profile: &TextProfile, top: f32) -> Result<()> {
+    let inner = WIDTH - MARGIN * 2.0;
+    let mut y = top;
+
+    writeln!(
+        svg,
+        r##"<text x="{MARGIN}" y="{}" font-size="16" font-weight="bold" fill="#2c3e50">{}</text>"##,
+        y + 16.0,
+        escape(label)
+    )?;
+    y += 24.0;
+
+    // 文長の推移
+    caption(svg, y, "Sentence rhythm")?;
+    y += 18.0;
+    let means = bucket_means(&profile.rhythm_metrics.sentence_lengths, BUCKETS);
+    let max = means.iter().cloned().fold(0.0_f32, f32::max).max(1.0);
+    let step = if means.len() > 1 { inner / (means.len() - 1) as f32 } else { 0.0 };
+    let points: Vec<String> = means
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let x = MARGIN + step * i as f32;
+            let py = y + TEMPO_HEIGHT - value / max * TEMPO_HEIGHT;
+            format!("{:.1},{:.1}", x, py)
+        })
+        .collect();
+    writeln!(
+        svg,
+        r##"<rect x="{MARGIN}" y="{y}" width="{inner}" height="{TEMPO_HEIGHT}" fill="#f8f9fa"/>"##
+    )?;
+    if !points.is_empty() {
+        writeln!(
+            svg,
+            r##"<polyline points="{}" fill="none" stroke="#3498db" stroke-width="1.5"/>"##,
+            points.join(" ")
+        )?;
+    }
+    y += TEMPO_HEIGHT + 18.0;
+
+    // 文長の分布
+    caption(svg, y, "Sentence length")?;
+    let bins = length_histogram(&profile.rhythm_metrics.sentence_lengths);
+    let max_count = bins.iter().map(|(_, count)| *count).max().unwrap_or(0).max(1);
+    let slot = inner / bins.len() as f32;
+    let chart_bottom = y + HISTOGRAM_HEIGHT - 14.0;
+    for (i, (bin, count)) in bins.iter().enumerate() {
+        let bar_height = *count as f32 / max_count as f32 * (HISTOGRAM_HEIGHT - 40.0);
+        let x = MARGIN + slot * i as f32;
+        writeln!(
+            svg,
+            r##"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="#1abc9c"/>"##,
+            x + 4.0,
+            chart_bottom - bar_height,
+            slot - 8.0,
+            bar_height
+        )?;
+        writeln!(
+            svg,
+            r##"<text x="{:.1}" y="{:.1}" text-anchor="middle" fill="#333">{}</text>"##,
+            x + slot / 2.0,
+            chart_bottom - bar_height - 3.0,
+            count
+        )?;
+        writeln!(
+            svg,
+            r##"<text x="{:.1}" y="{:.1}" text-anchor="middle" fill="#7f8c8d">{}</text>"##,
+            x + slot / 2.0,
+            chart_bottom + 12.0,
+            bin
+        )?;
+    }
+    y += HISTOGRAM_HEIGHT + 18.0;
+
+    // 会話の密度（濃いほど会話文が多い）
+    caption(svg, y, "Dialogue density (start → end)")?;
+    y += 18.0;
+    let density = dialogue_density(profile, BUCKETS);
+    let cell = inner / density.len().max(1) as f32;
+    for (i, ratio) in density.iter().enumerate() {
+        writeln!(
+            svg,
+            r##"<rect x="{:.2}" y="{y}" width="{:.2}" height="{DENSITY_HEIGHT}" fill="#e67e22" fill-opacity="{:.2}"/>"##,
+            MARGIN + cell * i as f32,
+            cell,
+            0.08 + ratio * 0.92
+        )?;
+    }
+
+    Ok(())
+}
+
+fn caption(svg: &mut String, y: f32, text: &str) -> Result<()> {
+    writeln!(
+        svg,
+        r##"<text x="{MARGIN}" y="{}" fill="#555">{}</text>"##,
+        y + 12.0,
+        escape(text)
+    )?;
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}